@@ -1,10 +1,16 @@
+use std::path::PathBuf;
+
 #[allow(unused_imports)]
 use tracing::{debug, error, info, trace, warn};
 
-use crate::game_go::{state_go, GoResponse};
+use crate::game_go::{state_go, GoResponse, GoView};
+use crate::library;
 use crate::menu::{Menu, MenuResponse};
+use crate::online::Connection;
+use crate::review::Review;
+use crate::tsumego::{state_tsumego, Problems};
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Mode {
     /// A loading screen to be used in between modes.
     #[allow(dead_code)]
@@ -18,50 +24,178 @@ pub enum Mode {
     #[allow(dead_code)]
     ShogiPlay2,
     /// A go booard
-    GoBoard { go_game: emi_go::Game },
+    GoBoard {
+        go_game: Box<emi_go::Game>,
+        view: Box<GoView>,
+        /// The connection to the other player in network games.
+        session: Option<Box<Connection>>,
+    },
+    /// Life and death problems to solve.
+    Tsumego { problems: Box<Problems> },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct State {
     mode: Mode,
 }
 
 impl State {
+    /// Start with the game that was open last time, if any, or else the
+    /// main menu.
     pub fn new() -> Self {
-        Self {
-            mode: Mode::Menu {
+        let mode = match library::resume() {
+            Some((game, review, file)) => Self::opened_game(game, review, Some(file)),
+            None => Mode::Menu {
                 menu: Menu::Main {},
             },
+        };
+        Self { mode }
+    }
+
+    /// Show a game that was opened rather than started.
+    fn opened_game(game: emi_go::Game, review: Option<Review>, file: Option<PathBuf>) -> Mode {
+        let mut view = GoView::new();
+        view.file = file;
+        view.review = review;
+        Mode::GoBoard {
+            go_game: Box::new(game),
+            view: Box::new(view),
+            session: None,
+        }
+    }
+
+    /// Open an SGF for review, saving the current game first. Files opened
+    /// this way are copied into the library when saved.
+    fn open_sgf(&mut self, name: &str, text: &str) {
+        if let Mode::GoBoard {
+            view,
+            session: Some(_),
+            ..
+        } = &mut self.mode
+        {
+            view.notice = Some("Can not open files during a network game".to_string());
+            return;
+        }
+        match Review::open(text, true) {
+            Ok((game, review)) => {
+                info!("Opened {}", name);
+                self.save_game(false);
+                self.mode = Self::opened_game(game, review, None);
+            }
+            Err(e) => {
+                error!("Could not open {}: {}", name, e);
+                if let Mode::GoBoard { view, .. } = &mut self.mode {
+                    view.notice = Some(format!("Could not open {name}: {e}"));
+                }
+            }
+        }
+    }
+
+    /// Open any SGF files dropped on the window.
+    fn open_dropped(&mut self, ctx: &eframe::egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let text = match (&file.path, &file.bytes) {
+                (Some(path), _) => std::fs::read_to_string(path).map_err(|e| e.to_string()),
+                (None, Some(bytes)) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+                (None, None) => continue,
+            };
+            let name = match &file.path {
+                Some(path) => path.display().to_string(),
+                None => file.name.clone(),
+            };
+            match text {
+                Ok(text) => self.open_sgf(&name, &text),
+                Err(e) => error!("Could not read {}: {}", name, e),
+            }
+        }
+    }
+
+    /// Save the current game to the library, if there is one with any
+    /// moves, and remember it for next time if `resume` is set.
+    fn save_game(&mut self, resume: bool) {
+        let file = match &mut self.mode {
+            Mode::GoBoard { go_game, view, .. }
+                if go_game.events().next().is_some() || view.review.is_some() =>
+            {
+                if view.file.is_none() && go_game.info().date.is_empty() {
+                    go_game.info_mut().date = library::today();
+                }
+                if let Err(e) = library::save(&view.to_sgf(go_game), &mut view.file) {
+                    warn!("Could not save game: {}", e);
+                }
+                view.file.clone()
+            }
+            _ => None,
+        };
+        let file = file.filter(|_| resume);
+        if let Err(e) = library::set_resume(file.as_deref()) {
+            debug!("Could not update resume file: {}", e);
         }
     }
 }
 
 impl eframe::App for State {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        self.open_dropped(ctx);
+
         // General info
         match &mut self.mode {
             Mode::Menu { menu } => match menu.draw(ctx) {
                 MenuResponse::None => {}
-                MenuResponse::CreateGoGame { game } => {
-                    self.mode = Mode::GoBoard { go_game: game };
+                MenuResponse::CreateGoGame { game, session } => {
+                    self.mode = Mode::GoBoard {
+                        go_game: game,
+                        view: Box::new(GoView::new()),
+                        session,
+                    };
+                }
+                MenuResponse::LoadGoGame { game, review, file } => {
+                    self.mode = Self::opened_game(*game, review.map(|r| *r), Some(file));
+                }
+                MenuResponse::Tsumego { problems } => {
+                    self.mode = Mode::Tsumego { problems };
                 }
             },
-            Mode::GoBoard { go_game } => {
+            Mode::GoBoard {
+                go_game,
+                view,
+                session,
+            } => {
                 // Self::state_options_go(ctx);
-                match state_go(ctx, go_game) {
+                match state_go(ctx, go_game, view, session.as_deref_mut()) {
                     GoResponse::None => {}
                     GoResponse::MainMenu => {
+                        self.save_game(false);
                         self.mode = Mode::Menu {
                             menu: Menu::Main {},
                         };
                         info!("going to main menu");
                     }
+                    GoResponse::Open(path) => match std::fs::read_to_string(&path) {
+                        Ok(text) => self.open_sgf(&path.display().to_string(), &text),
+                        Err(e) => view.notice = Some(format!("Could not open file: {e}")),
+                    },
                 }
             }
+            Mode::Tsumego { problems } => match state_tsumego(ctx, problems) {
+                GoResponse::MainMenu => {
+                    self.mode = Mode::Menu {
+                        menu: Menu::Main {},
+                    };
+                    info!("going to main menu");
+                }
+                GoResponse::None | GoResponse::Open(_) => {}
+            },
             Mode::Loading => todo!(),
             _ => unimplemented!(),
         }
     }
+
+    /// Keep the current game so it can be resumed on the next launch.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_game(true);
+    }
 }
 
 // May move to `thiserror`
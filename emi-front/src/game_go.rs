@@ -1,84 +1,656 @@
 use eframe::egui::{
-    self, emath, vec2, Color32, Frame, Painter, Pos2, Rect, Response, Sense, Shape, Stroke, Vec2,
+    self, emath, vec2, Align2, Color32, FontId, Frame, Painter, Pos2, Rect, Response, Sense, Shape,
+    Stroke, Vec2,
 };
-use emi_go::{Move, Piece};
+use emi_go::export;
+use emi_go::net::NetEvent;
+use emi_go::sgf;
+use emi_go::{
+    coord, diagram, Board, DeadStoneEstimate, GoError, Ladder, Markup, Move, Ownership, Piece,
+    SgfError, TimeControl, DEAD_STONE_PLAYOUTS,
+};
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::debug;
 
-#[allow(unused)]
-#[derive(Debug, Clone)]
+use crate::online::{Client, Connection};
+use crate::review::Review;
+use crate::theme::Theme;
+
+/// Number of random games the ownership estimate is built from.
+const OWNERSHIP_PLAYOUTS: u32 = 64;
+
+/// Number of random games played each frame while guessing dead stones, so
+/// the window keeps responding on large boards.
+const DEAD_STONE_PLAYOUTS_PER_FRAME: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Mark {
     /// No mark.
     None,
-    /// unimplemented
+    /// Draws a triangle outline.
     Triangle,
-    /// unimplemented
+    /// Draws a square outline.
     Square,
-    /// unimplemented
-    Numbering,
-    /// unimplemented
-    Lettering,
+    /// Draws a circle outline.
+    Circle,
+    /// Draws an X.
+    Cross,
+    /// Draws a number.
+    Numbering(u32),
+    /// Draws a label, usually a letter.
+    Lettering(String),
     /// Draws a dot on the stone.
     Dot,
 }
 
+impl From<&Markup> for Mark {
+    fn from(markup: &Markup) -> Self {
+        match markup {
+            Markup::Circle => Self::Circle,
+            Markup::Cross => Self::Cross,
+            Markup::Triangle => Self::Triangle,
+            Markup::Square => Self::Square,
+            Markup::Label(text) => match text.parse() {
+                Ok(n) => Self::Numbering(n),
+                Err(_) => Self::Lettering(text.clone()),
+            },
+        }
+    }
+}
+
+/// What clicking on the board does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tool {
+    /// Play a move.
+    #[default]
+    Play,
+    Triangle,
+    Square,
+    Circle,
+    Cross,
+    /// Label with the next unused number.
+    Number,
+    /// Label with the next unused letter.
+    Letter,
+    /// Remove marks.
+    EraseMark,
+}
+
+impl Tool {
+    const ALL: [Self; 8] = [
+        Self::Play,
+        Self::Triangle,
+        Self::Square,
+        Self::Circle,
+        Self::Cross,
+        Self::Number,
+        Self::Letter,
+        Self::EraseMark,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Play => "Play",
+            Self::Triangle => "Triangle",
+            Self::Square => "Square",
+            Self::Circle => "Circle",
+            Self::Cross => "Cross",
+            Self::Number => "Number",
+            Self::Letter => "Letter",
+            Self::EraseMark => "Erase mark",
+        }
+    }
+
+    /// The mark this tool would place on the current position.
+    fn markup(&self, go_game: &emi_go::Game) -> Option<Markup> {
+        let used = |label: &str| {
+            go_game
+                .marks()
+                .iter()
+                .any(|(_, mark)| matches!(mark, Markup::Label(l) if l == label))
+        };
+        match self {
+            Self::Play | Self::EraseMark => None,
+            Self::Triangle => Some(Markup::Triangle),
+            Self::Square => Some(Markup::Square),
+            Self::Circle => Some(Markup::Circle),
+            Self::Cross => Some(Markup::Cross),
+            Self::Number => (1..)
+                .map(|n: u32| n.to_string())
+                .find(|label| !used(label))
+                .map(Markup::Label),
+            Self::Letter => ('A'..='Z')
+                .map(String::from)
+                .find(|label| !used(label))
+                .map(Markup::Label),
+        }
+    }
+}
+
+/// Stones being placed in setup mode, before they are added to the game.
+#[derive(Debug, Clone)]
+pub struct Setup {
+    /// The game with the changes so far, shown instead of the real one.
+    game: emi_go::Game,
+    /// What clicking on a point puts there.
+    brush: Piece,
+    /// Who plays next once done.
+    to_move: Piece,
+}
+
+impl Setup {
+    pub fn new(go_game: &emi_go::Game) -> Self {
+        Self {
+            game: go_game.clone(),
+            brush: Piece::Black,
+            to_move: go_game.next_to_play(),
+        }
+    }
+
+    /// Put the brush's stone at `pos`, or take it away if it is already
+    /// there.
+    fn place(&mut self, pos: [u32; 2]) {
+        let [x, y] = pos;
+        let to = if self.game.board()[(x, y)] == self.brush {
+            Piece::None
+        } else {
+            self.brush
+        };
+        self.game.edit([(pos, to)]);
+    }
+
+    fn clear(&mut self) {
+        self.game.set_position(&Board::new(self.game.board_size()));
+    }
+
+    /// Add the changes and whose turn it is to `go_game` as one edit, so
+    /// they can be undone together.
+    fn finish(self, go_game: &mut emi_go::Game) {
+        go_game.set_position_to_play(self.game.board(), self.to_move);
+    }
+}
+
+/// Dead stones being marked after both players passed, before the game is
+/// scored.
+#[derive(Debug, Clone)]
+pub struct Marking {
+    dead: Vec<[u32; 2]>,
+    /// The guess at which stones are dead, while it is still being made.
+    guess: Option<DeadStoneEstimate>,
+}
+
+impl Marking {
+    /// Start guessing which stones are dead. The guess is made over the
+    /// next frames by [`Marking::refine`].
+    pub fn new(go_game: &emi_go::Game) -> Self {
+        Self {
+            dead: vec![],
+            guess: Some(go_game.start_dead_stone_estimate()),
+        }
+    }
+
+    /// Play a few more random games for the guess, and mark the stones it
+    /// finds dead once it has all of them. Returns true while still
+    /// guessing.
+    fn refine(&mut self, go_game: &emi_go::Game) -> bool {
+        let Some(guess) = &mut self.guess else {
+            return false;
+        };
+        go_game.refine_dead_stones(guess, DEAD_STONE_PLAYOUTS_PER_FRAME);
+        if guess.playouts() < DEAD_STONE_PLAYOUTS {
+            return true;
+        }
+        self.dead = go_game.dead_stones(guess);
+        self.guess = None;
+        false
+    }
+
+    fn is_guessing(&self) -> bool {
+        self.guess.is_some()
+    }
+
+    /// Mark the group at `pos` dead, or alive again if it already is. Does
+    /// nothing until the guess is done, as it would replace the marks.
+    fn toggle(&mut self, go_game: &emi_go::Game, pos: [u32; 2]) {
+        if !self.is_guessing() {
+            go_game.toggle_dead(&mut self.dead, pos);
+        }
+    }
+
+    /// The board with the dead stones taken off.
+    fn counted(&self, go_game: &emi_go::Game) -> Board {
+        let mut board = go_game.board().clone();
+        for &[x, y] in &self.dead {
+            board.remove(x, y);
+        }
+        board
+    }
+}
+
+/// Which stones have their move number drawn on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveNumbers {
+    /// Only mark the last move.
+    #[default]
+    Off,
+    /// Number every stone that was played.
+    All,
+    /// Number the stones of the last `n` moves.
+    Last(u32),
+}
+
+impl MoveNumbers {
+    /// Returns true if the stone played on move `number` should show its
+    /// number, out of `moves_played` moves.
+    fn shows(&self, number: u32, moves_played: u32) -> bool {
+        match *self {
+            Self::Off => false,
+            Self::All => true,
+            Self::Last(n) => number + n > moves_played,
+        }
+    }
+}
+
+/// How the coordinates around the board are labeled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coordinates {
+    /// No labels.
+    Off,
+    /// Letters `A` to `T` (skipping `I`) across, and numbers up from the
+    /// bottom, eg. `Q16`.
+    #[default]
+    Western,
+    /// SGF letters across and down from the top left, eg. `pd`.
+    Sgf,
+}
+
+impl Coordinates {
+    /// The labels to use on a board of `side`. Western letters run out
+    /// past 25 columns, so larger boards get SGF labels instead.
+    fn for_size(self, side: u32) -> Self {
+        match self {
+            Self::Western if side > coord::MAX_LABELED_SIZE => Self::Sgf,
+            _ => self,
+        }
+    }
+
+    /// The label for column `x`.
+    fn column(&self, x: u32) -> String {
+        match self {
+            Self::Off => String::new(),
+            Self::Western => coord::column_label(x).to_string(),
+            Self::Sgf => coord::sgf_label(x).to_string(),
+        }
+    }
+
+    /// The label for row `y`.
+    fn row(&self, y: u32, side: u32) -> String {
+        match self {
+            Self::Off => String::new(),
+            Self::Western => coord::row_label(y, side).to_string(),
+            Self::Sgf => coord::sgf_label(y).to_string(),
+        }
+    }
+}
+
+/// The area of the board taken up by the grid of intersections, leaving
+/// room around the edge for coordinate labels if they are shown.
+///
+/// Each intersection owns a square of side `grid_rect.width() / side`
+/// centered on it, which is what hit-testing uses.
+fn grid_rect(board_rect: Rect, side: u32, coordinates: Coordinates) -> Rect {
+    if coordinates == Coordinates::Off {
+        return board_rect;
+    }
+    // Labels get a band 0.8 units wide on each side
+    let unit = board_rect.width() / (side as f32 + 1.6);
+    board_rect.shrink(unit * 0.8)
+}
+
+/// Display options for a go game, kept separate from the game itself.
+#[derive(Debug, Clone)]
+pub struct GoView {
+    /// Shade each intersection by its estimated owner.
+    pub show_ownership: bool,
+    /// Cached ownership estimate, refined a little every frame.
+    ownership: Option<Ownership>,
+    /// Flag groups that can be captured in a ladder.
+    pub show_ladders: bool,
+    /// Ladders found on the board they were read for.
+    ladders: Option<(Board, Vec<Ladder>)>,
+    /// What clicking on the board does.
+    pub tool: Tool,
+    /// Which stones show their move numbers.
+    pub move_numbers: MoveNumbers,
+    /// How the coordinates around the board are labeled.
+    pub coordinates: Coordinates,
+    /// Available themes, built in ones first.
+    themes: Vec<Theme>,
+    /// Index of the current theme in `themes`.
+    theme: usize,
+    /// The last thing that went wrong, eg. a move that was rejected in a
+    /// network game, or a file that could not be saved.
+    pub notice: Option<String>,
+    /// Chat in network games, with the color of who said it.
+    chat: Vec<(Piece, String)>,
+    /// The chat message being typed.
+    chat_input: String,
+    /// Where the game is saved in the library, once it has been.
+    pub file: Option<PathBuf>,
+    /// The game tree, when reviewing a game opened from SGF.
+    pub review: Option<Review>,
+    /// Stones being placed in setup mode.
+    setup: Option<Setup>,
+    /// Dead stones being marked once both players passed.
+    marking: Option<Marking>,
+    /// The part of the board to export as an image, eg. `A1:J10`, or empty
+    /// for the whole board.
+    pub export_window: String,
+}
+
+impl GoView {
+    /// Default options, with user themes loaded.
+    pub fn new() -> Self {
+        Self {
+            show_ownership: false,
+            ownership: None,
+            show_ladders: false,
+            ladders: None,
+            tool: Tool::default(),
+            move_numbers: MoveNumbers::default(),
+            coordinates: Coordinates::default(),
+            themes: Theme::all(),
+            theme: 0,
+            notice: None,
+            chat: vec![],
+            chat_input: String::new(),
+            file: None,
+            review: None,
+            setup: None,
+            marking: None,
+            export_window: String::new(),
+        }
+    }
+
+    /// The game as SGF, or the whole tree when reviewing.
+    pub fn to_sgf(&self, go_game: &emi_go::Game) -> String {
+        match &self.review {
+            Some(review) => review.to_sgf(),
+            None => go_game.to_sgf(),
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    /// Returns the ownership estimate for the current position, starting a
+    /// new one if the position changed.
+    fn update_ownership(&mut self, go_game: &emi_go::Game) -> Option<&Ownership> {
+        if !self.show_ownership {
+            self.ownership = None;
+            return None;
+        }
+        match &mut self.ownership {
+            Some(ownership) if ownership.is_for(go_game.board()) => {
+                if ownership.playouts() < OWNERSHIP_PLAYOUTS {
+                    go_game.refine_ownership(ownership, 1);
+                }
+            }
+            _ => self.ownership = Some(go_game.estimate_ownership(1)),
+        }
+        self.ownership.as_ref()
+    }
+
+    /// Start marking dead stones once both players pass in a local game,
+    /// and stop if play goes on or the game is scored.
+    fn update_marking(&mut self, go_game: &emi_go::Game, local: bool) {
+        let counting = local && go_game.is_finished() && go_game.result().is_none();
+        if !counting {
+            self.marking = None;
+        } else if self.marking.is_none() {
+            self.marking = Some(Marking::new(go_game));
+        }
+    }
+
+    /// Returns the ladders on the current board, reading them again if the
+    /// board changed.
+    fn update_ladders(&mut self, go_game: &emi_go::Game) -> &[Ladder] {
+        if !self.show_ladders {
+            self.ladders = None;
+            return &[];
+        }
+        let board = go_game.board();
+        if !matches!(&self.ladders, Some((read, _)) if read == board) {
+            self.ladders = Some((board.clone(), emi_go::ladders(board)));
+        }
+        self.ladders.as_ref().map_or(&[], |(_, ladders)| ladders)
+    }
+
+    /// Returns true if the ownership estimate is still being refined.
+    fn is_estimating(&self) -> bool {
+        matches!(&self.ownership, Some(o) if o.playouts() != 0 && o.playouts() < OWNERSHIP_PLAYOUTS)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum GoResponse {
     None,
     MainMenu,
+    /// Open an SGF file for review.
+    Open(PathBuf),
 }
 
-fn draw_mark(painter: &Painter, piece_color: Piece, pos: Pos2, piece_radius: f32, mark: Mark) {
+fn draw_mark(
+    painter: &Painter,
+    theme: &Theme,
+    piece_color: Piece,
+    pos: Pos2,
+    piece_radius: f32,
+    mark: Mark,
+) {
     let color = match piece_color {
-        Piece::None => {
+        Piece::None => theme.lines,
+        Piece::Black => theme.white.gamma_multiply(0.9),
+        Piece::White => theme.black.gamma_multiply(0.9),
+    };
+    let stroke = Stroke::new((piece_radius / 8.0).max(1.0), color);
+    // Shapes are drawn a bit smaller than the stone
+    let r = piece_radius * 0.55;
+
+    let text = match mark {
+        Mark::None => return,
+        Mark::Dot => {
+            painter.add(Shape::circle_filled(pos, piece_radius / 3.0, color));
+            return;
+        }
+        Mark::Triangle => {
+            let points = [-90.0_f32, 30.0, 150.0]
+                .into_iter()
+                .map(|deg| pos + Vec2::angled(deg.to_radians()) * r)
+                .collect();
+            painter.add(Shape::closed_line(points, stroke));
             return;
         }
-        Piece::Black => Color32::WHITE.gamma_multiply(0.9),
-        Piece::White => Color32::BLACK.gamma_multiply(0.9),
+        Mark::Square => {
+            let rect = Rect::from_center_size(pos, Vec2::splat(r * 1.5));
+            painter.add(Shape::rect_stroke(rect, 0.0, stroke));
+            return;
+        }
+        Mark::Circle => {
+            painter.add(Shape::circle_stroke(pos, r * 0.9, stroke));
+            return;
+        }
+        Mark::Cross => {
+            let d = r * 0.75;
+            painter.add(Shape::line_segment(
+                [pos + vec2(-d, -d), pos + vec2(d, d)],
+                stroke,
+            ));
+            painter.add(Shape::line_segment(
+                [pos + vec2(-d, d), pos + vec2(d, -d)],
+                stroke,
+            ));
+            return;
+        }
+        Mark::Numbering(n) => n.to_string(),
+        Mark::Lettering(text) => text,
     };
 
-    match mark {
-        Mark::None => {}
-        Mark::Dot => _ = painter.add(Shape::circle_filled(pos, piece_radius / 3.0, color)),
-        _ => unimplemented!(),
+    // Hide the board lines behind text on empty points
+    if piece_color == Piece::None {
+        painter.add(Shape::circle_filled(pos, piece_radius * 0.8, theme.board));
     }
+    let font_size = if text.chars().count() > 2 {
+        piece_radius * 0.8
+    } else {
+        piece_radius * 1.1
+    };
+    painter.text(
+        pos,
+        Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(font_size),
+        color,
+    );
 }
 
-fn draw_go_piece(painter: &Painter, piece_color: Piece, pos: Pos2, radius: f32, mark: Mark) {
+fn draw_go_piece(
+    painter: &Painter,
+    theme: &Theme,
+    piece_color: Piece,
+    pos: Pos2,
+    radius: f32,
+    mark: Mark,
+) {
     let color = match piece_color {
-        Piece::None => {
-            return;
-        }
-        Piece::Black => Color32::BLACK,
-        Piece::White => Color32::WHITE,
+        Piece::None => None,
+        Piece::Black => Some(theme.black),
+        Piece::White => Some(theme.white),
     };
-    let piece = Shape::circle_filled(pos, radius, color);
-    painter.add(piece);
-    draw_mark(painter, piece_color, pos, radius, mark);
+    if let Some(color) = color {
+        if theme.shadow {
+            painter.add(Shape::circle_filled(
+                pos + vec2(radius * 0.08, radius * 0.12),
+                radius,
+                Color32::BLACK.gamma_multiply(0.3),
+            ));
+        }
+        let piece = Shape::circle_filled(pos, radius, color);
+        painter.add(piece);
+        if theme.shading {
+            // Fake a gradient with a few circles towards the top left
+            let light = if piece_color == Piece::Black {
+                0.08
+            } else {
+                0.25
+            };
+            for i in 1..=3 {
+                let t = i as f32 / 3.0;
+                painter.add(Shape::circle_filled(
+                    pos - Vec2::splat(radius * 0.3 * t),
+                    radius * (1.0 - 0.25 * i as f32),
+                    Color32::WHITE.gamma_multiply(light * t),
+                ));
+            }
+        }
+        if let Some(outline) = theme.outline {
+            painter.add(Shape::circle_stroke(
+                pos,
+                radius - 0.5,
+                Stroke::new(1.0, outline),
+            ));
+        }
+    }
+    draw_mark(painter, theme, piece_color, pos, radius, mark);
+}
+
+/// Draws a few wavy lines over the board so it looks like wood.
+fn draw_grain(painter: &Painter, board_rect: Rect, grain: Color32) {
+    let stroke = Stroke::new((board_rect.height() / 400.0).max(1.0), grain);
+    let lines = 24;
+    for i in 0..lines {
+        // Spread the lines unevenly, but the same way every frame
+        let offset = ((i * 7919) % 13) as f32 / 13.0 - 0.5;
+        let y = board_rect.top() + board_rect.height() * (i as f32 + 0.5 + offset) / lines as f32;
+        let wave = board_rect.height() / 150.0 * (1.0 + offset);
+        let points = (0..=32)
+            .map(|j| {
+                let t = j as f32 / 32.0;
+                Pos2::new(
+                    board_rect.left() + board_rect.width() * t,
+                    y + wave * (t * 9.0 + i as f32).sin(),
+                )
+            })
+            .collect();
+        painter.add(Shape::line(points, stroke));
+    }
 }
 
-fn draw_go_board(go_game: &emi_go::Game, painter: &egui::Painter, board_rect: Rect) {
+/// Draws the board, its lines and hoshi, and coordinate labels.
+///
+/// `board_rect` is the whole board, and `grid_rect` is the part of it
+/// covered by intersections, see [`grid_rect`].
+fn draw_go_board(
+    go_game: &emi_go::Game,
+    painter: &egui::Painter,
+    theme: &Theme,
+    board_rect: Rect,
+    grid_rect: Rect,
+    coordinates: Coordinates,
+) {
     // number of points along one side of the board (usually 9, 13, or 19)
     let board_points = go_game.board_size() as f32;
 
-    let board_size = board_rect.width();
+    let board_size = grid_rect.width();
     // The size of one square on the board
     let unit = board_size / board_points;
     let thickness = (board_size / 400.0).ceil() * 0.5;
 
-    let board = Shape::rect_filled(
-        board_rect,
-        0.0,
-        // #e7c88c
-        Color32::from_rgb(0xe7, 0xc8, 0x8c),
-    );
+    let board = Shape::rect_filled(board_rect, 0.0, theme.board);
 
     painter.add(board);
 
+    if let Some(grain) = theme.grain {
+        draw_grain(painter, board_rect, grain);
+    }
+
     // Converts from game coordinate to screen coordinate
     let coord_fn = |[x, y]: [f32; 2]| {
-        board_rect.left_top() + vec2(x * unit, y * unit) + Vec2::splat(unit / 2.0)
+        grid_rect.left_top() + vec2(x * unit, y * unit) + Vec2::splat(unit / 2.0)
     };
 
+    if coordinates != Coordinates::Off {
+        let side = go_game.board_size();
+        let font = FontId::proportional(unit * 0.45);
+        // Centers of the label bands on each side
+        let band = (grid_rect.left() - board_rect.left()) / 2.0;
+        for i in 0..side {
+            let line = coord_fn([i as f32, i as f32]);
+            let column = coordinates.column(i);
+            let row = coordinates.row(i, side);
+            for pos in [
+                Pos2::new(line.x, board_rect.top() + band),
+                Pos2::new(line.x, board_rect.bottom() - band),
+            ] {
+                painter.text(
+                    pos,
+                    Align2::CENTER_CENTER,
+                    &column,
+                    font.clone(),
+                    theme.lines,
+                );
+            }
+            for pos in [
+                Pos2::new(board_rect.left() + band, line.y),
+                Pos2::new(board_rect.right() - band, line.y),
+            ] {
+                painter.text(pos, Align2::CENTER_CENTER, &row, font.clone(), theme.lines);
+            }
+        }
+    }
+
     // draw lines
     for i in 0..board_points as usize {
         let horz_line = Shape::line_segment(
@@ -86,93 +658,208 @@ fn draw_go_board(go_game: &emi_go::Game, painter: &egui::Painter, board_rect: Re
                 coord_fn([0.0, i as f32]),
                 coord_fn([board_points - 1.0, i as f32]),
             ],
-            Stroke::new(thickness, Color32::BLACK),
+            Stroke::new(thickness, theme.lines),
         );
         let vert_line = Shape::line_segment(
             [
                 coord_fn([i as f32, 0.0]),
                 coord_fn([i as f32, board_points - 1.0]),
             ],
-            Stroke::new(thickness, Color32::BLACK),
+            Stroke::new(thickness, theme.lines),
         );
         painter.add(horz_line);
         painter.add(vert_line);
     }
 
-    if board_points <= 4.0 {
+    // Draw the dots on the board
+    for [x, y] in coord::hoshi(go_game.board_size()) {
+        painter.add(Shape::circle_filled(
+            coord_fn([x as f32, y as f32]),
+            thickness * 3.0,
+            theme.lines,
+        ));
+    }
+}
+
+/// Shades each intersection black or white by how likely it is to belong to
+/// that player.
+fn draw_ownership(ownership: &Ownership, painter: &Painter, board_rect: Rect) {
+    let board_points = ownership.board_size();
+    let unit = board_rect.width() / board_points as f32;
+    for x in 0..board_points {
+        for y in 0..board_points {
+            let value = ownership.get(x, y);
+            let color = if value > 0.0 {
+                Color32::BLACK
+            } else {
+                Color32::WHITE
+            };
+            let pos = board_rect.left_top()
+                + vec2(unit * (x as f32), unit * (y as f32))
+                + Vec2::splat(unit / 2.0);
+            painter.add(Shape::rect_filled(
+                Rect::from_center_size(pos, Vec2::splat(unit * 0.8)),
+                0.0,
+                color.gamma_multiply(value.abs() * 0.6),
+            ));
+        }
+    }
+}
+
+/// Fades out dead stones, and shades the points each player is counted
+/// for.
+fn draw_marking(
+    marking: &Marking,
+    go_game: &emi_go::Game,
+    theme: &Theme,
+    painter: &Painter,
+    board_rect: Rect,
+) {
+    // The count means nothing until the dead stones are guessed
+    if marking.is_guessing() {
         return;
     }
-    // Distance from the edge of the board (intersections)
-    // for dots to be placed
-    // One quarter
-    let a = if board_points < 17.0 {
-        (board_points / 3.0).floor() - 1.0
-    } else {
-        (board_points / 4.0).floor() - 1.0
+    let board_points = go_game.board_size();
+    let unit = board_rect.width() / board_points as f32;
+    let center = |x: u32, y: u32| {
+        board_rect.left_top() + vec2(unit * (x as f32), unit * (y as f32)) + Vec2::splat(unit / 2.0)
     };
-    // Half
-    let b = (board_points / 2.0).ceil() - 1.0;
-    // Three quarters
-    let c = board_points - a - 1.0;
-    let mut dots = Vec::with_capacity(9);
-    // Add corners
-    dots.push([a, a]);
-    dots.push([a, c]);
-    dots.push([c, a]);
-    dots.push([c, c]);
-
-    if board_points as u32 % 2 == 1 {
-        // center
-        dots.push([b, b]);
-
-        // Add sides
-        if board_points >= 19.0 {
-            dots.push([a, b]);
-            dots.push([c, b]);
-            dots.push([b, a]);
-            dots.push([b, c]);
+    for &[x, y] in &marking.dead {
+        let color = theme.board.gamma_multiply(0.6);
+        painter.add(Shape::circle_filled(center(x, y), unit / 2.0, color));
+    }
+    let owners = marking.counted(go_game).area_owners();
+    for (i, owner) in owners.into_iter().enumerate() {
+        let color = match owner {
+            Piece::None => continue,
+            Piece::Black => theme.black,
+            Piece::White => theme.white,
+        };
+        let (x, y) = (i as u32 % board_points, i as u32 / board_points);
+        if go_game.board().get(x, y) == owner {
+            continue;
         }
+        let square = Rect::from_center_size(center(x, y), Vec2::splat(unit * 0.35));
+        painter.add(Shape::rect_filled(square, 0.0, color));
     }
+}
 
-    // Draw the dots on the board
-    for dot_coord in dots {
-        painter.add(Shape::circle_filled(
-            coord_fn(dot_coord),
-            thickness * 3.0,
-            Color32::BLACK,
-        ));
+/// Rings the stones of each ladder in red, and marks where the attacker
+/// starts chasing them.
+fn draw_ladders(ladders: &[Ladder], board: &Board, painter: &Painter, board_rect: Rect) {
+    let unit = board_rect.width() / board.board_size() as f32;
+    let center = |[x, y]: [u32; 2]| {
+        board_rect.left_top() + vec2(unit * (x as f32), unit * (y as f32)) + Vec2::splat(unit / 2.0)
+    };
+    let color = Color32::from_rgb(220, 40, 40);
+    let stroke = Stroke::new((unit / 12.0).max(1.5), color);
+    for ladder in ladders {
+        let Some(&[x, y]) = ladder.stones.first() else {
+            continue;
+        };
+        let attacker = board.get(x, y).opposing();
+        for &pos in &ladder.stones {
+            painter.add(Shape::circle_stroke(center(pos), unit * 0.5, stroke));
+        }
+        let start = ladder.moves.iter().find_map(|move_| match *move_ {
+            Move::Place { pos, color } if color == attacker => Some(pos),
+            _ => None,
+        });
+        if let Some(pos) = start {
+            painter.add(Shape::circle_filled(center(pos), unit * 0.15, color));
+        }
     }
 }
 
-fn game_go_display_pieces(go_game: &emi_go::Game, painter: &egui::Painter, board_rect: Rect) {
+pub(crate) fn game_go_display_pieces(
+    go_game: &emi_go::Game,
+    theme: &Theme,
+    ownership: Option<&Ownership>,
+    move_numbers: MoveNumbers,
+    painter: &egui::Painter,
+    board_rect: Rect,
+) {
     let board_size = board_rect.width();
     // The number of points in the board along one side.
     let board_points = go_game.board_size();
     let r = board_size / board_points as f32 / 2.0;
+
+    if let Some(ownership) = ownership {
+        draw_ownership(ownership, painter, board_rect);
+    }
+
+    let moves_played = go_game.moves_played();
+    let numbers: Vec<_> = go_game
+        .stone_move_numbers()
+        .into_iter()
+        .filter(|&(_, number)| move_numbers.shows(number, moves_played))
+        .collect();
+
     for x in 0..board_points {
         for y in 0..board_points {
             // Calculate coordinate values
             let pos =
                 board_rect.left_top() + vec2(2.0 * r * (x as f32) + r, 2.0 * r * (y as f32) + r);
 
-            let mark = if go_game.last_played_pos() == Some([x, y]) {
+            let number = numbers.iter().find(|(pos, _)| *pos == [x, y]);
+            let mark = if let Some(markup) = go_game.mark_at([x, y]) {
+                Mark::from(markup)
+            } else if let Some(&(_, number)) = number {
+                Mark::Numbering(number)
+            } else if go_game.last_played_pos() == Some([x, y]) {
                 Mark::Dot
             } else {
                 Mark::None
             };
 
-            draw_go_piece(painter, go_game.board()[(x, y)], pos, r, mark);
+            draw_go_piece(painter, theme, go_game.board()[(x, y)], pos, r, mark);
         }
     }
 }
 
-fn interaction_go(
+/// Draw an empty board filling the space available. Returns the board's
+/// response and painter, and the rectangle the points are in.
+pub(crate) fn board_ui(
+    ui: &mut egui::Ui,
+    go_game: &emi_go::Game,
+    theme: &Theme,
+    coordinates: Coordinates,
+) -> (Response, Painter, Rect) {
+    let width = ui.available_width();
+    let height = ui.available_height().min(800.0);
+
+    let (response, painter) = ui.allocate_painter(vec2(width, height), Sense::click());
+
+    let to_screen = emath::RectTransform::from_to(
+        Rect::from_min_size(Pos2::ZERO, response.rect.size()),
+        response.rect,
+    );
+
+    let board_size = width.min(height);
+
+    let board_rect = Rect::from_center_size(
+        to_screen.transform_pos(Pos2::new(width / 2.0, height / 2.0)),
+        vec2(board_size, board_size),
+    );
+
+    let coordinates = coordinates.for_size(go_game.board_size());
+    let grid_rect = grid_rect(board_rect, go_game.board_size(), coordinates);
+    draw_go_board(go_game, &painter, theme, board_rect, grid_rect, coordinates);
+    (response, painter, grid_rect)
+}
+
+/// Handle clicks on the board. Marks are set right away, but stones are
+/// returned as a move for the caller to play.
+pub(crate) fn interaction_go(
     go_game: &mut emi_go::Game,
+    tool: Tool,
+    theme: &Theme,
     rect: Rect,
     painter: &egui::Painter,
     response: &Response,
-) {
-    let Some(pos) = response.hover_pos() else { return; };
+) -> Option<Move> {
+    let pos = response.hover_pos()?;
+    let mut move_ = None;
     // Is the mouse within board bounds?
     // This assumes the board is square, which is reasonable for now, but might change.
     let rect_size = rect.width();
@@ -182,47 +869,600 @@ fn interaction_go(
         let to_board =
             emath::RectTransform::from_to(Rect::from_min_size(Pos2::ZERO, rect.size()), rect);
 
-        // Calculate where on board
+        // Calculate where on board. The right and bottom edges are inside
+        // `rect` but past the last intersection's square.
+        let last = go_game.board_size() - 1;
         let board_pos = to_board.inverse().transform_pos(pos);
-        let point_coord_x = (board_pos.x / unit).floor() as u32;
-        let point_coord_y = (board_pos.y / unit).floor() as u32;
+        let point_coord_x = ((board_pos.x / unit).floor() as u32).min(last);
+        let point_coord_y = ((board_pos.y / unit).floor() as u32).min(last);
         let hover_pos = to_board.transform_pos(Pos2::new(
             point_coord_x as f32 * unit + unit / 2.0,
             point_coord_y as f32 * unit + unit / 2.0,
         ));
 
         if response.clicked() {
-            debug!("Trying to play at ({}, {})", point_coord_x, point_coord_y);
-            let _ = go_game.play_(Move::Place {
-                pos: [point_coord_x, point_coord_y],
-                color: go_game.next_to_play(),
-            });
+            let pos = [point_coord_x, point_coord_y];
+            match tool {
+                Tool::Play => {
+                    debug!("Trying to play at ({}, {})", point_coord_x, point_coord_y);
+                    move_ = Some(Move::Place {
+                        pos,
+                        color: go_game.next_to_play(),
+                    });
+                }
+                Tool::EraseMark => go_game.set_mark(pos, None),
+                _ => {
+                    let markup = tool.markup(go_game);
+                    // Clicking on the same kind of mark again removes it
+                    let same = match (go_game.mark_at(pos), &markup) {
+                        (Some(Markup::Label(_)), Some(Markup::Label(_))) => true,
+                        (Some(old), Some(new)) => old == new,
+                        _ => false,
+                    };
+                    go_game.set_mark(pos, if same { None } else { markup });
+                }
+            }
         }
 
         // make a rectangle
         let hover_hl = Shape::rect_filled(
             Rect::from_center_size(hover_pos, vec2(unit, unit)),
             0.0,
-            Color32::LIGHT_GREEN.gamma_multiply(0.5),
+            theme.highlight,
         );
         painter.add(hover_hl);
     }
+    move_
+}
+
+/// Play a move made on this side, sending it to the other player in network
+/// games, or adding it to the game tree when reviewing. Returns why the move
+/// could not be played, if it could not.
+fn play_local(
+    go_game: &mut emi_go::Game,
+    session: Option<&mut Connection>,
+    review: Option<&mut Review>,
+    move_: Move,
+) -> Result<(), GoError> {
+    let color = go_game.next_to_play();
+    go_game.play_(move_)?;
+    if let Some(session) = session {
+        session.send_last_move(go_game);
+    }
+    if let Some(review) = review {
+        match move_ {
+            Move::Place { pos, color } => review.play(color, Some(pos)),
+            Move::Pass => review.play(color, None),
+            Move::Resign { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// Write the game, or the whole tree when reviewing, to a file picked by
+/// the user.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_sgf(go_game: &emi_go::Game, view: &mut GoView) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("SGF", &["sgf"])
+        .set_file_name("game.sgf")
+        .save_file()
+    else {
+        return;
+    };
+    if let Err(e) = std::fs::write(&path, view.to_sgf(go_game)) {
+        view.notice = Some(format!("Could not save {}: {}", path.display(), e));
+    }
+}
+
+/// Draw the position as it is shown to an SVG or PNG file picked by the
+/// user.
+#[cfg(not(target_arch = "wasm32"))]
+fn export_image(go_game: &emi_go::Game, view: &mut GoView) {
+    let area = view.export_window.trim();
+    let window = if area.is_empty() {
+        None
+    } else if let Some(window) = export::parse_window(area, go_game.board_size()) {
+        Some(window)
+    } else {
+        view.notice = Some(format!("Bad area `{area}`, expected eg. A1:J10"));
+        return;
+    };
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("SVG", &["svg"])
+        .add_filter("PNG", &["png"])
+        .set_file_name("position.svg")
+        .save_file()
+    else {
+        return;
+    };
+    let Some(format) = export::Format::from_path(&path) else {
+        view.notice = Some(format!("{} should end in .svg or .png", path.display()));
+        return;
+    };
+
+    let theme = view.theme();
+    let rgb = |color: Color32| [color.r(), color.g(), color.b()];
+    let options = export::Options {
+        window,
+        coordinates: view.coordinates != Coordinates::Off,
+        move_numbers: view.move_numbers != MoveNumbers::Off,
+        colors: export::Colors {
+            board: rgb(theme.board),
+            lines: rgb(theme.lines),
+            black: rgb(theme.black),
+            white: rgb(theme.white),
+        },
+        ..Default::default()
+    };
+    let written = export::export(go_game, &options, format)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        view.notice = Some(format!("Could not export {}: {}", path.display(), e));
+    }
+}
+
+/// Pick what clicks place in setup mode, and add the stones to the game
+/// when done.
+fn setup_ui(ui: &mut egui::Ui, go_game: &mut emi_go::Game, setup: &mut Option<Setup>) {
+    let Some(s) = setup else {
+        return;
+    };
+    ui.heading("Set up position");
+    ui.label("Clicking places");
+    ui.radio_value(&mut s.brush, Piece::Black, "Black stones");
+    ui.radio_value(&mut s.brush, Piece::White, "White stones");
+    ui.radio_value(&mut s.brush, Piece::None, "Nothing");
+    ui.label("To play next");
+    ui.radio_value(&mut s.to_move, Piece::Black, "Black");
+    ui.radio_value(&mut s.to_move, Piece::White, "White");
+    if ui.button("Clear board").clicked() {
+        s.clear();
+    }
+
+    ui.separator();
+    let (done, cancel) = ui
+        .horizontal(|ui| (ui.button("Done").clicked(), ui.button("Cancel").clicked()))
+        .inner;
+    if done {
+        if let Some(s) = setup.take() {
+            s.finish(go_game);
+        }
+    } else if cancel {
+        *setup = None;
+    }
+}
+
+/// Set up a position pasted as SGF or a diagram, as one edit so it can be
+/// undone. Whose turn it is stays the same, unless the SGF sets it with
+/// `PL`.
+fn paste_position(go_game: &mut emi_go::Game, text: &str) -> Result<(), String> {
+    let (board, to_move) = if text.contains("(;") {
+        let trees = sgf::parse(text).map_err(|e| e.to_string())?;
+        let tree = trees.first().ok_or_else(|| SgfError::NoGame.to_string())?;
+        let pasted = emi_go::Game::from_sgf_tree(tree).map_err(|e| e.to_string())?;
+        let has_player = tree.main_line().any(|node| node.get("PL").is_some());
+        (
+            pasted.board().clone(),
+            has_player.then(|| pasted.next_to_play()),
+        )
+    } else {
+        (diagram::parse(text).map_err(|e| e.to_string())?, None)
+    };
+    let (pasted, side) = (board.board_size(), go_game.board_size());
+    if pasted != side {
+        return Err(format!(
+            "The position is {pasted}x{pasted}, but the board is {side}x{side}"
+        ));
+    }
+    let to_move = to_move.unwrap_or(go_game.next_to_play());
+    go_game.set_position_to_play(&board, to_move);
+    Ok(())
+}
+
+/// Read text from the clipboard.
+#[cfg(not(target_arch = "wasm32"))]
+fn clipboard_text() -> Result<String, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| e.to_string())
+}
+
+/// Step through the game tree, and pick variations from it.
+fn review_ui(ui: &mut egui::Ui, go_game: &mut emi_go::Game, view: &mut GoView) {
+    const SPACING: f32 = 22.0;
+    const RADIUS: f32 = 7.0;
+
+    let Some(review) = &mut view.review else {
+        return;
+    };
+    let node = review.node();
+    let last = review.line_len() - 1;
+    let mut go_to = None;
+    let mut select = None;
+
+    ui.horizontal(|ui| {
+        for (label, target) in [
+            ("|<", 0),
+            ("<", node.saturating_sub(1)),
+            (">", node + 1),
+            (">|", last),
+        ] {
+            if ui.button(label).clicked() {
+                go_to = Some(target);
+            }
+        }
+        ui.label(format!("Node {node} of {last}"));
+    });
+    // Arrow keys step through the game, unless typing
+    if ui.memory(|m| m.focus().is_none()) {
+        ui.input(|i| {
+            if i.key_pressed(egui::Key::ArrowLeft) {
+                go_to = Some(node.saturating_sub(1));
+            } else if i.key_pressed(egui::Key::ArrowRight) {
+                go_to = Some(node + 1);
+            } else if i.key_pressed(egui::Key::Home) {
+                go_to = Some(0);
+            } else if i.key_pressed(egui::Key::End) {
+                go_to = Some(last);
+            }
+        });
+    }
+
+    let nodes = review.layout();
+    egui::ScrollArea::both().show(ui, |ui| {
+        let depth = nodes.iter().map(|n| n.depth).max().unwrap_or(0);
+        let rows = nodes.iter().map(|n| n.row).max().unwrap_or(0);
+        let size = vec2(depth as f32 + 1.0, rows as f32 + 1.0) * SPACING;
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        let center = |n: &crate::review::TreeNode| {
+            rect.min + vec2(n.depth as f32 + 0.5, n.row as f32 + 0.5) * SPACING
+        };
+
+        let visuals = ui.visuals();
+        for n in &nodes {
+            let Some(parent) = n.parent else {
+                continue;
+            };
+            let color = if review.is_on_line(n) {
+                visuals.strong_text_color()
+            } else {
+                visuals.weak_text_color()
+            };
+            painter.line_segment([center(&nodes[parent]), center(n)], Stroke::new(1.5, color));
+        }
+        for n in &nodes {
+            let c = center(n);
+            let fill = match n.color {
+                Piece::Black => Color32::BLACK,
+                Piece::White => Color32::WHITE,
+                Piece::None => Color32::GRAY,
+            };
+            painter.circle(c, RADIUS, fill, Stroke::new(1.0, Color32::DARK_GRAY));
+            if review.is_current(n) {
+                painter.circle_stroke(c, RADIUS + 3.0, visuals.selection.stroke);
+                if go_to.is_some() || response.clicked() {
+                    ui.scroll_to_rect(Rect::from_center_size(c, Vec2::splat(SPACING)), None);
+                }
+            }
+        }
+
+        if let Some(pos) = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+        {
+            select = nodes
+                .iter()
+                .find(|n| center(n).distance(pos) <= RADIUS + 2.0)
+                .cloned();
+        }
+    });
+
+    let game = match (go_to, select) {
+        (_, Some(n)) => review.select(n.path, n.depth),
+        (Some(target), None) if target != node => review.go_to(target),
+        _ => return,
+    };
+    match game {
+        Ok(game) => *go_game = game,
+        Err(e) => view.notice = Some(e.to_string()),
+    }
+}
+
+/// Format a duration like a game clock, eg. `4:05`.
+fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs_f32().ceil() as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Show the time left for each player.
+fn clock_ui(ui: &mut egui::Ui, go_game: &emi_go::Game) {
+    let Some(clock) = go_game.clock() else {
+        return;
+    };
+    for (name, color) in [("Black", Piece::Black), ("White", Piece::White)] {
+        let player = clock.player(color);
+        let time = if !player.in_overtime() {
+            format_clock(player.main_left())
+        } else {
+            match clock.time_control() {
+                TimeControl::ByoYomi { .. } => format!(
+                    "{} ({} left)",
+                    format_clock(player.period_left()),
+                    player.periods_left()
+                ),
+                TimeControl::Canadian { .. } => format!(
+                    "{} / {} stones",
+                    format_clock(player.period_left()),
+                    player.stones_left()
+                ),
+                _ => format_clock(Duration::ZERO),
+            }
+        };
+        let text = egui::RichText::new(format!("{name}: {time}")).monospace();
+        if go_game.next_to_play() == color && !go_game.is_over() {
+            ui.label(text.strong());
+        } else {
+            ui.label(text);
+        }
+    }
+}
+
+/// Show how the game ended, if it has.
+fn result_ui(ui: &mut egui::Ui, go_game: &emi_go::Game) {
+    if let Some(result) = go_game.result() {
+        ui.heading(result.to_string());
+        ui.label(result.describe());
+    } else if go_game.is_finished() {
+        ui.label("Both players passed");
+    }
+}
+
+/// Show the count while marking dead stones, and end the game with it once
+/// the players agree.
+fn marking_ui(ui: &mut egui::Ui, go_game: &mut emi_go::Game, marking: &mut Option<Marking>) {
+    let Some(m) = marking else {
+        return;
+    };
+    ui.heading("Counting");
+    if m.is_guessing() {
+        ui.label("Guessing which stones are dead...");
+        return;
+    }
+    ui.label("Click a group to mark it dead or alive");
+    let score = go_game.area_score(&m.dead);
+    ui.label(format!("Black: {}", score.black));
+    ui.label(format!("White: {} + {} komi", score.white, score.komi));
+    ui.label(score.result().describe());
+    let accept = ui.button("Accept").clicked();
+    if ui.button("Guess again").clicked() {
+        *m = Marking::new(go_game);
+    }
+    if accept {
+        go_game.set_result(Some(score.result()));
+        *marking = None;
+    }
+}
+
+/// Show the chat, and send what is typed to the other player.
+fn chat_ui(ui: &mut egui::Ui, view: &mut GoView, session: &mut Connection) {
+    egui::ScrollArea::vertical()
+        .max_height(120.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for (color, text) in &view.chat {
+                let name = match *color {
+                    Piece::None => "Spectator",
+                    color if color == session.local_color() => "You",
+                    Piece::White => "White",
+                    Piece::Black => "Black",
+                };
+                ui.label(format!("{name}: {text}"));
+            }
+        });
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut view.chat_input)
+            .hint_text("Chat")
+            .desired_width(f32::INFINITY),
+    );
+    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        let text = std::mem::take(&mut view.chat_input);
+        if !text.trim().is_empty() {
+            session.send_chat(text, &mut view.chat);
+        }
+        response.request_focus();
+    }
+}
+
+/// Edit the comment on the current position. Comments are saved in SGF
+/// files, and shared in network games.
+fn comment_ui(ui: &mut egui::Ui, go_game: &mut emi_go::Game, session: Option<&mut Connection>) {
+    ui.label(format!("Comment on move {}", go_game.move_number()));
+    let mut comment = go_game.comment().to_string();
+    let response = ui.add(egui::TextEdit::multiline(&mut comment).desired_rows(3));
+    if response.changed() {
+        go_game.set_comment(comment);
+    }
+    // Sent once done typing rather than on every key
+    if response.lost_focus() {
+        if let Some(session) = session {
+            session.send_comment(go_game);
+        }
+    }
+}
+
+/// Show who is playing where, and whether they are connected.
+fn network_ui(ui: &mut egui::Ui, session: &Connection) {
+    match session {
+        Connection::Peer(session) => {
+            let color = match session.local_color() {
+                Piece::Black => "Black",
+                Piece::White => "White",
+                Piece::None => "?",
+            };
+            ui.label(format!("Playing {color}"));
+            if session.is_connected() {
+                ui.label("Connected");
+            } else if session.is_host() {
+                match session.local_addr() {
+                    Ok(addr) => ui.label(format!("Waiting for opponent on port {}", addr.port())),
+                    Err(_) => ui.label("Waiting for opponent"),
+                };
+            } else {
+                ui.label("Connecting...");
+            }
+        }
+        Connection::Server(client) => server_ui(ui, client),
+    }
+}
+
+/// Show the room, who is in it, and whether the server is still there.
+fn server_ui(ui: &mut egui::Ui, client: &Client) {
+    let seat = match client.local_color() {
+        Piece::Black => "Playing Black",
+        Piece::White => "Playing White",
+        Piece::None => "Watching",
+    };
+    match client.room() {
+        Some(room) => {
+            ui.label(format!("{seat} in {:?} on {}", room.name, client.address()));
+            let missing = match (room.black, room.white) {
+                (true, true) => None,
+                (false, true) => Some("Waiting for Black"),
+                (true, false) => Some("Waiting for White"),
+                (false, false) => Some("Waiting for players"),
+            };
+            if let Some(missing) = missing {
+                ui.label(missing);
+            }
+            if room.spectators > 0 {
+                ui.label(format!("{} watching", room.spectators));
+            }
+        }
+        None => {
+            ui.label(format!("{seat} on {}", client.address()));
+        }
+    }
+    if let Some(reason) = client.closed() {
+        ui.label(format!("Disconnected: {reason}"));
+    }
 }
 
 #[must_use]
-pub fn state_go(ctx: &egui::Context, go_game: &mut emi_go::Game) -> GoResponse {
-    let resp = egui::TopBottomPanel::top("game_go_menu")
-        .show(ctx, |ui| {
-            ui.menu_button("Back to Main Menu", |ui| {
-                if ui.button("Confirm").clicked() {
-                    ui.close_menu();
-                    GoResponse::MainMenu
-                } else {
-                    GoResponse::None
+pub fn state_go(
+    ctx: &egui::Context,
+    go_game: &mut emi_go::Game,
+    view: &mut GoView,
+    mut session: Option<&mut Connection>,
+) -> GoResponse {
+    if let Some(session) = &mut session {
+        for event in session.poll(go_game, &mut view.chat) {
+            match event {
+                NetEvent::Connected | NetEvent::Moved(_) => view.notice = None,
+                NetEvent::Disconnected => view.notice = Some("Connection lost".to_string()),
+                NetEvent::Resynced => {
+                    view.notice = Some("Games differed; using the host's".to_string());
                 }
+                NetEvent::Rejected(reason) => view.notice = Some(reason),
+                // Added to the chat while polling
+                NetEvent::Chat(_) => {}
+                // The comment box shows the game's comment as it is
+                NetEvent::Commented { .. } => {}
+            }
+        }
+        // Keep polling even when nothing is happening locally
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+    // Stones can only be placed on this side's turn in network games
+    let can_move = session.as_ref().is_none_or(|s| s.can_play(go_game));
+    // Edits are not part of the protocol, nor kept in reviewed game trees
+    let can_edit = session.is_none() && view.review.is_none();
+    view.update_marking(go_game, can_edit);
+
+    // Pasting anywhere but a text box sets up the position
+    if can_edit && ctx.memory(|m| m.focus().is_none()) {
+        let pasted = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
             })
-            .inner
-            .unwrap_or(GoResponse::None)
+        });
+        if let Some(text) = pasted {
+            view.notice = paste_position(go_game, &text).err();
+        }
+    }
+    if go_game.clock().is_some() && !go_game.is_over() {
+        let elapsed = ctx.input(|i| i.unstable_dt);
+        go_game.tick_clock(Duration::from_secs_f32(elapsed.max(0.0)));
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
+    let resp = egui::TopBottomPanel::top("game_go_menu")
+        .show(ctx, |ui| {
+            let mut resp = GoResponse::None;
+            ui.horizontal(|ui| {
+                ui.menu_button("Back to Main Menu", |ui| {
+                    if ui.button("Confirm").clicked() {
+                        ui.close_menu();
+                        resp = GoResponse::MainMenu;
+                    }
+                });
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.menu_button("File", |ui| {
+                    // Opening a file would leave the network game
+                    let open = egui::Button::new("Open SGF...");
+                    if ui.add_enabled(session.is_none(), open).clicked() {
+                        ui.close_menu();
+                        let picked = rfd::FileDialog::new()
+                            .add_filter("SGF", &["sgf"])
+                            .pick_file();
+                        if let Some(path) = picked {
+                            resp = GoResponse::Open(path);
+                        }
+                    }
+                    if ui.button("Save SGF...").clicked() {
+                        ui.close_menu();
+                        save_sgf(go_game, view);
+                    }
+                });
+                ui.menu_button("Position", |ui| {
+                    if ui.button("Copy as SGF").clicked() {
+                        ui.close_menu();
+                        ui.output_mut(|o| o.copied_text = go_game.position_to_sgf());
+                    }
+                    if ui.button("Copy as diagram").clicked() {
+                        ui.close_menu();
+                        let text = diagram::to_ascii(go_game.board());
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui
+                        .add_enabled(can_edit, egui::Button::new("Paste"))
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        let pasted =
+                            clipboard_text().and_then(|text| paste_position(go_game, &text));
+                        view.notice = pasted.err();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.menu_button("Export image", |ui| {
+                        ui.label("Area, eg. A1:J10, or empty for the whole board");
+                        ui.text_edit_singleline(&mut view.export_window);
+                        if ui.button("Save as...").clicked() {
+                            ui.close_menu();
+                            export_image(go_game, view);
+                        }
+                    });
+                });
+            });
+            resp
         })
         .inner;
 
@@ -231,6 +1471,11 @@ pub fn state_go(ctx: &egui::Context, go_game: &mut emi_go::Game) -> GoResponse {
         .resizable(false)
         .show(ctx, |ui| {
             ui.add_space(5.0);
+            if view.setup.is_some() {
+                setup_ui(ui, go_game, &mut view.setup);
+                return;
+            }
+
             // Something to show who is going next
             // TODO: improve side ui
             Frame::none().outer_margin(2.0).show(ui, |ui| {
@@ -262,50 +1507,209 @@ pub fn state_go(ctx: &egui::Context, go_game: &mut emi_go::Game) -> GoResponse {
                 ));
             });
 
-            if ui.button("Pass").clicked() {
-                let _ = go_game.play_(Move::Pass);
+            if let Some(session) = &session {
+                network_ui(ui, session);
+            }
+            if let Some(notice) = &view.notice {
+                ui.colored_label(ui.visuals().warn_fg_color, notice);
+            }
+            clock_ui(ui, go_game);
+            if view.marking.is_some() {
+                marking_ui(ui, go_game, &mut view.marking);
+            } else {
+                result_ui(ui, go_game);
+            }
+
+            let can_pass = can_move && view.marking.is_none();
+            if ui
+                .add_enabled(can_pass, egui::Button::new("Pass"))
+                .clicked()
+            {
+                let review = view.review.as_mut();
+                if let Err(e) = play_local(go_game, session.as_deref_mut(), review, Move::Pass) {
+                    view.notice = Some(e.to_string());
+                }
             }
 
-            if ui.button("Undo").clicked() {
+            // Either player may resign at any time, but only for themselves
+            let (can_resign, resigning) = match &session {
+                Some(session) => {
+                    let color = session.local_color();
+                    (session.is_connected() && color != Piece::None, color)
+                }
+                None => (true, go_game.next_to_play()),
+            };
+            let can_resign = can_resign && view.review.is_none();
+            ui.add_enabled_ui(go_game.result().is_none() && can_resign, |ui| {
+                ui.menu_button("Resign", |ui| {
+                    if ui.button("Confirm").clicked() {
+                        ui.close_menu();
+                        let move_ = Move::Resign { color: resigning };
+                        if let Err(e) = play_local(go_game, session.as_deref_mut(), None, move_) {
+                            view.notice = Some(e.to_string());
+                        }
+                    }
+                });
+            });
+
+            // Taking back moves is not part of the protocol, and reviews
+            // step back through the tree instead
+            let can_undo = session.is_none() && view.review.is_none();
+            if ui
+                .add_enabled(can_undo, egui::Button::new("Undo"))
+                .clicked()
+            {
                 // This will be the api:
                 // go_game.pass_turn(go_game.next_to_play());
                 //
                 // But for now...
                 go_game.undo();
             }
+            if ui
+                .add_enabled(can_edit, egui::Button::new("Set up position"))
+                .clicked()
+            {
+                view.setup = Some(Setup::new(go_game));
+            }
+
+            ui.separator();
+            ui.checkbox(&mut view.show_ownership, "Show territory");
+            ui.checkbox(&mut view.show_ladders, "Show ladders");
+
+            egui::ComboBox::from_label("Theme")
+                .selected_text(&view.theme().name)
+                .show_ui(ui, |ui| {
+                    for (i, theme) in view.themes.iter().enumerate() {
+                        ui.selectable_value(&mut view.theme, i, &theme.name);
+                    }
+                });
+
+            ui.label("Coordinates");
+            ui.radio_value(&mut view.coordinates, Coordinates::Off, "Off");
+            ui.radio_value(&mut view.coordinates, Coordinates::Western, "A1");
+            ui.radio_value(&mut view.coordinates, Coordinates::Sgf, "SGF (aa)");
+
+            ui.label("Move numbers");
+            let last_n = match view.move_numbers {
+                MoveNumbers::Last(n) => n,
+                _ => 10,
+            };
+            ui.radio_value(&mut view.move_numbers, MoveNumbers::Off, "Off");
+            ui.radio_value(&mut view.move_numbers, MoveNumbers::All, "All");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut view.move_numbers, MoveNumbers::Last(last_n), "Last");
+                let mut n = last_n;
+                if ui
+                    .add(egui::widgets::DragValue::new(&mut n).clamp_range(1..=999))
+                    .changed()
+                {
+                    view.move_numbers = MoveNumbers::Last(n);
+                }
+            });
+
+            ui.separator();
+            ui.label("Tool");
+            for tool in Tool::ALL {
+                ui.selectable_value(&mut view.tool, tool, tool.name());
+            }
+            if ui.button("Clear marks").clicked() {
+                go_game.clear_marks();
+            }
+
+            ui.separator();
+            comment_ui(ui, go_game, session.as_deref_mut());
         });
 
+    if view.review.is_some() {
+        egui::TopBottomPanel::bottom("game_go_review")
+            .resizable(true)
+            .show(ctx, |ui| review_ui(ui, go_game, view));
+    }
+
+    if let Some(session) = &mut session {
+        egui::TopBottomPanel::bottom("game_go_chat")
+            .resizable(true)
+            .show(ctx, |ui| chat_ui(ui, view, session));
+    }
+
     egui::CentralPanel::default().show(ctx, |ui| {
         Frame::canvas(ui.style())
             .rounding(0.0)
             .fill(Color32::TRANSPARENT)
             .stroke(Stroke::default())
             .show(ui, |ui| {
-                let width = ui.available_width();
-                let height = ui.available_height().min(800.0);
-
-                let (response, painter) = ui.allocate_painter(vec2(width, height), Sense::click());
+                let theme = view.theme().clone();
+                let (response, painter, grid_rect) =
+                    board_ui(ui, go_game, &theme, view.coordinates);
+                let move_numbers = view.move_numbers;
 
-                let to_screen = emath::RectTransform::from_to(
-                    Rect::from_min_size(Pos2::ZERO, response.rect.size()),
-                    response.rect,
-                );
-
-                let board_size = width.min(height);
+                // Setup mode shows and changes its own copy of the game
+                if let Some(setup) = &mut view.setup {
+                    game_go_display_pieces(
+                        &setup.game,
+                        &theme,
+                        None,
+                        move_numbers,
+                        &painter,
+                        grid_rect,
+                    );
+                    let clicked = interaction_go(
+                        &mut setup.game,
+                        Tool::Play,
+                        &theme,
+                        grid_rect,
+                        &painter,
+                        &response,
+                    );
+                    if let Some(Move::Place { pos, .. }) = clicked {
+                        setup.place(pos);
+                    }
+                    return;
+                }
 
-                let board_rect = Rect::from_center_size(
-                    to_screen.transform_pos(Pos2::new(width / 2.0, height / 2.0)),
-                    vec2(board_size, board_size),
+                let ownership = view.update_ownership(go_game);
+                game_go_display_pieces(
+                    go_game,
+                    &theme,
+                    ownership,
+                    move_numbers,
+                    &painter,
+                    grid_rect,
                 );
+                if view.is_estimating() {
+                    ctx.request_repaint();
+                }
+                let ladders = view.update_ladders(go_game);
+                draw_ladders(ladders, go_game.board(), &painter, grid_rect);
 
-                // Draw go board
-                draw_go_board(go_game, &painter, board_rect);
-                game_go_display_pieces(go_game, &painter, board_rect);
+                // Clicks mark groups dead while counting
+                if let Some(marking) = &mut view.marking {
+                    if marking.refine(go_game) {
+                        ctx.request_repaint();
+                    }
+                    draw_marking(marking, go_game, &theme, &painter, grid_rect);
+                    let clicked =
+                        interaction_go(go_game, Tool::Play, &theme, grid_rect, &painter, &response);
+                    if let Some(Move::Place { pos, .. }) = clicked {
+                        marking.toggle(go_game, pos);
+                    }
+                    return;
+                }
 
                 // Handle interaction
-                interaction_go(go_game, board_rect, &painter, &response);
+                let clicked =
+                    interaction_go(go_game, view.tool, &theme, grid_rect, &painter, &response);
+                if let Some(move_) = clicked.filter(|_| can_move) {
+                    if let Err(e) = play_local(go_game, session, view.review.as_mut(), move_) {
+                        view.notice = Some(e.to_string());
+                    }
+                }
             });
     });
 
+    if let Some(review) = &mut view.review {
+        review.sync_markup(go_game);
+    }
+
     resp
 }
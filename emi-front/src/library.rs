@@ -0,0 +1,250 @@
+//! Saved games, kept as SGF files in `games/` in the user's data directory.
+//!
+//! Games are saved there when going back to the main menu or closing the
+//! window, and any other SGF files copied into the folder show up in the
+//! library too. The game that was open when the window closed is resumed on
+//! the next launch.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use emi_go::{Game, SgfError};
+
+use crate::review::Review;
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+/// The name of the file holding the path of the game to resume.
+const RESUME_FILE: &str = "resume";
+
+/// A saved game, as listed in the library.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// When the game was played, or else when the file was last changed.
+    pub date: String,
+    pub black: String,
+    pub white: String,
+    pub board_size: u32,
+    pub moves: u32,
+    /// The result in SGF form, eg. `W+R`, if the game is over.
+    pub result: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+    /// There is nowhere to keep games, eg. on the web.
+    NoDataDir,
+    Io(io::Error),
+    Sgf(SgfError),
+}
+
+impl std::fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoDataDir => write!(f, "No data directory to save games in"),
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Sgf(e) => write!(f, "Could not read game: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {}
+
+impl From<io::Error> for LibraryError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<SgfError> for LibraryError {
+    fn from(e: SgfError) -> Self {
+        Self::Sgf(e)
+    }
+}
+
+/// Where emi keeps its data.
+///
+/// This is `$EMI_DATA` if set, otherwise `emi` in the user's data
+/// directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn data_dir() -> Option<PathBuf> {
+    use std::env;
+
+    if let Some(path) = env::var_os("EMI_DATA") {
+        return Some(PathBuf::from(path));
+    }
+    let data = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if let Some(xdg) = env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(xdg)
+    } else {
+        PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("share")
+    };
+    Some(data.join("emi"))
+}
+
+/// There is no file system on the web.
+#[cfg(target_arch = "wasm32")]
+pub fn data_dir() -> Option<PathBuf> {
+    None
+}
+
+/// The folder saved games are kept in.
+pub fn games_dir() -> Option<PathBuf> {
+    Some(data_dir()?.join("games"))
+}
+
+/// Every game in the library, newest first. Files that can not be read are
+/// skipped.
+pub fn list() -> Vec<Entry> {
+    let Some(dir) = games_dir() else {
+        return vec![];
+    };
+    let read_dir = match fs::read_dir(&dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Could not read {}: {}", dir.display(), e);
+            }
+            return vec![];
+        }
+    };
+
+    let mut entries: Vec<(SystemTime, Entry)> = read_dir
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("sgf"))
+        })
+        .filter_map(|path| {
+            let game = match fs::read_to_string(&path)
+                .map_err(LibraryError::from)
+                .and_then(|text| Ok(Game::from_sgf(&text)?))
+            {
+                Ok(game) => game,
+                Err(e) => {
+                    warn!("Skipping {}: {}", path.display(), e);
+                    return None;
+                }
+            };
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH);
+            let info = game.info();
+            let date = if info.date.is_empty() {
+                date_of(modified)
+            } else {
+                info.date.clone()
+            };
+            let entry = Entry {
+                date,
+                black: info.black_name.clone(),
+                white: info.white_name.clone(),
+                board_size: game.board_size(),
+                moves: game.moves_played(),
+                result: game.result().map(|r| r.to_string()),
+                path,
+            };
+            Some((modified, entry))
+        })
+        .collect();
+    entries.sort_by_key(|&(modified, _)| std::cmp::Reverse(modified));
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Open a saved game, for review if it has variations.
+pub fn load(path: &Path) -> Result<(Game, Option<Review>), LibraryError> {
+    let text = fs::read_to_string(path)?;
+    Ok(Review::open(&text, false)?)
+}
+
+/// Save `sgf` to `file`, or to a new file in the library if it has none
+/// yet.
+pub fn save(sgf: &str, file: &mut Option<PathBuf>) -> Result<(), LibraryError> {
+    let path = match file {
+        Some(path) => path.clone(),
+        None => {
+            let dir = games_dir().ok_or(LibraryError::NoDataDir)?;
+            fs::create_dir_all(&dir)?;
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let mut path = dir.join(format!("game-{secs}.sgf"));
+            let mut n = 1;
+            while path.exists() {
+                path = dir.join(format!("game-{secs}-{n}.sgf"));
+                n += 1;
+            }
+            path
+        }
+    };
+    fs::write(&path, sgf)?;
+    debug!("Saved game to {}", path.display());
+    *file = Some(path);
+    Ok(())
+}
+
+/// Remember `file` as the game to open on the next launch, or forget it.
+pub fn set_resume(file: Option<&Path>) -> Result<(), LibraryError> {
+    let resume = data_dir().ok_or(LibraryError::NoDataDir)?.join(RESUME_FILE);
+    match file {
+        Some(file) => {
+            fs::create_dir_all(resume.parent().expect("Joined above"))?;
+            fs::write(resume, file.to_string_lossy().as_bytes())?;
+        }
+        None => match fs::remove_file(resume) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+/// The game that was open when emi last closed, and where it is saved.
+pub fn resume() -> Option<(Game, Option<Review>, PathBuf)> {
+    let resume = data_dir()?.join(RESUME_FILE);
+    let path = PathBuf::from(fs::read_to_string(resume).ok()?.trim());
+    match load(&path) {
+        Ok((game, review)) => {
+            info!("Resuming {}", path.display());
+            Some((game, review, path))
+        }
+        Err(e) => {
+            warn!("Could not resume {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Today's date, for dating new games.
+pub fn today() -> String {
+    date_of(SystemTime::now())
+}
+
+/// A time as `YYYY-MM-DD` in UTC, the way SGF writes dates.
+fn date_of(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The calendar date `days` after 1970-01-01, from Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
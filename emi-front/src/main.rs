@@ -3,7 +3,12 @@ use tracing::info;
 
 mod app;
 mod game_go;
+mod library;
 mod menu;
+mod online;
+mod review;
+mod theme;
+mod tsumego;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> color_eyre::eyre::Result<()> {
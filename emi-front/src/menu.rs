@@ -1,16 +1,65 @@
-use eframe::egui::{self, Button, Context};
+use eframe::egui::{self, Button, Context, Ui};
+use emi_go::net::{Session, DEFAULT_PORT};
+use emi_go::{Piece, TimeControl};
+use emi_server::protocol::Seat;
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{error, info};
 
-#[derive(Debug, Clone)]
+use crate::library;
+use crate::online::{Client, Connection};
+use crate::review::Review;
+use crate::tsumego::Problems;
+
+#[derive(Debug)]
 pub enum MenuResponse {
     None,
-    CreateGoGame { game: emi_go::Game },
+    CreateGoGame {
+        game: Box<emi_go::Game>,
+        /// The connection to the other player in network games.
+        session: Option<Box<Connection>>,
+    },
+    /// Open a game from the library.
+    LoadGoGame {
+        game: Box<emi_go::Game>,
+        /// The game tree, if the game has variations.
+        review: Option<Box<Review>>,
+        /// Where the game is saved.
+        file: PathBuf,
+    },
+    /// Work through a collection of problems.
+    Tsumego {
+        problems: Box<Problems>,
+    },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Menu {
     Main {},
-    Go { board_size: u32, ko_type: KoType },
+    Go {
+        board_size: u32,
+        ko_type: KoType,
+        time_control: TimeControl,
+        /// Points White gets for moving second.
+        komi: f32,
+        network: Network,
+        /// Why the last attempt to host or join failed.
+        network_error: Option<String>,
+    },
+    /// Rooms on a server to pick from, or open.
+    Online {
+        client: Box<Client>,
+        /// The name for a new room.
+        room_name: String,
+        board_size: u32,
+        seat: Seat,
+    },
+    /// Saved games to pick from.
+    Library {
+        entries: Vec<library::Entry>,
+        /// Why the last game picked could not be opened.
+        error: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -18,27 +67,116 @@ pub enum KoType {
     SimpleKo,
 }
 
+/// Where the other player is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Network {
+    /// Both players share this computer.
+    Local,
+    /// Wait for the other player to join on `port`.
+    Host { port: u16, color: Piece },
+    /// Join a game hosted at `address`, eg. `192.168.1.20:7650`.
+    Join { address: String },
+    /// Play in a room on an `emi-server` at `address`, eg.
+    /// `example.com:7651`.
+    Server { address: String },
+}
+
 impl Menu {
     #[must_use]
     /// Currently just a test method
     pub fn draw(&mut self, ctx: &Context) -> MenuResponse {
         match self {
             Self::Main {} => {
-                egui::CentralPanel::default().show(ctx, |ui| {
+                let problems = egui::CentralPanel::default().show(ctx, |ui| {
+                    let mut problems = None;
                     if ui.button("Create Go Game").clicked() {
                         *self = Self::Go {
                             board_size: 19,
                             ko_type: KoType::SimpleKo,
+                            time_control: TimeControl::None,
+                            komi: 7.5,
+                            network: Network::Local,
+                            network_error: None,
                         };
                     }
+                    if ui.button("Load Game").clicked() {
+                        *self = Self::Library {
+                            entries: library::list(),
+                            error: None,
+                        };
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Solve Problems").clicked() {
+                        let picked = rfd::FileDialog::new()
+                            .add_filter("SGF", &["sgf"])
+                            .pick_file();
+                        match picked.map(|path| Problems::open(&path)) {
+                            Some(Ok(opened)) => problems = Some(Box::new(opened)),
+                            Some(Err(e)) => error!("Could not open problems: {}", e),
+                            None => {}
+                        }
+                    }
+                    problems
                 });
+                if let Some(problems) = problems.inner {
+                    return MenuResponse::Tsumego { problems };
+                }
             }
-            Self::Go { .. } => {
-                if self.draw_menu_go(ctx) {
+            Self::Library { entries, error } => {
+                if let Some(response) = library_ui(ctx, entries, error) {
+                    return response;
+                }
+            }
+            Self::Online {
+                client,
+                room_name,
+                board_size,
+                seat,
+            } => {
+                if let Some(game) = client.poll_lobby() {
+                    let Self::Online { client, .. } = std::mem::replace(self, Self::Main {}) else {
+                        unreachable!()
+                    };
                     return MenuResponse::CreateGoGame {
-                        game: self.create_go_game(),
+                        game: Box::new(game),
+                        session: Some(Box::new(Connection::Server(client))),
                     };
                 }
+                online_ui(ctx, client, room_name, board_size, seat);
+                // Keep polling even when nothing is happening locally
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+            Self::Go { .. } => {
+                if self.draw_menu_go(ctx) {
+                    if let Self::Go {
+                        board_size,
+                        network: Network::Server { address },
+                        ..
+                    } = self
+                    {
+                        *self = Self::Online {
+                            client: Box::new(Client::connect(address)),
+                            room_name: String::new(),
+                            board_size: *board_size,
+                            seat: Seat::Black,
+                        };
+                        return MenuResponse::None;
+                    }
+                    match self.connect() {
+                        Ok(session) => {
+                            return MenuResponse::CreateGoGame {
+                                game: Box::new(self.create_go_game()),
+                                session: session.map(|s| Box::new(Connection::Peer(s))),
+                            };
+                        }
+                        Err(e) => {
+                            error!("Could not start network game: {}", e);
+                            if let Self::Go { network_error, .. } = self {
+                                *network_error = Some(e.to_string());
+                            }
+                        }
+                    }
+                }
             }
         }
         MenuResponse::None
@@ -47,17 +185,45 @@ impl Menu {
     pub fn draw_menu_go(&mut self, ctx: &Context) -> bool {
         let Self::Go {
             board_size,
+            time_control,
+            komi,
+            network,
+            network_error,
             ..
-        } = self else { unreachable!() };
+        } = self
+        else {
+            unreachable!()
+        };
 
         egui::CentralPanel::default()
             .show(ctx, |ui| {
                 ui.heading("Create Go Game");
 
-                ui.horizontal(|ui| {
-                    ui.label("Go board size (drag): ");
-                    ui.add(egui::widgets::DragValue::new(board_size).clamp_range(9..=19));
-                });
+                network_ui(ui, network);
+                if let Some(e) = network_error {
+                    ui.colored_label(ui.visuals().error_fg_color, e.as_str());
+                }
+
+                // The host picks the board size, and rooms are opened later
+                if matches!(network, Network::Local | Network::Host { .. }) {
+                    ui.horizontal(|ui| {
+                        ui.label("Go board size (drag): ");
+                        ui.add(egui::widgets::DragValue::new(board_size).clamp_range(9..=19));
+                    });
+                }
+
+                // Clocks and komi are not shared over the network
+                if *network == Network::Local {
+                    ui.horizontal(|ui| {
+                        ui.label("Komi: ");
+                        ui.add(
+                            egui::widgets::DragValue::new(komi)
+                                .speed(0.5)
+                                .clamp_range(-50.0..=50.0),
+                        );
+                    });
+                    time_control_ui(ui, time_control);
+                }
 
                 if ui.add(Button::new("Start")).clicked() {
                     info!("Starting go game!");
@@ -70,11 +236,351 @@ impl Menu {
 
     pub fn create_go_game(&self) -> emi_go::Game {
         use emi_go::Game;
-        let Self::Go { board_size, .. } = self else {
+        let Self::Go {
+            board_size,
+            time_control,
+            komi,
+            network,
+            ..
+        } = self
+        else {
             error!("Tried to create a go game while not in go menu; Creating size 19 game.");
             return Game::new(19);
         };
         // TODO: handle ko types
-        Game::new(*board_size)
+        match network {
+            Network::Local => {
+                let mut game = Game::with_time_control(*board_size, *time_control);
+                game.info_mut().komi = *komi;
+                game
+            }
+            _ => Game::new(*board_size),
+        }
     }
+
+    /// Host or join a game, if one was picked.
+    fn connect(&self) -> std::io::Result<Option<Session>> {
+        let Self::Go { network, .. } = self else {
+            return Ok(None);
+        };
+        match network {
+            Network::Local | Network::Server { .. } => Ok(None),
+            Network::Host { port, color } => Session::host(("0.0.0.0", *port), *color).map(Some),
+            Network::Join { address } => {
+                let address = address.trim();
+                let has_port = address
+                    .rsplit_once(':')
+                    .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+                if has_port {
+                    Session::join(address).map(Some)
+                } else {
+                    Session::join((address, DEFAULT_PORT)).map(Some)
+                }
+            }
+        }
+    }
+}
+
+/// List saved games, returning the one that was opened.
+fn library_ui(
+    ctx: &Context,
+    entries: &[library::Entry],
+    error: &mut Option<String>,
+) -> Option<MenuResponse> {
+    let mut picked = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Load Game");
+        if let Some(e) = error {
+            ui.colored_label(ui.visuals().error_fg_color, e.as_str());
+        }
+        if entries.is_empty() {
+            match library::games_dir() {
+                Some(dir) => ui.label(format!("No saved games in {}", dir.display())),
+                None => ui.label("Games can not be saved here"),
+            };
+            return;
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("library")
+                .striped(true)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    for heading in ["Date", "Black", "White", "Size", "Moves", "Result", ""] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+
+                    let name = |name: &str| if name.is_empty() { "?" } else { name }.to_string();
+                    for entry in entries {
+                        ui.label(&entry.date);
+                        ui.label(name(&entry.black));
+                        ui.label(name(&entry.white));
+                        ui.label(format!("{0}x{0}", entry.board_size));
+                        ui.label(entry.moves.to_string());
+                        ui.label(entry.result.as_deref().unwrap_or("-"));
+                        if ui.button("Open").clicked() {
+                            picked = Some(entry.path.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    });
+
+    let path = picked?;
+    match library::load(&path) {
+        Ok((game, review)) => Some(MenuResponse::LoadGoGame {
+            game: Box::new(game),
+            review: review.map(Box::new),
+            file: path,
+        }),
+        Err(e) => {
+            error!("Could not open {}: {}", path.display(), e);
+            *error = Some(e.to_string());
+            None
+        }
+    }
+}
+
+/// Pick between a local game, hosting, joining, or a server.
+fn network_ui(ui: &mut Ui, network: &mut Network) {
+    ui.horizontal(|ui| {
+        let local = *network == Network::Local;
+        let host = matches!(network, Network::Host { .. });
+        let join = matches!(network, Network::Join { .. });
+        let server = matches!(network, Network::Server { .. });
+        if ui.radio(local, "Local").clicked() && !local {
+            *network = Network::Local;
+        }
+        if ui.radio(host, "Host").clicked() && !host {
+            *network = Network::Host {
+                port: DEFAULT_PORT,
+                color: Piece::Black,
+            };
+        }
+        if ui.radio(join, "Join").clicked() && !join {
+            *network = Network::Join {
+                address: String::new(),
+            };
+        }
+        if ui.radio(server, "Server").clicked() && !server {
+            *network = Network::Server {
+                address: format!("localhost:{}", emi_server::DEFAULT_PORT),
+            };
+        }
+    });
+
+    match network {
+        Network::Local => {}
+        Network::Host { port, color } => {
+            ui.horizontal(|ui| {
+                ui.label("Port: ");
+                ui.add(egui::widgets::DragValue::new(port).clamp_range(1024..=u16::MAX));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Play as: ");
+                ui.radio_value(color, Piece::Black, "Black");
+                ui.radio_value(color, Piece::White, "White");
+            });
+        }
+        Network::Join { address } => {
+            ui.horizontal(|ui| {
+                ui.label("Host address: ");
+                ui.text_edit_singleline(address);
+            });
+        }
+        Network::Server { address } => {
+            ui.horizontal(|ui| {
+                ui.label("Server address: ");
+                ui.text_edit_singleline(address);
+            });
+        }
+    }
+}
+
+/// List the rooms on a server to join, and open new ones.
+fn online_ui(
+    ctx: &Context,
+    client: &mut Client,
+    room_name: &mut String,
+    board_size: &mut u32,
+    seat: &mut Seat,
+) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading(format!("Rooms on {}", client.address()));
+        if let Some(reason) = client.closed() {
+            ui.colored_label(ui.visuals().error_fg_color, reason);
+            if ui.button("Reconnect").clicked() {
+                *client = Client::connect(client.address());
+            }
+            return;
+        }
+        if !client.is_open() {
+            ui.label("Connecting...");
+            return;
+        }
+        if let Some(e) = client.error() {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Room name: ");
+            ui.text_edit_singleline(room_name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Go board size (drag): ");
+            ui.add(egui::widgets::DragValue::new(board_size).clamp_range(9..=19));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Play as: ");
+            ui.radio_value(seat, Seat::Black, "Black");
+            ui.radio_value(seat, Seat::White, "White");
+        });
+        if ui.button("Open room").clicked() {
+            client.create_room(room_name.trim(), *board_size, *seat);
+        }
+
+        ui.separator();
+        if ui.button("Refresh").clicked() {
+            client.list_rooms();
+        }
+        if client.rooms().is_empty() {
+            ui.label("No open rooms");
+            return;
+        }
+
+        let mut picked = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("rooms")
+                .striped(true)
+                .spacing([20.0, 4.0])
+                .show(ui, |ui| {
+                    for heading in ["Room", "Size", "Moves", "Watching", "Result", ""] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+
+                    for room in client.rooms() {
+                        ui.label(&room.name);
+                        ui.label(format!("{0}x{0}", room.board_size));
+                        ui.label(room.moves.to_string());
+                        ui.label(room.spectators.to_string());
+                        ui.label(room.result.as_deref().unwrap_or("-"));
+                        ui.horizontal(|ui| {
+                            for (taken, seat, name) in [
+                                (room.black, Seat::Black, "Play Black"),
+                                (room.white, Seat::White, "Play White"),
+                                (false, Seat::Spectator, "Watch"),
+                            ] {
+                                if ui.add_enabled(!taken, Button::new(name)).clicked() {
+                                    picked = Some((room.id, seat));
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+        if let Some((room, seat)) = picked {
+            client.join(room, seat);
+        }
+    });
+}
+
+/// Pick the kind of time control and its settings.
+fn time_control_ui(ui: &mut Ui, time_control: &mut TimeControl) {
+    let minutes = Duration::from_secs(60);
+    let seconds = Duration::from_secs(1);
+    let presets = [
+        ("Untimed", TimeControl::None),
+        ("Absolute", TimeControl::Absolute { main: minutes * 30 }),
+        (
+            "Byo-yomi",
+            TimeControl::ByoYomi {
+                main: minutes * 10,
+                periods: 5,
+                period: seconds * 30,
+            },
+        ),
+        (
+            "Canadian",
+            TimeControl::Canadian {
+                main: minutes * 10,
+                stones: 25,
+                period: minutes * 5,
+            },
+        ),
+        (
+            "Fischer",
+            TimeControl::Fischer {
+                main: minutes * 5,
+                increment: seconds * 10,
+            },
+        ),
+    ];
+
+    let kind = |control: &TimeControl| std::mem::discriminant(control);
+    let selected = presets
+        .iter()
+        .find(|(_, preset)| kind(preset) == kind(time_control))
+        .map_or("Untimed", |(name, _)| name);
+    egui::ComboBox::from_label("Time control")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (name, preset) in presets {
+                if ui.selectable_label(selected == name, name).clicked() {
+                    *time_control = preset;
+                }
+            }
+        });
+
+    match time_control {
+        TimeControl::None => {}
+        TimeControl::Absolute { main } => {
+            duration_ui(ui, "Main time (minutes): ", main, minutes);
+        }
+        TimeControl::ByoYomi {
+            main,
+            periods,
+            period,
+        } => {
+            duration_ui(ui, "Main time (minutes): ", main, minutes);
+            ui.horizontal(|ui| {
+                ui.label("Periods: ");
+                ui.add(egui::widgets::DragValue::new(periods).clamp_range(1..=30));
+            });
+            duration_ui(ui, "Period (seconds): ", period, seconds);
+        }
+        TimeControl::Canadian {
+            main,
+            stones,
+            period,
+        } => {
+            duration_ui(ui, "Main time (minutes): ", main, minutes);
+            ui.horizontal(|ui| {
+                ui.label("Stones per period: ");
+                ui.add(egui::widgets::DragValue::new(stones).clamp_range(1..=100));
+            });
+            duration_ui(ui, "Period (minutes): ", period, minutes);
+        }
+        TimeControl::Fischer { main, increment } => {
+            duration_ui(ui, "Main time (minutes): ", main, minutes);
+            duration_ui(ui, "Increment (seconds): ", increment, seconds);
+        }
+    }
+}
+
+/// Edit a duration as a whole number of `unit`s.
+fn duration_ui(ui: &mut Ui, label: &str, duration: &mut Duration, unit: Duration) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut units = duration.as_secs() / unit.as_secs();
+        if ui
+            .add(egui::widgets::DragValue::new(&mut units).clamp_range(0..=600))
+            .changed()
+        {
+            *duration = unit * units as u32;
+        }
+    });
 }
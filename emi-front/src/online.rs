@@ -0,0 +1,652 @@
+//! Games on an `emi-server`, which works from the browser too.
+//!
+//! A [`Client`] first sits in the lobby, listing rooms until one is opened
+//! or joined. From then on it keeps the room's game in sync the same way a
+//! [`Session`] does for local network games, and [`Connection`] lets the
+//! board treat both alike.
+//!
+//! The server sends every move back to everyone in the room, including
+//! whoever played it. Moves played here are checked against that echo
+//! rather than played twice, and any disagreement is settled by joining
+//! the room again, which sends the whole game.
+
+use emi_go::net::{NetEvent, Session};
+use emi_go::{Event, Game, GameResult, Move, Piece};
+use emi_server::protocol::{ClientMessage, NodeComment, RoomId, RoomInfo, Seat, ServerMessage};
+use emi_server::DEFAULT_PORT;
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+use socket::Socket;
+
+/// Something that happened on a [`Socket`].
+#[derive(Debug, Clone, PartialEq)]
+enum SocketEvent {
+    Opened,
+    Message(String),
+    /// The connection closed, or could not be opened, for this reason.
+    Closed(String),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod socket {
+    use std::io;
+    use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+    use std::time::Duration;
+
+    use tungstenite::stream::MaybeTlsStream;
+    use tungstenite::Message;
+
+    use super::SocketEvent;
+
+    /// How long reading waits for a message before checking for ones to
+    /// send.
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// A WebSocket run on its own thread, so connecting and waiting for
+    /// messages never hold up drawing.
+    #[derive(Debug)]
+    pub struct Socket {
+        outbox: Sender<String>,
+        inbox: Receiver<SocketEvent>,
+    }
+
+    impl Socket {
+        pub fn connect(url: String) -> Self {
+            let (outbox, outgoing) = mpsc::channel();
+            let (incoming, inbox) = mpsc::channel();
+            std::thread::spawn(move || {
+                let reason = match run(&url, &outgoing, &incoming) {
+                    Ok(()) | Err(tungstenite::Error::ConnectionClosed) => {
+                        "Connection closed".to_string()
+                    }
+                    Err(e) => e.to_string(),
+                };
+                // Nobody is listening any more if this fails
+                let _ = incoming.send(SocketEvent::Closed(reason));
+            });
+            Self { outbox, inbox }
+        }
+
+        pub fn send(&mut self, text: String) {
+            // The thread reports why it stopped if this fails
+            let _ = self.outbox.send(text);
+        }
+
+        pub fn receive(&mut self) -> Vec<SocketEvent> {
+            self.inbox.try_iter().collect()
+        }
+    }
+
+    /// Pass messages back and forth until either side closes.
+    #[allow(clippy::result_large_err)]
+    fn run(
+        url: &str,
+        outgoing: &Receiver<String>,
+        incoming: &Sender<SocketEvent>,
+    ) -> tungstenite::Result<()> {
+        let (mut socket, _) = tungstenite::connect(url)?;
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_read_timeout(Some(POLL_INTERVAL))?;
+            stream.set_nodelay(true)?;
+        }
+        if incoming.send(SocketEvent::Opened).is_err() {
+            return Ok(());
+        }
+
+        loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    if incoming.send(SocketEvent::Message(text)).is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(Message::Close(_)) => return Ok(()),
+                // Pings are answered by tungstenite
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => return Err(e),
+            }
+
+            loop {
+                match outgoing.try_recv() {
+                    Ok(text) => socket.send(Message::Text(text))?,
+                    Err(TryRecvError::Empty) => break,
+                    // The client was dropped
+                    Err(TryRecvError::Disconnected) => return socket.close(None),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod socket {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+    use web_sys::{CloseEvent, MessageEvent, WebSocket};
+
+    use super::SocketEvent;
+
+    type Callback = Closure<dyn FnMut(web_sys::Event)>;
+
+    /// The browser's WebSocket, with its callbacks queueing events until
+    /// they are received.
+    #[derive(Debug)]
+    pub struct Socket {
+        socket: Option<WebSocket>,
+        events: Rc<RefCell<Vec<SocketEvent>>>,
+        /// Messages sent before the socket opened.
+        pending: Vec<String>,
+        /// Kept alive for as long as the socket can call them.
+        _callbacks: Vec<Callback>,
+    }
+
+    impl Socket {
+        pub fn connect(url: String) -> Self {
+            let events = Rc::new(RefCell::new(vec![]));
+            let socket = match WebSocket::new(&url) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    let reason = e
+                        .as_string()
+                        .unwrap_or_else(|| format!("Bad address {url}"));
+                    events.borrow_mut().push(SocketEvent::Closed(reason));
+                    return Self {
+                        socket: None,
+                        events,
+                        pending: vec![],
+                        _callbacks: vec![],
+                    };
+                }
+            };
+
+            let queue = |to_event: fn(web_sys::Event) -> SocketEvent| {
+                let events = Rc::clone(&events);
+                Callback::new(move |event| events.borrow_mut().push(to_event(event)))
+            };
+            let on_open = queue(|_| SocketEvent::Opened);
+            let on_message = queue(|event| {
+                let text = event
+                    .dyn_into::<MessageEvent>()
+                    .ok()
+                    .and_then(|message| message.data().as_string());
+                SocketEvent::Message(text.unwrap_or_default())
+            });
+            let on_close = queue(|event| {
+                let reason = event
+                    .dyn_into::<CloseEvent>()
+                    .map(|close| close.reason())
+                    .unwrap_or_default();
+                SocketEvent::Closed(if reason.is_empty() {
+                    "Connection closed".to_string()
+                } else {
+                    reason
+                })
+            });
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+            Self {
+                socket: Some(socket),
+                events,
+                pending: vec![],
+                _callbacks: vec![on_open, on_message, on_close],
+            }
+        }
+
+        pub fn send(&mut self, text: String) {
+            match &self.socket {
+                Some(socket) if socket.ready_state() == WebSocket::OPEN => {
+                    // The socket reports closing by itself if this fails
+                    let _ = socket.send_with_str(&text);
+                }
+                Some(_) => self.pending.push(text),
+                None => {}
+            }
+        }
+
+        pub fn receive(&mut self) -> Vec<SocketEvent> {
+            let events = std::mem::take(&mut *self.events.borrow_mut());
+            if events.contains(&SocketEvent::Opened) {
+                for text in std::mem::take(&mut self.pending) {
+                    self.send(text);
+                }
+            }
+            events
+        }
+    }
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            if let Some(socket) = &self.socket {
+                socket.set_onopen(None);
+                socket.set_onmessage(None);
+                socket.set_onclose(None);
+                let _ = socket.close();
+            }
+        }
+    }
+}
+
+/// A connection to an `emi-server`.
+#[derive(Debug)]
+pub struct Client {
+    socket: Socket,
+    /// The server's address, as typed.
+    address: String,
+    open: bool,
+    /// Why the connection closed, once it has.
+    closed: Option<String>,
+    /// The rooms from the last time they were listed.
+    rooms: Vec<RoomInfo>,
+    /// The room sat in, once joined.
+    room: Option<RoomInfo>,
+    seat: Seat,
+    /// The game of a room just joined from the lobby.
+    joined: Option<Game>,
+    /// How many events of the game the server has sent. Moves played here
+    /// after those are waiting to be sent back.
+    confirmed: usize,
+    /// The last error the server sent.
+    error: Option<String>,
+}
+
+impl Client {
+    /// Start connecting to a server at `address`, eg. `example.com:7651` or
+    /// `wss://example.com/emi`. The port defaults to [`DEFAULT_PORT`].
+    pub fn connect(address: &str) -> Self {
+        let address = address.trim().to_string();
+        let url = if address.starts_with("ws://") || address.starts_with("wss://") {
+            address.clone()
+        } else {
+            let has_port = address
+                .rsplit_once(':')
+                .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+            if has_port {
+                format!("ws://{address}")
+            } else {
+                format!("ws://{address}:{DEFAULT_PORT}")
+            }
+        };
+        info!("Connecting to {}", url);
+        Self {
+            socket: Socket::connect(url),
+            address,
+            open: false,
+            closed: None,
+            rooms: vec![],
+            room: None,
+            seat: Seat::Spectator,
+            joined: None,
+            confirmed: 0,
+            error: None,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open && self.closed.is_none()
+    }
+
+    /// Why the connection closed, if it has.
+    pub fn closed(&self) -> Option<&str> {
+        self.closed.as_deref()
+    }
+
+    /// The last error the server sent, eg. because a seat was taken.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// The open rooms, as of the last [`Client::list_rooms`].
+    pub fn rooms(&self) -> &[RoomInfo] {
+        &self.rooms
+    }
+
+    /// The room sat in, once joined.
+    pub fn room(&self) -> Option<&RoomInfo> {
+        self.room.as_ref()
+    }
+
+    /// The color played here, or `Piece::None` when watching.
+    pub fn local_color(&self) -> Piece {
+        self.seat.color()
+    }
+
+    /// Returns true if it is this side's turn to place a stone, and the
+    /// server has sent back every move played here.
+    pub fn can_play(&self, game: &Game) -> bool {
+        self.is_open()
+            && self.room.is_some()
+            && game.next_to_play() == self.local_color()
+            && game.events().len() == self.confirmed
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        let text = serde_json::to_string(message).expect("Messages always serialize");
+        self.socket.send(text);
+    }
+
+    pub fn list_rooms(&mut self) {
+        self.send(&ClientMessage::ListRooms);
+    }
+
+    /// Open a new room and sit down in it.
+    pub fn create_room(&mut self, name: &str, board_size: u32, seat: Seat) {
+        self.error = None;
+        self.send(&ClientMessage::CreateRoom {
+            name: name.to_string(),
+            board_size,
+            seat,
+        });
+    }
+
+    pub fn join(&mut self, room: RoomId, seat: Seat) {
+        self.error = None;
+        self.send(&ClientMessage::Join { room, seat });
+    }
+
+    /// Send the last move in `game` to the server. Call this after playing
+    /// a move on this side.
+    pub fn send_last_move(&mut self, game: &Game) {
+        let message = match game.events().last().and_then(Event::to_move) {
+            Some(Move::Place { pos: [x, y], .. }) => ClientMessage::Play { x, y },
+            Some(Move::Pass) => ClientMessage::Pass,
+            Some(Move::Resign { .. }) => ClientMessage::Resign,
+            None => return,
+        };
+        self.send(&message);
+    }
+
+    /// Send a chat message to everyone in the room. It shows up once the
+    /// server sends it back.
+    pub fn send_chat(&mut self, text: &str) {
+        self.send(&ClientMessage::Chat {
+            text: text.to_string(),
+        });
+    }
+
+    /// Send the comment on the current position of `game`.
+    pub fn send_comment(&mut self, game: &Game) {
+        self.send(&ClientMessage::Comment {
+            text: game.comment().to_string(),
+        });
+    }
+
+    /// Read what the server sent since last time, keeping track of the
+    /// connection and the room.
+    fn receive(&mut self) -> Vec<ServerMessage> {
+        let mut messages = vec![];
+        for event in self.socket.receive() {
+            match event {
+                SocketEvent::Opened => {
+                    info!("Connected to {}", self.address);
+                    self.open = true;
+                    self.list_rooms();
+                }
+                SocketEvent::Message(text) => match serde_json::from_str(&text) {
+                    Ok(message) => messages.push(message),
+                    Err(e) => warn!("Bad message from the server {:?}: {}", text, e),
+                },
+                SocketEvent::Closed(reason) => {
+                    warn!("Connection to {} closed: {}", self.address, reason);
+                    self.closed = Some(reason);
+                }
+            }
+        }
+
+        for message in &messages {
+            debug!("Received {:?}", message);
+            match message {
+                ServerMessage::Rooms { rooms } => self.rooms = rooms.clone(),
+                ServerMessage::Joined { room, seat, .. } => {
+                    self.room = Some(room.clone());
+                    self.seat = *seat;
+                }
+                ServerMessage::RoomChanged { room } => self.room = Some(room.clone()),
+                ServerMessage::Left => self.room = None,
+                ServerMessage::Error { message } => self.error = Some(message.clone()),
+                _ => {}
+            }
+        }
+        messages
+    }
+
+    /// Keep the lobby up to date, returning the game once a room is opened
+    /// or joined.
+    pub fn poll_lobby(&mut self) -> Option<Game> {
+        for message in self.receive() {
+            if let ServerMessage::Joined {
+                room,
+                events,
+                comments,
+                ..
+            } = message
+            {
+                match replay(&room, &events, comments) {
+                    Ok(game) => {
+                        self.confirmed = events.len();
+                        self.joined = Some(game);
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+        self.joined.take()
+    }
+
+    /// Send and receive messages, applying the moves played in the room to
+    /// `game`. Chat messages are added to `chat` along with who sent them.
+    pub fn poll(&mut self, game: &mut Game, chat: &mut Vec<(Piece, String)>) -> Vec<NetEvent> {
+        let was_open = self.is_open();
+        let mut net_events = vec![];
+        for message in self.receive() {
+            if let Err(reason) = self.handle(message, game, chat, &mut net_events) {
+                warn!("Out of sync with the server: {}", reason);
+                net_events.push(NetEvent::Rejected(reason));
+                self.resync();
+            }
+        }
+        if was_open && !self.is_open() {
+            net_events.push(NetEvent::Disconnected);
+        }
+        net_events
+    }
+
+    fn handle(
+        &mut self,
+        message: ServerMessage,
+        game: &mut Game,
+        chat: &mut Vec<(Piece, String)>,
+        net_events: &mut Vec<NetEvent>,
+    ) -> Result<(), String> {
+        match message {
+            ServerMessage::Joined {
+                room,
+                events,
+                comments,
+                ..
+            } => {
+                let rebuilt = replay(&room, &events, comments)?;
+                self.confirmed = events.len();
+                if rebuilt.events().eq(game.events()) {
+                    net_events.push(NetEvent::Connected);
+                } else {
+                    net_events.push(NetEvent::Resynced);
+                }
+                *game = rebuilt;
+            }
+            ServerMessage::Moved {
+                event,
+                hash,
+                result,
+            } => {
+                let event: Event = event.parse().map_err(|_| "Bad event")?;
+                if self.confirmed < game.events().len() {
+                    // A move played here, coming back
+                    if game.events().nth(self.confirmed) != Some(&event) {
+                        return Err("Moves do not match".to_string());
+                    }
+                } else {
+                    let move_ = event.to_move().ok_or("Edits are not allowed")?;
+                    game.play_(move_)
+                        .map_err(|_| format!("Illegal move {move_:?}"))?;
+                    net_events.push(NetEvent::Moved(move_));
+                }
+                self.confirmed += 1;
+
+                let hash = u64::from_str_radix(&hash, 16).map_err(|_| "Bad hash")?;
+                if self.confirmed == game.events().len() && game.position_hash() != hash {
+                    return Err("Position does not match".to_string());
+                }
+                if let Some(result) = result {
+                    let result: GameResult = result.parse().map_err(|_| "Bad result")?;
+                    game.set_result(Some(result));
+                }
+            }
+            ServerMessage::Chat { from, text } => chat.push((from.color(), text)),
+            ServerMessage::Commented(comment) => {
+                if !game.set_comment_at(comment.node, comment.text) {
+                    return Err(format!("No position {} to comment on", comment.node));
+                }
+                net_events.push(NetEvent::Commented { node: comment.node });
+            }
+            ServerMessage::Error { message } => {
+                // Only a refused move leaves this game ahead of the room's.
+                // Anything else, like a seat that is taken or a chat message
+                // that is too long, is just shown.
+                if self.confirmed < game.events().len() {
+                    return Err(message);
+                }
+                net_events.push(NetEvent::Rejected(message));
+            }
+            ServerMessage::Rooms { .. }
+            | ServerMessage::RoomChanged { .. }
+            | ServerMessage::Left => {}
+        }
+        Ok(())
+    }
+
+    /// Join the room again, which sends the whole game.
+    fn resync(&mut self) {
+        if let Some(room) = &self.room {
+            let (room, seat) = (room.id, self.seat);
+            self.send(&ClientMessage::Join { room, seat });
+        }
+    }
+}
+
+/// Play the events of `room`'s game on a new board, which only ever has
+/// moves in it, and add its comments.
+fn replay(room: &RoomInfo, events: &[String], comments: Vec<NodeComment>) -> Result<Game, String> {
+    let size = room.board_size;
+    if !(2..=52).contains(&size) {
+        return Err(format!("Bad board size {size}"));
+    }
+    let mut game = Game::new(size);
+    game.info_mut().name = room.name.clone();
+    for event in events {
+        let event: Event = event.parse().map_err(|_| format!("Bad event {event:?}"))?;
+        let move_ = event.to_move().ok_or("Edits are not allowed")?;
+        game.play_(move_)
+            .map_err(|_| format!("Illegal move {move_:?} in the room's game"))?;
+    }
+    for comment in comments {
+        game.set_comment_at(comment.node, comment.text);
+    }
+    Ok(game)
+}
+
+/// Where the other player's moves come from in a network game.
+#[derive(Debug)]
+pub enum Connection {
+    /// Straight to the other player, on a local network.
+    Peer(Session),
+    /// Through a room on a server.
+    Server(Box<Client>),
+}
+
+impl Connection {
+    /// The color played here, or `Piece::None` when watching or not yet
+    /// known.
+    pub fn local_color(&self) -> Piece {
+        match self {
+            Self::Peer(session) => session.local_color(),
+            Self::Server(client) => client.local_color(),
+        }
+    }
+
+    /// Returns true if the games are in sync and moves can be sent.
+    pub fn is_connected(&self) -> bool {
+        match self {
+            Self::Peer(session) => session.is_connected(),
+            Self::Server(client) => client.is_open() && client.room().is_some(),
+        }
+    }
+
+    /// Returns true if it is this side's turn to place a stone.
+    pub fn can_play(&self, game: &Game) -> bool {
+        match self {
+            Self::Peer(session) => session.can_play(game),
+            Self::Server(client) => client.can_play(game),
+        }
+    }
+
+    /// Send and receive messages, applying the other player's moves to
+    /// `game` and adding chat messages to `chat`.
+    pub fn poll(&mut self, game: &mut Game, chat: &mut Vec<(Piece, String)>) -> Vec<NetEvent> {
+        match self {
+            Self::Peer(session) => {
+                let them = session.local_color().opposing();
+                let mut events = session.poll(game);
+                events.retain(|event| match event {
+                    NetEvent::Chat(text) => {
+                        chat.push((them, text.clone()));
+                        false
+                    }
+                    _ => true,
+                });
+                events
+            }
+            Self::Server(client) => client.poll(game, chat),
+        }
+    }
+
+    pub fn send_last_move(&mut self, game: &Game) {
+        match self {
+            Self::Peer(session) => session.send_last_move(game),
+            Self::Server(client) => client.send_last_move(game),
+        }
+    }
+
+    /// Send a chat message, adding it to `chat` once it is sent.
+    pub fn send_chat(&mut self, text: String, chat: &mut Vec<(Piece, String)>) {
+        match self {
+            Self::Peer(session) => {
+                session.send_chat(&text);
+                chat.push((session.local_color(), text));
+            }
+            // The server sends it back along with everyone else's
+            Self::Server(client) => client.send_chat(&text),
+        }
+    }
+
+    pub fn send_comment(&mut self, game: &Game) {
+        match self {
+            Self::Peer(session) => session.send_comment(game),
+            Self::Server(client) => client.send_comment(game),
+        }
+    }
+}
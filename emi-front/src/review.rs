@@ -0,0 +1,262 @@
+//! Stepping through a game tree loaded from SGF, variations and all.
+//!
+//! The tree is kept as it was read, and the board is replayed up to the
+//! current node whenever it changes. Moves played while reviewing are added
+//! to the tree as new variations, so they are kept when saving.
+
+use emi_go::sgf::{self, SgfNode, SgfTree};
+use emi_go::{Annotations, Game, Piece, SgfError};
+
+#[derive(Debug, Clone)]
+pub struct Review {
+    tree: SgfTree,
+    /// The variation taken at each branch along the current line. Branches
+    /// past the end take the first variation.
+    path: Vec<usize>,
+    /// How many nodes past the root the board is.
+    node: usize,
+    /// The comment and marks of the current node, to notice when they are
+    /// changed on the board.
+    markup: Annotations,
+}
+
+/// A node placed for drawing the tree.
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    /// How many nodes past the root.
+    pub depth: usize,
+    /// Each variation gets its own row, with the main line on the first.
+    pub row: usize,
+    /// Index of the node before this one.
+    pub parent: Option<usize>,
+    /// Who moved, or `Piece::None` for setup and empty nodes.
+    pub color: Piece,
+    /// The variations taken to reach this node.
+    pub path: Vec<usize>,
+}
+
+impl Review {
+    /// Start at the root of `tree`.
+    pub fn new(tree: SgfTree) -> Self {
+        Self {
+            tree,
+            path: vec![],
+            node: 0,
+            markup: Annotations::default(),
+        }
+    }
+
+    /// Read the first game in `text`. It is opened for review at its root
+    /// if `review` is set or it has variations, and otherwise played out
+    /// to the end.
+    pub fn open(text: &str, review: bool) -> Result<(Game, Option<Self>), SgfError> {
+        let tree = sgf::parse(text)?
+            .into_iter()
+            .next()
+            .ok_or(SgfError::NoGame)?;
+        if !review && !has_variations(&tree) {
+            return Ok((Game::from_sgf_tree(&tree)?, None));
+        }
+        let mut review = Self::new(tree);
+        let game = review.game()?;
+        Ok((game, Some(review)))
+    }
+
+    /// The board at the current node.
+    pub fn game(&mut self) -> Result<Game, SgfError> {
+        let game = Game::from_sgf_nodes(self.line().take(self.node + 1))?;
+        self.markup = game.annotations().clone();
+        Ok(game)
+    }
+
+    /// The nodes of the current line, from the root.
+    fn line(&self) -> impl Iterator<Item = &SgfNode> {
+        let mut tree = Some(&self.tree);
+        let mut depth = 0;
+        std::iter::from_fn(move || {
+            let t = tree?;
+            let choice = self.path.get(depth).copied().unwrap_or(0);
+            tree = t.variations.get(choice);
+            depth += 1;
+            Some(&t.nodes)
+        })
+        .flatten()
+    }
+
+    pub fn node(&self) -> usize {
+        self.node
+    }
+
+    /// How many nodes there are on the current line, including the root.
+    pub fn line_len(&self) -> usize {
+        self.line().count()
+    }
+
+    /// Move to `node` on the current line, returning the board there.
+    pub fn go_to(&mut self, node: usize) -> Result<Game, SgfError> {
+        let node = node.min(self.line_len() - 1);
+        self.select(self.path.clone(), node)
+    }
+
+    /// Move `node` nodes past the root after taking the variations in
+    /// `path`, eg. from a [`TreeNode`], returning the board there. Nothing
+    /// changes if a move on the way can not be played.
+    pub fn select(&mut self, path: Vec<usize>, node: usize) -> Result<Game, SgfError> {
+        let old_path = std::mem::replace(&mut self.path, path);
+        let old_node = std::mem::replace(&mut self.node, node);
+        let game = self.game();
+        if game.is_err() {
+            self.path = old_path;
+            self.node = old_node;
+        }
+        game
+    }
+
+    /// Whether `node` is the one on the board.
+    pub fn is_current(&self, node: &TreeNode) -> bool {
+        node.depth == self.node && self.is_on_line(node)
+    }
+
+    /// Whether `node` is on the current line.
+    pub fn is_on_line(&self, node: &TreeNode) -> bool {
+        node.path
+            .iter()
+            .enumerate()
+            .all(|(depth, &choice)| self.path.get(depth).copied().unwrap_or(0) == choice)
+    }
+
+    /// The size of the board, which was checked when the game was opened.
+    fn board_size(&self) -> u32 {
+        self.tree.root().board_size().unwrap_or(19)
+    }
+
+    /// Add a move after the current node and move to it. If the same move
+    /// is already there it is followed instead.
+    pub fn play(&mut self, color: Piece, pos: Option<[u32; 2]>) {
+        let node = SgfNode::from_move(color, pos);
+        let size = self.board_size();
+        let (tree, i, depth) = locate_mut(&mut self.tree, &self.path, self.node);
+        self.node += 1;
+        self.markup = Annotations::default();
+
+        // Split the stretch so the move can branch off in the middle
+        if i + 1 < tree.nodes.len() {
+            if tree.nodes[i + 1].move_(size) == node.move_(size) {
+                return;
+            }
+            let nodes = tree.nodes.split_off(i + 1);
+            let variations = std::mem::take(&mut tree.variations);
+            tree.variations.push(SgfTree { nodes, variations });
+        }
+
+        let existing = tree
+            .variations
+            .iter()
+            .position(|v| v.nodes[0].move_(size) == node.move_(size));
+        let choice = match existing {
+            Some(choice) => choice,
+            None if tree.variations.is_empty() => {
+                tree.nodes.push(node);
+                self.path.truncate(depth);
+                return;
+            }
+            None => {
+                tree.variations.push(SgfTree {
+                    nodes: vec![node],
+                    variations: vec![],
+                });
+                tree.variations.len() - 1
+            }
+        };
+        self.path.truncate(depth);
+        self.path.push(choice);
+    }
+
+    /// Keep comments and marks made on the board in the tree.
+    pub fn sync_markup(&mut self, game: &Game) {
+        if *game.annotations() == self.markup {
+            return;
+        }
+        self.markup = game.annotations().clone();
+        let (tree, i, _) = locate_mut(&mut self.tree, &self.path, self.node);
+        tree.nodes[i].set_markup(&self.markup);
+    }
+
+    /// The whole tree as SGF.
+    pub fn to_sgf(&self) -> String {
+        format!("{}\n", self.tree)
+    }
+
+    /// Place every node for drawing, in the order they are reached.
+    pub fn layout(&self) -> Vec<TreeNode> {
+        let size = self.board_size();
+        let mut nodes = vec![];
+        let mut rows = 1;
+        // Stretches left to place, with where their first node goes
+        let mut stack = vec![(&self.tree, 0, 0, None, vec![])];
+        while let Some((tree, depth, row, mut parent, path)) = stack.pop() {
+            for (i, node) in tree.nodes.iter().enumerate() {
+                nodes.push(TreeNode {
+                    depth: depth + i,
+                    row,
+                    parent,
+                    color: node
+                        .move_(size)
+                        .ok()
+                        .flatten()
+                        .map_or(Piece::None, |(color, _)| color),
+                    path: path.clone(),
+                });
+                parent = Some(nodes.len() - 1);
+            }
+            let depth = depth + tree.nodes.len();
+
+            // The first variation carries on in the same row, and the rest
+            // get new rows below, in order
+            let mut placed = vec![];
+            for (choice, variation) in tree.variations.iter().enumerate() {
+                let row = if choice == 0 {
+                    row
+                } else {
+                    rows += 1;
+                    rows - 1
+                };
+                let mut path = path.clone();
+                path.push(choice);
+                placed.push((variation, depth, row, parent, path));
+            }
+            stack.extend(placed.into_iter().rev());
+        }
+        nodes
+    }
+}
+
+/// The stretch of `tree` holding `node` on the line following `path`, the
+/// node's index in it, and how many branches come before it.
+fn locate_mut<'a>(
+    mut tree: &'a mut SgfTree,
+    path: &[usize],
+    node: usize,
+) -> (&'a mut SgfTree, usize, usize) {
+    let mut start = 0;
+    let mut depth = 0;
+    while node >= start + tree.nodes.len() && !tree.variations.is_empty() {
+        start += tree.nodes.len();
+        let choice = path.get(depth).copied().unwrap_or(0);
+        tree = &mut tree.variations[choice];
+        depth += 1;
+    }
+    (tree, node - start, depth)
+}
+
+/// Whether any node has more than one move after it.
+fn has_variations(tree: &SgfTree) -> bool {
+    let mut stack = vec![tree];
+    while let Some(tree) = stack.pop() {
+        if tree.variations.len() > 1 {
+            return true;
+        }
+        stack.extend(&tree.variations);
+    }
+    false
+}
@@ -0,0 +1,256 @@
+use eframe::egui::Color32;
+use std::path::PathBuf;
+#[allow(unused_imports)]
+use tracing::{info, warn};
+
+/// How the board and stones look.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Color of the board.
+    pub board: Color32,
+    /// Color of the wood grain drawn over the board, if any.
+    pub grain: Option<Color32>,
+    /// Color of the lines, hoshi, coordinates and marks on empty points.
+    pub lines: Color32,
+    pub black: Color32,
+    pub white: Color32,
+    /// Color of the outline around stones, if any.
+    pub outline: Option<Color32>,
+    /// Draw a highlight on stones so they look rounded.
+    pub shading: bool,
+    /// Draw a shadow under stones.
+    pub shadow: bool,
+    /// Color of the highlight under the mouse.
+    pub highlight: Color32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Classic".to_string(),
+            // #e7c88c
+            board: Color32::from_rgb(0xe7, 0xc8, 0x8c),
+            grain: None,
+            lines: Color32::BLACK,
+            black: Color32::BLACK,
+            white: Color32::WHITE,
+            outline: None,
+            shading: false,
+            shadow: false,
+            highlight: Color32::LIGHT_GREEN.gamma_multiply(0.5),
+        }
+    }
+}
+
+impl Theme {
+    /// Themes that are always available. The first one is the default.
+    pub fn builtin() -> Vec<Self> {
+        let classic = Self::default();
+        vec![
+            classic.clone(),
+            Self {
+                name: "Kaya".to_string(),
+                board: Color32::from_rgb(0xdc, 0xb3, 0x6b),
+                grain: Some(Color32::from_rgb(0xc9, 0x9c, 0x55)),
+                white: Color32::from_rgb(0xf4, 0xf4, 0xee),
+                shading: true,
+                shadow: true,
+                ..classic.clone()
+            },
+            Self {
+                name: "Paper".to_string(),
+                board: Color32::WHITE,
+                outline: Some(Color32::BLACK),
+                highlight: Color32::LIGHT_BLUE.gamma_multiply(0.5),
+                ..classic.clone()
+            },
+            Self {
+                name: "Night".to_string(),
+                board: Color32::from_rgb(0x2e, 0x34, 0x40),
+                lines: Color32::from_rgb(0x9a, 0xa5, 0xb1),
+                black: Color32::from_rgb(0x0b, 0x0d, 0x10),
+                white: Color32::from_rgb(0xd8, 0xde, 0xe9),
+                outline: Some(Color32::from_rgb(0x9a, 0xa5, 0xb1)),
+                shading: true,
+                highlight: Color32::from_rgb(0x88, 0xc0, 0xd0).gamma_multiply(0.5),
+                ..classic
+            },
+        ]
+    }
+
+    /// Built in themes, followed by any user themes from the themes file.
+    pub fn all() -> Vec<Self> {
+        let mut themes = Self::builtin();
+        let Some(path) = themes_path() else {
+            return themes;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => match parse_themes(&text) {
+                Ok(user) => {
+                    info!("Loaded {} themes from {}", user.len(), path.display());
+                    themes.extend(user);
+                }
+                Err(e) => warn!("Could not parse {}: {}", path.display(), e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Could not read {}: {}", path.display(), e),
+        }
+        themes
+    }
+}
+
+/// Where user themes are read from.
+///
+/// This is `$EMI_THEMES` if set, otherwise `emi/themes.ini` in the user's
+/// config directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn themes_path() -> Option<PathBuf> {
+    use std::env;
+
+    if let Some(path) = env::var_os("EMI_THEMES") {
+        return Some(PathBuf::from(path));
+    }
+    let config = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if let Some(xdg) = env::var_os("XDG_CONFIG_HOME") {
+        PathBuf::from(xdg)
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".config")
+    };
+    Some(config.join("emi").join("themes.ini"))
+}
+
+/// There is no file system on the web.
+#[cfg(target_arch = "wasm32")]
+pub fn themes_path() -> Option<PathBuf> {
+    None
+}
+
+/// Parse user themes. Each theme starts with its name in brackets, followed
+/// by `key = value` lines. Keys that are left out are the same as in the
+/// classic theme.
+///
+/// ```ini
+/// # Comments start with '#'
+/// [Green felt]
+/// board = #35654d
+/// lines = #d0d0d0
+/// outline = #000000
+/// shadow = true
+/// ```
+///
+/// Colors are written as `#rrggbb` or `#rrggbbaa`, and `grain` and
+/// `outline` can also be `none`.
+pub fn parse_themes(text: &str) -> Result<Vec<Theme>, ThemeError> {
+    let mut themes: Vec<Theme> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let Some(name) = name.strip_suffix(']') else {
+                return Err(ThemeError::Syntax { line: line_no });
+            };
+            themes.push(Theme {
+                name: name.trim().to_string(),
+                ..Theme::default()
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ThemeError::Syntax { line: line_no });
+        };
+        let Some(theme) = themes.last_mut() else {
+            return Err(ThemeError::NoTheme { line: line_no });
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let bad_value = || ThemeError::BadValue {
+            line: line_no,
+            key: key.to_string(),
+        };
+        let color = || parse_color(value).ok_or_else(bad_value);
+        let optional_color = || match value {
+            "none" => Ok(None),
+            _ => color().map(Some),
+        };
+        let flag = || value.parse::<bool>().map_err(|_| bad_value());
+
+        match key {
+            "board" => theme.board = color()?,
+            "grain" => theme.grain = optional_color()?,
+            "lines" => theme.lines = color()?,
+            "black" => theme.black = color()?,
+            "white" => theme.white = color()?,
+            "outline" => theme.outline = optional_color()?,
+            "shading" => theme.shading = flag()?,
+            "shadow" => theme.shadow = flag()?,
+            "highlight" => theme.highlight = color()?,
+            _ => {
+                return Err(ThemeError::UnknownKey {
+                    line: line_no,
+                    key: key.to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(themes)
+}
+
+/// Parse `#rrggbb` or `#rrggbbaa`.
+fn parse_color(text: &str) -> Option<Color32> {
+    let hex = text.strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?)),
+        8 => Some(Color32::from_rgba_unmultiplied(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    /// A line that is not a comment, `[name]` or `key = value`.
+    Syntax {
+        line: usize,
+    },
+    /// A setting before the first `[name]`.
+    NoTheme {
+        line: usize,
+    },
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+    BadValue {
+        line: usize,
+        key: String,
+    },
+}
+
+impl std::fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax { line } => write!(f, "line {line}: expected `[name]` or `key = value`"),
+            Self::NoTheme { line } => write!(f, "line {line}: setting before any `[name]`"),
+            Self::UnknownKey { line, key } => write!(f, "line {line}: unknown key `{key}`"),
+            Self::BadValue { line, key } => write!(f, "line {line}: bad value for `{key}`"),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
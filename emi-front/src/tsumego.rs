@@ -0,0 +1,240 @@
+//! Solving life and death problems from SGF collections.
+//!
+//! Which problems have been solved is kept in `solved` in the data
+//! directory, one problem id per line, so it is shared between collections
+//! that have the same problems.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Color32};
+use emi_go::sgf::SgfTree;
+use emi_go::tsumego::{self, Status, Tsumego};
+use emi_go::{Move, Piece};
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+use crate::game_go::{
+    board_ui, game_go_display_pieces, interaction_go, Coordinates, GoResponse, MoveNumbers, Tool,
+};
+use crate::library;
+use crate::theme::Theme;
+
+/// A collection of problems being worked through.
+#[derive(Debug)]
+pub struct Problems {
+    /// The file the problems came from.
+    name: String,
+    problems: Vec<SgfTree>,
+    /// The id of each problem, if it can be set up.
+    ids: Vec<Option<u64>>,
+    index: usize,
+    attempt: Tsumego,
+    /// Every problem solved so far, in any collection.
+    solved: BTreeSet<u64>,
+    theme: Theme,
+    /// The last move that could not be played.
+    notice: Option<String>,
+}
+
+impl Problems {
+    /// Read a collection, starting at the first problem not solved yet.
+    /// Problems that can not be set up are skipped, unless none can be.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let problems = tsumego::collection(&text).map_err(|e| e.to_string())?;
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        );
+        info!("Opened {} problems from {}", problems.len(), name);
+
+        let ids: Vec<_> = problems
+            .iter()
+            .map(|p| Tsumego::new(p.clone()).ok().map(|t| t.id()))
+            .collect();
+        let Some(first) = ids.iter().position(Option::is_some) else {
+            let e = Tsumego::new(problems[0].clone()).err();
+            return Err(match e {
+                Some(e) if problems.len() == 1 => e.to_string(),
+                _ => format!("None of the {} problems can be set up", problems.len()),
+            });
+        };
+        let attempt = Tsumego::new(problems[first].clone()).map_err(|e| e.to_string())?;
+        let mut state = Self {
+            name,
+            problems,
+            ids,
+            index: first,
+            attempt,
+            solved: read_solved(),
+            theme: Theme::default(),
+            notice: None,
+        };
+        let unsolved = state
+            .ids
+            .iter()
+            .position(|id| id.is_some_and(|id| !state.solved.contains(&id)));
+        state.go_to(unsolved.unwrap_or(first));
+        Ok(state)
+    }
+
+    /// Start problem `index`, skipping over it if it can not be set up.
+    fn go_to(&mut self, index: usize) {
+        match Tsumego::new(self.problems[index].clone()) {
+            Ok(attempt) => {
+                self.index = index;
+                self.attempt = attempt;
+                self.notice = None;
+            }
+            Err(e) => self.notice = Some(format!("Problem {} is broken: {}", index + 1, e)),
+        }
+    }
+
+    fn play(&mut self, pos: [u32; 2]) {
+        match self.attempt.play(pos) {
+            Ok(Status::Solved) => {
+                self.notice = None;
+                if self.solved.insert(self.attempt.id()) {
+                    write_solved(&self.solved);
+                }
+            }
+            Ok(_) => self.notice = None,
+            Err(e) => self.notice = Some(e.to_string()),
+        }
+    }
+
+    fn solved_here(&self) -> usize {
+        self.ids
+            .iter()
+            .flatten()
+            .filter(|id| self.solved.contains(id))
+            .count()
+    }
+}
+
+fn solved_path() -> Option<PathBuf> {
+    Some(library::data_dir()?.join("solved"))
+}
+
+/// Ids of every problem solved so far.
+fn read_solved() -> BTreeSet<u64> {
+    let Some(text) = solved_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return BTreeSet::new();
+    };
+    text.lines()
+        .filter_map(|line| u64::from_str_radix(line.trim(), 16).ok())
+        .collect()
+}
+
+fn write_solved(solved: &BTreeSet<u64>) {
+    let Some(path) = solved_path() else {
+        return;
+    };
+    let text: String = solved.iter().map(|id| format!("{id:016x}\n")).collect();
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, text));
+    if let Err(e) = written {
+        warn!("Could not save solved problems: {}", e);
+    }
+}
+
+/// Show the current problem, and play the solver's moves on it.
+#[must_use]
+pub fn state_tsumego(ctx: &egui::Context, state: &mut Problems) -> GoResponse {
+    let resp = egui::TopBottomPanel::top("tsumego_menu")
+        .show(ctx, |ui| {
+            let mut resp = GoResponse::None;
+            ui.menu_button("Back to Main Menu", |ui| {
+                if ui.button("Confirm").clicked() {
+                    ui.close_menu();
+                    resp = GoResponse::MainMenu;
+                }
+            });
+            resp
+        })
+        .inner;
+
+    egui::SidePanel::right("tsumego_control")
+        .resizable(false)
+        .show(ctx, |ui| {
+            let count = state.problems.len();
+            ui.heading(format!("Problem {} of {}", state.index + 1, count));
+            ui.label(&state.name);
+            ui.label(format!("{} of {} solved", state.solved_here(), count));
+            if let Some(name) = state.attempt.name() {
+                ui.label(name);
+            }
+
+            ui.separator();
+            let to_play = match state.attempt.player() {
+                Piece::White => "White to play",
+                _ => "Black to play",
+            };
+            match state.attempt.status() {
+                Status::Unsolved => ui.label(to_play),
+                Status::Solved => ui.colored_label(Color32::from_rgb(40, 160, 60), "Correct!"),
+                Status::Failed => ui.colored_label(ui.visuals().error_fg_color, "Wrong"),
+            };
+            if state.solved.contains(&state.attempt.id()) {
+                ui.label("Solved before");
+            }
+            if let Some(notice) = &state.notice {
+                ui.colored_label(ui.visuals().warn_fg_color, notice);
+            }
+            let comment = state.attempt.comment();
+            if !comment.is_empty() {
+                ui.label(comment);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(state.index > 0, egui::Button::new("Previous"))
+                    .clicked()
+                {
+                    state.go_to(state.index - 1);
+                }
+                if ui.button("Retry").clicked() {
+                    state.go_to(state.index);
+                }
+                if ui
+                    .add_enabled(state.index + 1 < count, egui::Button::new("Next"))
+                    .clicked()
+                {
+                    state.go_to(state.index + 1);
+                }
+            });
+        });
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let game = state.attempt.game();
+        let (response, painter, grid_rect) = board_ui(ui, game, &state.theme, Coordinates::Off);
+        game_go_display_pieces(
+            game,
+            &state.theme,
+            None,
+            MoveNumbers::Off,
+            &painter,
+            grid_rect,
+        );
+
+        let mut game = game.clone();
+        let clicked = interaction_go(
+            &mut game,
+            Tool::Play,
+            &state.theme,
+            grid_rect,
+            &painter,
+            &response,
+        );
+        if let Some(Move::Place { pos, .. }) = clicked {
+            state.play(pos);
+        }
+    });
+
+    resp
+}
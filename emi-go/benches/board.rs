@@ -0,0 +1,105 @@
+//! How long the rules take on 9x9 and 19x19 boards.
+//!
+//! `liberties` and `capture_` are run on every point of a board in the middle
+//! of a game, and `construct_event` for every empty point of it. Playouts
+//! start from an empty board.
+//!
+//! Run with `cargo bench -p emi-go`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use emi_go::{Game, Move, Piece};
+
+const SIZES: [u32; 2] = [9, 19];
+
+/// A game with about half the board filled by pseudorandom moves, the same
+/// one every run.
+fn middle_game(side: u32) -> Game {
+    let mut game = Game::new(side);
+    let mut seed = 0x5eed_u64;
+    let mut played = 0;
+    while played < side * side / 2 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let point = ((seed >> 33) % u64::from(side * side)) as u32;
+        let pos @ [x, y] = [point % side, point / side];
+        let color = game.next_to_play();
+        if game.board().is_real_eye(x, y, color) {
+            continue;
+        }
+        if game.play_(Move::Place { pos, color }).is_ok() {
+            played += 1;
+        }
+    }
+    game
+}
+
+fn points(side: u32) -> impl Iterator<Item = [u32; 2]> {
+    (0..side).flat_map(move |y| (0..side).map(move |x| [x, y]))
+}
+
+fn liberties(c: &mut Criterion) {
+    let mut group = c.benchmark_group("liberties");
+    for side in SIZES {
+        let game = middle_game(side);
+        let board = game.board();
+        group.bench_with_input(BenchmarkId::from_parameter(side), &side, |b, &side| {
+            b.iter(|| {
+                for [x, y] in points(side) {
+                    black_box(board.liberties(x, y));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn capture(c: &mut Criterion) {
+    let mut group = c.benchmark_group("capture_");
+    for side in SIZES {
+        let game = middle_game(side);
+        let board = game.board();
+        group.bench_with_input(BenchmarkId::from_parameter(side), &side, |b, &side| {
+            b.iter(|| {
+                for [x, y] in points(side) {
+                    black_box(board.capture_(x, y));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn construct_event(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construct_event");
+    for side in SIZES {
+        let game = middle_game(side);
+        let color = game.next_to_play();
+        let empty: Vec<_> = points(side)
+            .filter(|&[x, y]| game.board().get(x, y) == Piece::None)
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(side), &empty, |b, empty| {
+            b.iter(|| {
+                for &pos in empty {
+                    black_box(game.construct_event(Move::Place { pos, color }));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn playout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playout");
+    group.sample_size(20);
+    for side in SIZES {
+        let game = Game::new(side);
+        group.bench_with_input(BenchmarkId::from_parameter(side), &game, |b, game| {
+            b.iter(|| game.estimate_ownership(1))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, liberties, capture, construct_event, playout);
+criterion_main!(benches);
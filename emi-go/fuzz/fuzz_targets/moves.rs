@@ -0,0 +1,59 @@
+//! Random moves played and taken back.
+//!
+//! The first byte picks the board size, and every two bytes after it are a
+//! move: a point, a pass, or taking back the last move. Moves may be illegal,
+//! which has to leave the game as it was. After a legal move every chain on
+//! the board has a liberty, and taking the move back restores the position.
+
+#![no_main]
+
+use emi_go::{Board, Game, Move};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, data)) = data.split_first() else {
+        return;
+    };
+    let side = 2 + u32::from(size) % 18;
+    let points = side * side;
+    let mut game = Game::new(side);
+
+    for bytes in data.chunks_exact(2) {
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        if value & 0x8000 != 0 {
+            game.undo();
+            continue;
+        }
+        let index = u32::from(value) % (points + 1);
+        let move_ = if index == points {
+            Move::Pass
+        } else {
+            let color = game.next_to_play();
+            Move::Place {
+                pos: [index % side, index / side],
+                color,
+            }
+        };
+
+        let before = game.clone();
+        if game.play_(move_).is_err() {
+            assert_eq!(game.board(), before.board());
+            assert_eq!(game.position_hash(), before.position_hash());
+            continue;
+        }
+        check_liberties(game.board());
+        let mut undone = game.clone();
+        undone.undo();
+        assert_eq!(undone.board(), before.board());
+        assert_eq!(undone.position_hash(), before.position_hash());
+    }
+});
+
+fn check_liberties(board: &Board) {
+    let side = board.board_size();
+    for y in 0..side {
+        for x in 0..side {
+            assert_ne!(board.liberties(x, y), Some(0), "no liberties at {x}, {y}");
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Reading SGF files, which may come from anywhere.
+//!
+//! Any text either reads as games or fails with an error, and a game that
+//! reads can be written back out.
+
+#![no_main]
+
+use emi_go::{sgf, Game};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let Ok(trees) = sgf::parse(text) else {
+        return;
+    };
+    for tree in &trees {
+        if let Ok(game) = Game::from_sgf_tree(tree) {
+            let _ = game.to_sgf();
+        }
+    }
+});
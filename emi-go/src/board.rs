@@ -31,6 +31,16 @@ impl Board {
         self.side
     }
 
+    /// A Zobrist hash of the stones on the board. Boards with the same
+    /// stones have the same hash, and different boards almost never do.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (i, piece) in self.store.iter().enumerate() {
+            hash ^= zobrist_key(i, *piece);
+        }
+        hash
+    }
+
     /// Sets the coordinate to `Piece::None`.
     pub fn remove(&mut self, x: u32, y: u32) {
         *self.get_mut(x, y) = Piece::None;
@@ -81,6 +91,30 @@ impl Board {
         Some(liberties)
     }
 
+    /// The empty points next to the group the stone at the specified
+    /// coordinate is a part of.
+    ///
+    /// Returns `Option::None` if the specified coordinate is empty.
+    pub fn liberty_points(&self, x: u32, y: u32) -> Option<Vec<[u32; 2]>> {
+        let color = self.get(x, y);
+        if let Piece::None = color {
+            return None;
+        }
+        let mut liberties = vec![];
+
+        let f = |x, y| match self.get(x, y) {
+            Piece::None => {
+                liberties.push([x, y]);
+                false
+            }
+            piece => piece == color,
+        };
+
+        self.dfs((x, y), f);
+
+        Some(liberties)
+    }
+
     /// Count the number of stones in the group the stone at the
     /// specified position is a part of.
     ///
@@ -157,19 +191,21 @@ impl Board {
     /// General Depth-First Search function. Takes a start position `pos`
     /// and a closure `f`. `f` takes in the next potentially searched
     /// position and returns `true` if the DFS should continue at that
-    /// position.
-    fn dfs(&self, pos: (u32, u32), mut f: impl FnMut(u32, u32) -> bool) {
+    /// position. `f` is called at most once for each position.
+    pub(crate) fn dfs(&self, pos: (u32, u32), mut f: impl FnMut(u32, u32) -> bool) {
+        let mut checked = vec![false; self.store.len()];
+        checked[(pos.1 * self.side + pos.0) as usize] = true;
         if !f(pos.0, pos.1) {
             return;
         }
         let mut stack = vec![pos];
-        let mut visited = vec![];
-        while let Some(next @ (x, y)) = stack.pop() {
-            visited.push(next);
-            for neighbor @ (nx, ny) in self.surround(x, y) {
-                if visited.contains(&neighbor) {
+        while let Some((x, y)) = stack.pop() {
+            for (nx, ny) in self.surround(x, y) {
+                let idx = (ny * self.side + nx) as usize;
+                if checked[idx] {
                     continue;
                 }
+                checked[idx] = true;
                 if f(nx, ny) {
                     stack.push((nx, ny));
                 }
@@ -178,6 +214,27 @@ impl Board {
     }
 }
 
+/// The Zobrist key for `piece` at index `i` of the board. Empty points are
+/// zero so they do not change the hash.
+///
+/// Keys are mixed from the index with SplitMix64 rather than stored in a
+/// table, so they are the same for every board size and every run.
+pub(crate) fn zobrist_key(i: usize, piece: Piece) -> u64 {
+    let color = match piece {
+        Piece::None => return 0,
+        Piece::Black => 0,
+        Piece::White => 1,
+    };
+    splitmix64(((i as u64) << 1 | color) ^ 0x5851_f42d_4c95_7f2d)
+}
+
+pub(crate) fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 impl Index<(u32, u32)> for Board {
     type Output = Piece;
     fn index(&self, (x, y): (u32, u32)) -> &Self::Output {
@@ -190,3 +247,27 @@ impl IndexMut<(u32, u32)> for Board {
         self.get_mut(x, y)
     }
 }
+
+#[cfg(test)]
+impl Board {
+    /// Read a board from rows of `X` for black, `O` for white and `.` for
+    /// empty points, for setting up positions in tests.
+    pub(crate) fn from_rows(rows: &str) -> Self {
+        let rows: Vec<Vec<char>> = rows
+            .lines()
+            .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+            .filter(|row: &Vec<char>| !row.is_empty())
+            .collect();
+        let mut board = Board::new(rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                *board.get_mut(x as u32, y as u32) = match c {
+                    'X' => Piece::Black,
+                    'O' => Piece::White,
+                    _ => Piece::None,
+                };
+            }
+        }
+        board
+    }
+}
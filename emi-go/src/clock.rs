@@ -0,0 +1,440 @@
+use std::time::Duration;
+
+use crate::Piece;
+
+/// How much time each player gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeControl {
+    /// Untimed.
+    #[default]
+    None,
+    /// A fixed amount of time for the whole game.
+    Absolute { main: Duration },
+    /// After main time runs out, each move must be made within `period`.
+    /// Going over uses up one of the `periods`.
+    ByoYomi {
+        main: Duration,
+        periods: u32,
+        period: Duration,
+    },
+    /// After main time runs out, `stones` moves must be made within each
+    /// `period`.
+    Canadian {
+        main: Duration,
+        stones: u32,
+        period: Duration,
+    },
+    /// `increment` is added after every move.
+    Fischer { main: Duration, increment: Duration },
+}
+
+impl TimeControl {
+    /// Main time, before any overtime.
+    pub fn main_time(&self) -> Duration {
+        match *self {
+            Self::None => Duration::ZERO,
+            Self::Absolute { main }
+            | Self::ByoYomi { main, .. }
+            | Self::Canadian { main, .. }
+            | Self::Fischer { main, .. } => main,
+        }
+    }
+
+    /// Overtime as described by the SGF `OT` property, eg. `5x30 byo-yomi`.
+    pub fn overtime_description(&self) -> Option<String> {
+        match *self {
+            Self::None | Self::Absolute { .. } => None,
+            Self::ByoYomi {
+                periods, period, ..
+            } => Some(format!("{}x{} byo-yomi", periods, period.as_secs())),
+            Self::Canadian { stones, period, .. } => {
+                Some(format!("{}/{} canadian", stones, period.as_secs()))
+            }
+            Self::Fischer { increment, .. } => Some(format!("{} fischer", increment.as_secs())),
+        }
+    }
+
+    /// Read main time from the SGF `TM` property and overtime as written by
+    /// [`TimeControl::overtime_description`]. Overtime in any other format
+    /// is left out, leaving just main time.
+    pub fn from_sgf(main: Duration, overtime: Option<&str>) -> Self {
+        let mut fields = overtime.unwrap_or_default().split_whitespace();
+        let (amount, kind) = (fields.next().unwrap_or_default(), fields.next());
+        let secs = |text: &str| text.parse().ok().map(Duration::from_secs);
+        let overtime = match kind.map(str::to_ascii_lowercase).as_deref() {
+            Some("byo-yomi") => amount.split_once('x').and_then(|(periods, period)| {
+                Some(Self::ByoYomi {
+                    main,
+                    periods: periods.parse().ok().filter(|&n| n > 0)?,
+                    period: secs(period)?,
+                })
+            }),
+            Some("canadian") => amount.split_once('/').and_then(|(stones, period)| {
+                Some(Self::Canadian {
+                    main,
+                    stones: stones.parse().ok().filter(|&n| n > 0)?,
+                    period: secs(period)?,
+                })
+            }),
+            Some("fischer") => secs(amount).map(|increment| Self::Fischer { main, increment }),
+            _ => None,
+        };
+        match overtime {
+            Some(control) => control,
+            None if main.is_zero() => Self::None,
+            None => Self::Absolute { main },
+        }
+    }
+}
+
+/// The time one player has left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerClock {
+    /// Main time left.
+    main: Duration,
+    /// Time left in the current overtime period.
+    period: Duration,
+    /// Byo-yomi periods left, including the current one.
+    periods: u32,
+    /// Moves left to play in the current Canadian period.
+    stones: u32,
+    flagged: bool,
+}
+
+impl PlayerClock {
+    fn new(control: TimeControl) -> Self {
+        let (period, periods, stones) = match control {
+            TimeControl::ByoYomi {
+                periods, period, ..
+            } => (period, periods, 0),
+            TimeControl::Canadian { stones, period, .. } => (period, 0, stones),
+            _ => (Duration::ZERO, 0, 0),
+        };
+        Self {
+            main: control.main_time(),
+            period,
+            periods,
+            stones,
+            flagged: false,
+        }
+    }
+
+    /// The clock after a move that left `time_left`, and `overtime_left`
+    /// once in overtime, as stored in [`crate::Annotations`].
+    fn restore(control: TimeControl, time_left: Duration, overtime_left: Option<u32>) -> Self {
+        let mut clock = Self::new(control);
+        match (control, overtime_left) {
+            (TimeControl::ByoYomi { .. }, Some(periods)) => {
+                clock.main = Duration::ZERO;
+                clock.period = time_left;
+                clock.periods = periods;
+            }
+            (TimeControl::Canadian { .. }, Some(stones)) => {
+                clock.main = Duration::ZERO;
+                clock.period = time_left;
+                clock.stones = stones;
+            }
+            _ => clock.main = time_left,
+        }
+        clock
+    }
+
+    /// Main time left.
+    pub fn main_left(&self) -> Duration {
+        self.main
+    }
+
+    /// Returns true once main time has run out.
+    pub fn in_overtime(&self) -> bool {
+        self.main.is_zero()
+    }
+
+    /// Time left in the current overtime period.
+    pub fn period_left(&self) -> Duration {
+        self.period
+    }
+
+    /// Byo-yomi periods left, including the current one.
+    pub fn periods_left(&self) -> u32 {
+        self.periods
+    }
+
+    /// Moves left to play in the current Canadian period.
+    pub fn stones_left(&self) -> u32 {
+        self.stones
+    }
+
+    /// Returns true if this player ran out of time.
+    pub fn flagged(&self) -> bool {
+        self.flagged
+    }
+
+    /// The time left as written in the SGF `BL`/`WL` property: main time,
+    /// or the current period once in overtime.
+    pub fn time_left(&self) -> Duration {
+        if self.in_overtime() {
+            self.period
+        } else {
+            self.main
+        }
+    }
+
+    /// Byo-yomi periods or Canadian stones left once in overtime, as written
+    /// in the SGF `OB`/`OW` property.
+    pub fn overtime_left(&self, control: TimeControl) -> Option<u32> {
+        match control {
+            _ if !self.in_overtime() => None,
+            TimeControl::ByoYomi { .. } => Some(self.periods),
+            TimeControl::Canadian { .. } => Some(self.stones),
+            _ => None,
+        }
+    }
+
+    fn spend(&mut self, control: TimeControl, mut elapsed: Duration) {
+        if self.flagged {
+            return;
+        }
+        if self.main >= elapsed {
+            self.main -= elapsed;
+            return;
+        }
+        elapsed -= self.main;
+        self.main = Duration::ZERO;
+
+        match control {
+            TimeControl::None => {}
+            TimeControl::Absolute { .. } | TimeControl::Fischer { .. } => self.flagged = true,
+            TimeControl::ByoYomi { period, .. } => {
+                while elapsed >= self.period {
+                    elapsed -= self.period;
+                    self.periods = self.periods.saturating_sub(1);
+                    if self.periods == 0 {
+                        self.period = Duration::ZERO;
+                        self.flagged = true;
+                        return;
+                    }
+                    self.period = period;
+                }
+                self.period -= elapsed;
+            }
+            TimeControl::Canadian { .. } => {
+                if elapsed >= self.period {
+                    self.period = Duration::ZERO;
+                    self.flagged = true;
+                } else {
+                    self.period -= elapsed;
+                }
+            }
+        }
+    }
+
+    fn moved(&mut self, control: TimeControl) {
+        if self.flagged {
+            return;
+        }
+        match control {
+            TimeControl::None | TimeControl::Absolute { .. } => {}
+            TimeControl::ByoYomi { period, .. } => {
+                if self.in_overtime() {
+                    self.period = period;
+                }
+            }
+            TimeControl::Canadian { stones, period, .. } => {
+                if self.in_overtime() {
+                    self.stones = self.stones.saturating_sub(1);
+                    if self.stones == 0 {
+                        self.stones = stones;
+                        self.period = period;
+                    }
+                }
+            }
+            TimeControl::Fischer { increment, .. } => self.main += increment,
+        }
+    }
+}
+
+/// A clock for both players. Time is only spent when the clock is ticked,
+/// which is up to whoever is showing the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    control: TimeControl,
+    black: PlayerClock,
+    white: PlayerClock,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            black: PlayerClock::new(control),
+            white: PlayerClock::new(control),
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// The clock of the player with stones of `color`.
+    pub fn player(&self, color: Piece) -> &PlayerClock {
+        match color {
+            Piece::White => &self.white,
+            _ => &self.black,
+        }
+    }
+
+    fn player_mut(&mut self, color: Piece) -> &mut PlayerClock {
+        match color {
+            Piece::White => &mut self.white,
+            _ => &mut self.black,
+        }
+    }
+
+    /// Spend `elapsed` of `color`'s time.
+    pub fn tick(&mut self, color: Piece, elapsed: Duration) {
+        let control = self.control;
+        self.player_mut(color).spend(control, elapsed);
+    }
+
+    /// Update overtime and increments after `color` plays a move.
+    pub fn moved(&mut self, color: Piece) {
+        let control = self.control;
+        self.player_mut(color).moved(control);
+    }
+
+    /// Set `color`'s clock back to the time left after one of their moves,
+    /// or to the start of the game if `time_left` is `None`.
+    pub(crate) fn restore(
+        &mut self,
+        color: Piece,
+        time_left: Option<Duration>,
+        overtime_left: Option<u32>,
+    ) {
+        let control = self.control;
+        *self.player_mut(color) = match time_left {
+            Some(left) => PlayerClock::restore(control, left, overtime_left),
+            None => PlayerClock::new(control),
+        };
+    }
+
+    /// The player who ran out of time, if any.
+    pub fn flagged(&self) -> Option<Piece> {
+        if self.black.flagged {
+            Some(Piece::Black)
+        } else if self.white.flagged {
+            Some(Piece::White)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn byo_yomi_periods_run_out() {
+        let mut clock = Clock::new(TimeControl::ByoYomi {
+            main: secs(10),
+            periods: 2,
+            period: secs(30),
+        });
+        clock.tick(Piece::Black, secs(10));
+        let black = clock.player(Piece::Black);
+        assert!(black.in_overtime());
+        assert_eq!((black.period_left(), black.periods_left()), (secs(30), 2));
+
+        // Going over a period uses it up
+        clock.tick(Piece::Black, secs(35));
+        let black = clock.player(Piece::Black);
+        assert_eq!((black.period_left(), black.periods_left()), (secs(25), 1));
+        clock.moved(Piece::Black);
+        assert_eq!(clock.player(Piece::Black).period_left(), secs(30));
+
+        clock.tick(Piece::Black, secs(30));
+        assert_eq!(clock.flagged(), Some(Piece::Black));
+        assert_eq!(clock.player(Piece::Black).periods_left(), 0);
+    }
+
+    #[test]
+    fn canadian_stones_reset_with_the_period() {
+        let mut clock = Clock::new(TimeControl::Canadian {
+            main: secs(0),
+            stones: 2,
+            period: secs(60),
+        });
+        clock.tick(Piece::White, secs(20));
+        clock.moved(Piece::White);
+        let white = clock.player(Piece::White);
+        assert_eq!((white.period_left(), white.stones_left()), (secs(40), 1));
+
+        clock.tick(Piece::White, secs(30));
+        clock.moved(Piece::White);
+        let white = clock.player(Piece::White);
+        assert_eq!((white.period_left(), white.stones_left()), (secs(60), 2));
+
+        clock.tick(Piece::White, secs(60));
+        assert_eq!(clock.flagged(), Some(Piece::White));
+    }
+
+    #[test]
+    fn fischer_adds_the_increment() {
+        let mut clock = Clock::new(TimeControl::Fischer {
+            main: secs(60),
+            increment: secs(5),
+        });
+        clock.tick(Piece::Black, secs(10));
+        clock.moved(Piece::Black);
+        assert_eq!(clock.player(Piece::Black).main_left(), secs(55));
+
+        clock.tick(Piece::Black, secs(56));
+        clock.moved(Piece::Black);
+        assert_eq!(clock.flagged(), Some(Piece::Black));
+        assert_eq!(clock.player(Piece::Black).main_left(), secs(0));
+    }
+
+    #[test]
+    fn clocks_are_restored_from_the_time_left() {
+        for control in [
+            TimeControl::Absolute { main: secs(120) },
+            TimeControl::ByoYomi {
+                main: secs(60),
+                periods: 3,
+                period: secs(30),
+            },
+            TimeControl::Canadian {
+                main: secs(60),
+                stones: 5,
+                period: secs(120),
+            },
+            TimeControl::Fischer {
+                main: secs(120),
+                increment: secs(5),
+            },
+        ] {
+            let mut clock = Clock::new(control);
+            for elapsed in [20, 30, 25] {
+                clock.tick(Piece::Black, secs(elapsed));
+                clock.moved(Piece::Black);
+
+                let black = *clock.player(Piece::Black);
+                let mut restored = Clock::new(control);
+                restored.restore(
+                    Piece::Black,
+                    Some(black.time_left()),
+                    black.overtime_left(control),
+                );
+                assert_eq!(restored, clock, "{control:?} after {elapsed}s");
+            }
+
+            // Taking back every move gives back the whole clock
+            clock.restore(Piece::Black, None, None);
+            assert_eq!(clock, Clock::new(control));
+        }
+    }
+}
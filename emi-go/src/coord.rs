@@ -0,0 +1,116 @@
+//! Names for board coordinates.
+//!
+//! Internally, `[0, 0]` is the top left corner of the board, with `x` going
+//! right and `y` going down.
+
+/// Column letters as printed on a goban. `I` is skipped so it is not
+/// confused with `J` or the number 1.
+const COLUMNS: &str = "ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+/// The largest board that can be named with [`column_label`].
+pub const MAX_LABELED_SIZE: u32 = COLUMNS.len() as u32;
+
+/// The letter for column `x`, from `A` to `Z` skipping `I`.
+pub fn column_label(x: u32) -> char {
+    COLUMNS
+        .chars()
+        .nth(x as usize)
+        .expect("Column is too large to be labeled")
+}
+
+/// The number for row `y`, counting up from `1` at the bottom of the board.
+pub fn row_label(y: u32, side: u32) -> u32 {
+    side - y
+}
+
+/// The human readable name of a point, eg. `Q16`.
+pub fn pos_name([x, y]: [u32; 2], side: u32) -> String {
+    format!("{}{}", column_label(x), row_label(y, side))
+}
+
+/// The point named by `name`, eg. `Q16` or `q16`, the reverse of
+/// [`pos_name`].
+pub fn from_pos_name(name: &str, side: u32) -> Option<[u32; 2]> {
+    let mut chars = name.trim().chars();
+    let column = chars.next()?.to_ascii_uppercase();
+    let x = COLUMNS.chars().position(|c| c == column)? as u32;
+    let row: u32 = chars.as_str().parse().ok()?;
+    if x >= side || row == 0 || row > side {
+        return None;
+    }
+    Some([x, side - row])
+}
+
+/// The SGF letter for a row or column, starting at `a` at the top left.
+pub fn sgf_label(i: u32) -> char {
+    // SGF goes a-z and then A-Z for boards up to 52x52
+    match i {
+        0..=25 => (b'a' + i as u8) as char,
+        26..=51 => (b'A' + (i - 26) as u8) as char,
+        _ => panic!("Coordinate is too large for SGF"),
+    }
+}
+
+/// The row or column for an SGF letter, the reverse of [`sgf_label`].
+pub fn from_sgf_label(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32 + 26),
+        _ => None,
+    }
+}
+
+/// The hoshi, the points marked with dots, on a board `side` points across.
+/// Boards too small for corner points only have the center.
+pub fn hoshi(side: u32) -> Vec<[u32; 2]> {
+    if side <= 4 {
+        return vec![];
+    }
+    // Distance of the corner points from the edge
+    let a = if side < 17 {
+        side / 3 - 1
+    } else {
+        side / 4 - 1
+    };
+    let b = side.div_ceil(2) - 1;
+    let c = side - a - 1;
+    let mut points = vec![];
+    if a >= 2 {
+        points.extend([[a, a], [a, c], [c, a], [c, c]]);
+    }
+    if side % 2 == 1 {
+        points.push([b, b]);
+        if side >= 19 {
+            points.extend([[a, b], [c, b], [b, a], [b, c]]);
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hoshi_on_common_sizes() {
+        assert_eq!(hoshi(9), vec![[2, 2], [2, 6], [6, 2], [6, 6], [4, 4]]);
+        assert_eq!(hoshi(13), vec![[3, 3], [3, 9], [9, 3], [9, 9], [6, 6]]);
+        let mut points = hoshi(19);
+        points.sort();
+        let mut expected = vec![];
+        for x in [3, 9, 15] {
+            for y in [3, 9, 15] {
+                expected.push([x, y]);
+            }
+        }
+        assert_eq!(points, expected);
+    }
+
+    #[test]
+    fn hoshi_on_other_sizes() {
+        assert!(hoshi(4).is_empty());
+        assert_eq!(hoshi(7), vec![[3, 3]]);
+        assert_eq!(hoshi(16), vec![[4, 4], [4, 11], [11, 4], [11, 11]]);
+        assert_eq!(hoshi(21).len(), 9);
+    }
+}
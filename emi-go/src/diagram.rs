@@ -0,0 +1,380 @@
+//! Plain text diagrams of positions, for pasting into chat and forums.
+//!
+//! Black stones are `X`, white stones `O` and empty points `.`, one row per
+//! line from the top of the board:
+//!
+//! ```text
+//! . . . . .
+//! . X O . .
+//! . X O . .
+//! . . X O .
+//! . . . . .
+//! ```
+//!
+//! Boards and games can also be shown with coordinates around them, and
+//! games with the last move in brackets and the ko point, if any, as `*`:
+//!
+//! ```text
+//!    A B C D E
+//!  5 . . . . . 5
+//!  4 . X O . . 4
+//!  3 X(O)* O . 3
+//!  2 . X O . . 2
+//!  1 . . . . . 1
+//!    A B C D E
+//! Black to play
+//! ```
+//!
+//! [`parse`] and [`parse_game`] read all of these.
+
+use std::fmt;
+
+use crate::coord::{self, MAX_LABELED_SIZE};
+use crate::event::Event;
+use crate::{Board, Game, GoError, Move, Piece};
+
+/// The largest board a diagram can be read as, the same as for SGF.
+pub const MAX_SIZE: u32 = 52;
+
+/// Why a diagram could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagramError {
+    /// There are no rows of points.
+    Empty,
+    /// A row has a different number of points to the first.
+    Ragged { line: usize },
+    /// The board is not square.
+    NotSquare { width: usize, height: usize },
+    /// The board is larger than [`MAX_SIZE`].
+    TooLarge,
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no board found"),
+            Self::Ragged { line } => write!(f, "line {line}: row is a different length"),
+            Self::NotSquare { width, height } => {
+                write!(f, "board is {width}x{height}, not square")
+            }
+            Self::TooLarge => write!(f, "board is larger than {MAX_SIZE}x{MAX_SIZE}"),
+        }
+    }
+}
+
+impl std::error::Error for DiagramError {}
+
+/// Write `board` as a diagram.
+pub fn to_ascii(board: &Board) -> String {
+    let side = board.board_size();
+    let mut text = String::with_capacity((side * side * 2) as usize);
+    for y in 0..side {
+        for x in 0..side {
+            if x > 0 {
+                text.push(' ');
+            }
+            text.push(match board.get(x, y) {
+                Piece::Black => 'X',
+                Piece::White => 'O',
+                Piece::None => '.',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Write `board` with coordinates around it, marking the last move and the
+/// ko point. Boards too large to label are written without coordinates.
+fn write_diagram(
+    f: &mut fmt::Formatter<'_>,
+    board: &Board,
+    last: Option<[u32; 2]>,
+    ko: Option<[u32; 2]>,
+) -> fmt::Result {
+    let side = board.board_size();
+    if side > MAX_LABELED_SIZE {
+        return write!(f, "{}", to_ascii(board));
+    }
+    let columns: String = (0..side)
+        .map(|x| format!(" {}", coord::column_label(x)))
+        .collect();
+    writeln!(f, "  {columns}")?;
+    for y in 0..side {
+        let row = coord::row_label(y, side);
+        write!(f, "{row:>2}")?;
+        for x in 0..side {
+            // The space before a point, which brackets the last move
+            let sep = if last == Some([x, y]) {
+                '('
+            } else if x > 0 && last == Some([x - 1, y]) {
+                ')'
+            } else {
+                ' '
+            };
+            let point = match board.get(x, y) {
+                Piece::Black => 'X',
+                Piece::White => 'O',
+                Piece::None if ko == Some([x, y]) => '*',
+                Piece::None => '.',
+            };
+            write!(f, "{sep}{point}")?;
+        }
+        let sep = if last == Some([side - 1, y]) {
+            ')'
+        } else {
+            ' '
+        };
+        writeln!(f, "{sep}{row}")?;
+    }
+    writeln!(f, "  {columns}")
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_diagram(f, self, None, None)
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_diagram(f, &self.board, self.last_played_pos(), self.ko_point())?;
+        match self.turn {
+            Piece::White => write!(f, "White to play"),
+            _ => write!(f, "Black to play"),
+        }
+    }
+}
+
+impl Game {
+    /// The point the player to move can not play on because of ko, if any.
+    fn ko_point(&self) -> Option<[u32; 2]> {
+        let pos = self.ko_coord?;
+        let mut game = self.clone();
+        let color = self.turn;
+        let banned = game.play_(Move::Place { pos, color });
+        matches!(banned, Err(GoError::IllegalKo { .. })).then_some(pos)
+    }
+}
+
+/// Read a diagram. Star points may be written `+` or `,`, and borders,
+/// coordinates and Sensei's Library style `$$` prefixes are skipped, as are
+/// any lines that are not rows of the board.
+pub fn parse(text: &str) -> Result<Board, DiagramError> {
+    Ok(read(text)?.board)
+}
+
+/// Read a diagram as a game with no history, as written by the `Display`
+/// of [`Game`]. The last move in brackets is played as the only move, so
+/// the player after it is to play unless a line like `White to play` says
+/// otherwise. A `*` is the ko point.
+pub fn parse_game(text: &str) -> Result<Game, DiagramError> {
+    let Diagram {
+        mut board,
+        last,
+        ko,
+        to_play,
+    } = read(text)?;
+
+    let mut game = Game::new(board.board_size());
+    if let Some(pos @ [x, y]) = last {
+        let color = board.get(x, y);
+        board.remove(x, y);
+        game.board = board;
+        game.turn = color;
+        game.push_event_unchecked(Event::Play {
+            pos,
+            color,
+            prev_ko: None,
+        });
+    } else {
+        game.board = board;
+    }
+    if let Some(color) = to_play {
+        game.turn = color;
+    }
+    game.ko_coord = ko;
+    Ok(game)
+}
+
+/// Everything read from a diagram.
+struct Diagram {
+    board: Board,
+    /// A stone in brackets.
+    last: Option<[u32; 2]>,
+    /// A `*` on an empty point.
+    ko: Option<[u32; 2]>,
+    to_play: Option<Piece>,
+}
+
+fn read(text: &str) -> Result<Diagram, DiagramError> {
+    let mut rows: Vec<Vec<Point>> = vec![];
+    let mut to_play = None;
+    for (i, line) in text.lines().enumerate() {
+        let lower = line.trim().to_lowercase();
+        if let Some(player) = lower.strip_suffix(" to play") {
+            to_play = match player {
+                "black" => Some(Piece::Black),
+                "white" => Some(Piece::White),
+                _ => to_play,
+            };
+            continue;
+        }
+        let Some(row) = parse_row(line) else {
+            continue;
+        };
+        if rows.first().is_some_and(|first| first.len() != row.len()) {
+            return Err(DiagramError::Ragged { line: i + 1 });
+        }
+        rows.push(row);
+    }
+
+    let height = rows.len();
+    let width = rows.first().ok_or(DiagramError::Empty)?.len();
+    if width != height {
+        return Err(DiagramError::NotSquare { width, height });
+    }
+    if width > MAX_SIZE as usize {
+        return Err(DiagramError::TooLarge);
+    }
+
+    let mut diagram = Diagram {
+        board: Board::new(width as u32),
+        last: None,
+        ko: None,
+        to_play,
+    };
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, point) in row.into_iter().enumerate() {
+            let pos = [x as u32, y as u32];
+            *diagram.board.get_mut(pos[0], pos[1]) = point.piece;
+            match point.mark {
+                Mark::None => {}
+                Mark::Last if point.piece != Piece::None => diagram.last = Some(pos),
+                Mark::Last => {}
+                Mark::Ko => diagram.ko = Some(pos),
+            }
+        }
+    }
+    Ok(diagram)
+}
+
+/// One point of a row.
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    piece: Piece,
+    mark: Mark,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    None,
+    /// In brackets.
+    Last,
+    /// Written `*`.
+    Ko,
+}
+
+/// The points in one line, or `None` if it is not a row of the board. Top
+/// and bottom borders like `+-----+` have dashes, so are not rows.
+fn parse_row(line: &str) -> Option<Vec<Point>> {
+    let line = line.trim().trim_start_matches("$$");
+    let line = line.trim_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+    let mut row = vec![];
+    let mut bracketed = false;
+    for c in line.chars() {
+        let (piece, mark) = match c {
+            'X' | 'x' => (Piece::Black, Mark::None),
+            'O' | 'o' => (Piece::White, Mark::None),
+            '.' | '+' | ',' => (Piece::None, Mark::None),
+            '*' => (Piece::None, Mark::Ko),
+            '(' => {
+                bracketed = true;
+                continue;
+            }
+            '|' | ')' | ' ' | '\t' => continue,
+            _ => return None,
+        };
+        let mark = if std::mem::take(&mut bracketed) {
+            Mark::Last
+        } else {
+            mark
+        };
+        row.push(Point { piece, mark });
+    }
+    (!row.is_empty()).then_some(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White just took a ko at C3, so Black can not take back at C3 yet.
+    const KO: &str = "   A B C D E
+ 5 . . . . . 5
+ 4 . X O . . 4
+ 3 X(O)* O . 3
+ 2 . X O . . 2
+ 1 . . . . . 1
+   A B C D E
+Black to play";
+
+    #[test]
+    fn ascii_round_trips() {
+        let mut board = Board::new(5);
+        *board.get_mut(1, 1) = Piece::Black;
+        *board.get_mut(2, 1) = Piece::White;
+        *board.get_mut(4, 4) = Piece::Black;
+        let text = to_ascii(&board);
+        assert_eq!(text.lines().nth(1), Some(". X O . ."));
+        assert_eq!(parse(&text), Ok(board));
+    }
+
+    #[test]
+    fn coordinates_round_trip() {
+        let mut board = Board::new(9);
+        *board.get_mut(2, 6) = Piece::Black;
+        let text = board.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[0], "   A B C D E F G H J");
+        assert_eq!(lines[7], " 3 . . X . . . . . . 3");
+        assert_eq!(parse(&text), Ok(board));
+    }
+
+    #[test]
+    fn last_move_and_ko_round_trip() {
+        let game = parse_game(KO).unwrap();
+        assert_eq!(game.last_played_pos(), Some([1, 2]));
+        assert_eq!(game.ko_point(), Some([2, 2]));
+        assert_eq!(game.next_to_play(), Piece::Black);
+        assert_eq!(game.to_string(), KO);
+    }
+
+    #[test]
+    fn player_to_move_follows_the_last_move() {
+        let game = parse_game(&KO.replace("Black to play", "")).unwrap();
+        assert_eq!(game.next_to_play(), Piece::Black);
+        let game = parse_game(&KO.replace("Black", "White")).unwrap();
+        assert_eq!(game.next_to_play(), Piece::White);
+    }
+
+    #[test]
+    fn bad_diagrams_are_rejected() {
+        assert_eq!(parse(""), Err(DiagramError::Empty));
+        assert_eq!(parse("hello\n"), Err(DiagramError::Empty));
+        assert_eq!(
+            parse(". . .\n. .\n. . .\n"),
+            Err(DiagramError::Ragged { line: 2 })
+        );
+        assert_eq!(
+            parse(". . .\n. . .\n"),
+            Err(DiagramError::NotSquare {
+                width: 3,
+                height: 2
+            })
+        );
+        let row = ".".repeat(MAX_SIZE as usize + 1);
+        let text = format!("{row}\n").repeat(MAX_SIZE as usize + 1);
+        assert_eq!(parse(&text), Err(DiagramError::TooLarge));
+    }
+}
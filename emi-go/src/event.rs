@@ -1,4 +1,7 @@
-use crate::Piece;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Move, Piece};
 
 #[derive(Debug, Clone, PartialEq)]
 /// An event in a go game.
@@ -23,10 +26,20 @@ pub enum Event {
         to: Piece,
     },
     Edits(Vec<Event>),
+    /// Hands the turn to the other player outside of a move, eg. after
+    /// setting up a position. Can be part of `Edits`.
+    Turn {
+        from: Piece,
+        to: Piece,
+    },
     Pass {
         color: Piece,
         prev_ko: Option<[u32; 2]>,
     },
+    /// Ends the game. Not a node in the game tree.
+    Resign {
+        color: Piece,
+    },
 }
 
 use Event::*;
@@ -40,6 +53,13 @@ impl Event {
         }
     }
 
+    pub fn edit_pos(&self) -> Option<[u32; 2]> {
+        match self {
+            Edit { pos, .. } => Some(*pos),
+            _ => None,
+        }
+    }
+
     pub fn play_color(&self) -> Option<Piece> {
         match self {
             Play { color, .. } => Some(*color),
@@ -47,6 +67,206 @@ impl Event {
             _ => None,
         }
     }
+
+    /// The move that would create this event, or `None` for edits.
+    pub fn to_move(&self) -> Option<Move> {
+        match self {
+            Play { pos, color, .. } | Capture { pos, color, .. } => Some(Move::Place {
+                pos: *pos,
+                color: *color,
+            }),
+            Pass { .. } => Some(Move::Pass),
+            Resign { color } => Some(Move::Resign { color: *color }),
+            Edit { .. } | Edits(_) | Turn { .. } => None,
+        }
+    }
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::None => '-',
+        Piece::Black => 'B',
+        Piece::White => 'W',
+    }
+}
+
+fn fmt_pos(f: &mut fmt::Formatter<'_>, [x, y]: [u32; 2]) -> fmt::Result {
+    write!(f, "{x},{y}")
+}
+
+fn fmt_ko(f: &mut fmt::Formatter<'_>, ko: Option<[u32; 2]>) -> fmt::Result {
+    match ko {
+        Some(pos) => fmt_pos(f, pos),
+        None => write!(f, "-"),
+    }
+}
+
+/// A single line of text, used to send events over the network.
+///
+/// eg. `play B 3,3 -`, `capture W 4,3 - 3,3;2,2`, `pass B 4,3`,
+/// `resign W`, `edit 0,0 - B`, `turn B W` or `edits 0,0:-B 1,0:W- turn:BW`.
+/// Coordinates are `x,y`, and the last field of moves is the previous ko
+/// point.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Play {
+                pos,
+                color,
+                prev_ko,
+            } => {
+                write!(f, "play {} ", piece_char(*color))?;
+                fmt_pos(f, *pos)?;
+                write!(f, " ")?;
+                fmt_ko(f, *prev_ko)
+            }
+            Capture {
+                pos,
+                color,
+                captured,
+                prev_ko,
+            } => {
+                write!(f, "capture {} ", piece_char(*color))?;
+                fmt_pos(f, *pos)?;
+                write!(f, " ")?;
+                fmt_ko(f, *prev_ko)?;
+                for (i, cap) in captured.iter().enumerate() {
+                    write!(f, "{}", if i == 0 { ' ' } else { ';' })?;
+                    fmt_pos(f, *cap)?;
+                }
+                Ok(())
+            }
+            Edit { pos, from, to } => {
+                write!(f, "edit ")?;
+                fmt_pos(f, *pos)?;
+                write!(f, " {} {}", piece_char(*from), piece_char(*to))
+            }
+            Edits(edits) => {
+                write!(f, "edits")?;
+                for edit in edits {
+                    // Only single edits and turns can be nested
+                    match edit {
+                        Edit { pos, from, to } => {
+                            write!(f, " ")?;
+                            fmt_pos(f, *pos)?;
+                            write!(f, ":{}{}", piece_char(*from), piece_char(*to))?;
+                        }
+                        Turn { from, to } => {
+                            write!(f, " turn:{}{}", piece_char(*from), piece_char(*to))?;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+            Turn { from, to } => write!(f, "turn {} {}", piece_char(*from), piece_char(*to)),
+            Pass { color, prev_ko } => {
+                write!(f, "pass {} ", piece_char(*color))?;
+                fmt_ko(f, *prev_ko)
+            }
+            Resign { color } => write!(f, "resign {}", piece_char(*color)),
+        }
+    }
+}
+
+/// Text that is not an event written by `Event`'s `Display`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEventError;
+
+impl fmt::Display for ParseEventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Not an event")
+    }
+}
+
+impl std::error::Error for ParseEventError {}
+
+fn parse_piece(s: &str) -> Result<Piece, ParseEventError> {
+    match s {
+        "-" => Ok(Piece::None),
+        "B" => Ok(Piece::Black),
+        "W" => Ok(Piece::White),
+        _ => Err(ParseEventError),
+    }
+}
+
+fn parse_pos(s: &str) -> Result<[u32; 2], ParseEventError> {
+    let (x, y) = s.split_once(',').ok_or(ParseEventError)?;
+    Ok([
+        x.parse().map_err(|_| ParseEventError)?,
+        y.parse().map_err(|_| ParseEventError)?,
+    ])
+}
+
+fn parse_ko(s: &str) -> Result<Option<[u32; 2]>, ParseEventError> {
+    match s {
+        "-" => Ok(None),
+        _ => parse_pos(s).map(Some),
+    }
+}
+
+impl FromStr for Event {
+    type Err = ParseEventError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let mut next = || fields.next().ok_or(ParseEventError);
+        let event = match next()? {
+            "play" => Play {
+                color: parse_piece(next()?)?,
+                pos: parse_pos(next()?)?,
+                prev_ko: parse_ko(next()?)?,
+            },
+            "capture" => Capture {
+                color: parse_piece(next()?)?,
+                pos: parse_pos(next()?)?,
+                prev_ko: parse_ko(next()?)?,
+                captured: next()?
+                    .split(';')
+                    .map(parse_pos)
+                    .collect::<Result<_, _>>()?,
+            },
+            "edit" => Edit {
+                pos: parse_pos(next()?)?,
+                from: parse_piece(next()?)?,
+                to: parse_piece(next()?)?,
+            },
+            "edits" => {
+                let mut edits = vec![];
+                while let Ok(edit) = next() {
+                    let (pos, pieces) = edit.split_once(':').ok_or(ParseEventError)?;
+                    let mut pieces = pieces.chars().map(|c| parse_piece(&c.to_string()));
+                    let from = pieces.next().ok_or(ParseEventError)??;
+                    let to = pieces.next().ok_or(ParseEventError)??;
+                    edits.push(match pos {
+                        "turn" => Turn { from, to },
+                        pos => Edit {
+                            pos: parse_pos(pos)?,
+                            from,
+                            to,
+                        },
+                    });
+                }
+                Edits(edits)
+            }
+            "turn" => Turn {
+                from: parse_piece(next()?)?,
+                to: parse_piece(next()?)?,
+            },
+            "pass" => Pass {
+                color: parse_piece(next()?)?,
+                prev_ko: parse_ko(next()?)?,
+            },
+            "resign" => Resign {
+                color: parse_piece(next()?)?,
+            },
+            _ => return Err(ParseEventError),
+        };
+        if next().is_ok() {
+            return Err(ParseEventError);
+        }
+        Ok(event)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -65,6 +285,71 @@ impl Events {
         self.0.pop()
     }
 
+    pub fn iter(&self) -> std::slice::Iter<'_, Event> {
+        self.0.iter()
+    }
+
+    /// The number of moves (including passes) played. Edits are not
+    /// counted.
+    pub fn moves_played(&self) -> u32 {
+        self.0
+            .iter()
+            .filter(|evt| matches!(evt, Play { .. } | Capture { .. } | Pass { .. }))
+            .count() as u32
+    }
+
+    /// The move number of every stone still on the board that was played
+    /// (rather than placed with an edit), in no particular order.
+    pub fn stone_move_numbers(&self) -> Vec<([u32; 2], u32)> {
+        let mut numbers: Vec<([u32; 2], u32)> = vec![];
+        let mut move_number = 0;
+        let remove = |numbers: &mut Vec<([u32; 2], u32)>, pos: [u32; 2]| {
+            numbers.retain(|(p, _)| *p != pos);
+        };
+        for evt in self.0.iter() {
+            match evt {
+                Play { pos, .. } => {
+                    move_number += 1;
+                    remove(&mut numbers, *pos);
+                    numbers.push((*pos, move_number));
+                }
+                Capture { pos, captured, .. } => {
+                    move_number += 1;
+                    for cap in captured {
+                        remove(&mut numbers, *cap);
+                    }
+                    remove(&mut numbers, *pos);
+                    numbers.push((*pos, move_number));
+                }
+                Edit { pos, .. } => remove(&mut numbers, *pos),
+                Edits(edits) => {
+                    for edit in edits {
+                        if let Some(pos) = edit.edit_pos() {
+                            remove(&mut numbers, pos);
+                        }
+                    }
+                }
+                Pass { .. } => move_number += 1,
+                Turn { .. } | Resign { .. } => {}
+            }
+        }
+        numbers
+    }
+
+    /// The number of stones captured by the player with stones of
+    /// `color`.
+    pub fn captures(&self, color: Piece) -> u32 {
+        self.0
+            .iter()
+            .map(|evt| match evt {
+                Capture {
+                    color: c, captured, ..
+                } if *c == color => captured.len() as u32,
+                _ => 0,
+            })
+            .sum()
+    }
+
     pub fn last_played_pos(&self) -> Option<[u32; 2]> {
         self.0.last()?.play_pos()
     }
@@ -80,6 +365,10 @@ impl Events {
     pub fn last_was_pass(&self) -> bool {
         matches!(self.last(), Some(Pass { .. }))
     }
+
+    pub fn last_two_were_passes(&self) -> bool {
+        matches!(self.0.as_slice(), [.., Pass { .. }, Pass { .. }])
+    }
 }
 
 impl Default for Events {
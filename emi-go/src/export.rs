@@ -0,0 +1,863 @@
+//! Images of positions, for study notes and web pages.
+//!
+//! A position is laid out as a list of shapes, the same way the board is
+//! drawn in the GUI, and then written out as SVG or filled in pixel by pixel
+//! for PNG. There are no fonts to draw PNG text with, so coordinates, move
+//! numbers and labels use a small built in pixel font that only has digits
+//! and capital letters.
+
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use crate::coord::{self, MAX_LABELED_SIZE};
+use crate::{Game, Markup, Piece};
+
+/// The widest or tallest image that can be made, in pixels.
+pub const MAX_PIXELS: u32 = 8192;
+
+/// An RGB color.
+pub type Color = [u8; 3];
+
+/// The colors to draw with. The default is the GUI's classic theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colors {
+    pub board: Color,
+    /// Lines, hoshi, coordinates, marks on empty points, and the outline
+    /// of white stones.
+    pub lines: Color,
+    pub black: Color,
+    pub white: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            board: [0xe7, 0xc8, 0x8c],
+            lines: [0, 0, 0],
+            black: [0, 0, 0],
+            white: [0xff, 0xff, 0xff],
+        }
+    }
+}
+
+/// What to draw, and how large.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Two opposite corners of the part of the board to draw, or the whole
+    /// board if `None`. See [`parse_window`].
+    pub window: Option<[[u32; 2]; 2]>,
+    /// Label the rows and columns, on boards small enough to have labels.
+    pub coordinates: bool,
+    /// Number every stone that was played as a move. Otherwise the last
+    /// move has a dot on it.
+    pub move_numbers: bool,
+    /// Draw the marks and labels on the position.
+    pub marks: bool,
+    /// The distance between two lines, in pixels.
+    pub unit: u32,
+    pub colors: Colors,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            window: None,
+            coordinates: true,
+            move_numbers: false,
+            marks: true,
+            unit: 32,
+            colors: Colors::default(),
+        }
+    }
+}
+
+/// Which kind of image to make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    Png,
+}
+
+impl Format {
+    /// The format for a file name ending in `.svg` or `.png`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Why an image could not be made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportError {
+    /// The window is not on the board.
+    Window,
+    /// The image would be empty or wider than [`MAX_PIXELS`].
+    Size,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Window => write!(f, "the area to draw is not on the board"),
+            Self::Size => write!(
+                f,
+                "the image would be empty or over {MAX_PIXELS} pixels wide"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Read a window of the board written as two corners, eg. `A1:J10` or
+/// `a1-j10`.
+pub fn parse_window(text: &str, side: u32) -> Option<[[u32; 2]; 2]> {
+    let (from, to) = text.split_once([':', '-'])?;
+    Some([
+        coord::from_pos_name(from, side)?,
+        coord::from_pos_name(to, side)?,
+    ])
+}
+
+/// Draw the current position of `game` as `format`.
+pub fn export(game: &Game, options: &Options, format: Format) -> Result<Vec<u8>, ExportError> {
+    match format {
+        Format::Svg => svg(game, options).map(String::into_bytes),
+        Format::Png => png(game, options),
+    }
+}
+
+/// Draw the current position of `game` as SVG.
+pub fn svg(game: &Game, options: &Options) -> Result<String, ExportError> {
+    let picture = Picture::new(game, options)?;
+    let mut out = String::new();
+    let (width, height) = (picture.width, picture.height);
+    // Writing to a `String` can not fail
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    for shape in &picture.shapes {
+        let _ = writeln!(out, "{}", shape.to_svg());
+    }
+    out.push_str("</svg>\n");
+    Ok(out)
+}
+
+/// Draw the current position of `game` as PNG.
+pub fn png(game: &Game, options: &Options) -> Result<Vec<u8>, ExportError> {
+    let picture = Picture::new(game, options)?;
+    let mut canvas = Canvas::new(picture.width, picture.height);
+    for shape in &picture.shapes {
+        canvas.draw(shape);
+    }
+
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, picture.width, picture.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // The image is in memory and its size was checked, so this can not fail
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&canvas.pixels))
+        .expect("Could not encode PNG");
+    Ok(bytes)
+}
+
+type Point = [f32; 2];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Rect {
+        min: Point,
+        max: Point,
+        color: Color,
+    },
+    Line {
+        from: Point,
+        to: Point,
+        width: f32,
+        color: Color,
+    },
+    Circle {
+        center: Point,
+        radius: f32,
+        color: Color,
+    },
+    Ring {
+        center: Point,
+        radius: f32,
+        width: f32,
+        color: Color,
+    },
+    /// The outline of a closed polygon.
+    Outline {
+        points: Vec<Point>,
+        width: f32,
+        color: Color,
+    },
+    /// Text centered on `center`, with a font size of `size` pixels.
+    Text {
+        center: Point,
+        size: f32,
+        text: String,
+        color: Color,
+    },
+}
+
+impl Shape {
+    fn to_svg(&self) -> String {
+        match self {
+            Self::Rect { min, max, color } => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                num(min[0]),
+                num(min[1]),
+                num(max[0] - min[0]),
+                num(max[1] - min[1]),
+                hex(*color)
+            ),
+            Self::Line {
+                from,
+                to,
+                width,
+                color,
+            } => format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="square"/>"#,
+                num(from[0]),
+                num(from[1]),
+                num(to[0]),
+                num(to[1]),
+                hex(*color),
+                num(*width)
+            ),
+            Self::Circle {
+                center,
+                radius,
+                color,
+            } => format!(
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                num(center[0]),
+                num(center[1]),
+                num(*radius),
+                hex(*color)
+            ),
+            Self::Ring {
+                center,
+                radius,
+                width,
+                color,
+            } => format!(
+                r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                num(center[0]),
+                num(center[1]),
+                num(*radius),
+                hex(*color),
+                num(*width)
+            ),
+            Self::Outline {
+                points,
+                width,
+                color,
+            } => {
+                let points: Vec<_> = points
+                    .iter()
+                    .map(|[x, y]| format!("{},{}", num(*x), num(*y)))
+                    .collect();
+                format!(
+                    r#"<polygon points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                    points.join(" "),
+                    hex(*color),
+                    num(*width)
+                )
+            }
+            Self::Text {
+                center,
+                size,
+                text,
+                color,
+            } => format!(
+                r#"<text x="{}" y="{}" font-size="{}" font-family="sans-serif" text-anchor="middle" dominant-baseline="central" fill="{}">{}</text>"#,
+                num(center[0]),
+                num(center[1]),
+                num(*size),
+                hex(*color),
+                escape(text)
+            ),
+        }
+    }
+}
+
+/// A number for SVG, without needless decimals.
+fn num(n: f32) -> String {
+    let text = format!("{n:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn hex([r, g, b]: Color) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Everything to draw, in order, on an image of the given size.
+#[derive(Debug)]
+struct Picture {
+    width: u32,
+    height: u32,
+    shapes: Vec<Shape>,
+}
+
+impl Picture {
+    fn new(game: &Game, options: &Options) -> Result<Self, ExportError> {
+        let side = game.board_size();
+        let last = side - 1;
+        let [[x0, y0], [x1, y1]] = match options.window {
+            None => [[0, 0], [last, last]],
+            Some([[ax, ay], [bx, by]]) => [[ax.min(bx), ay.min(by)], [ax.max(bx), ay.max(by)]],
+        };
+        if x1 > last || y1 > last {
+            return Err(ExportError::Window);
+        }
+
+        let coordinates = options.coordinates && side <= MAX_LABELED_SIZE;
+        let unit = options.unit as f32;
+        // Labels get a band 0.8 units wide on each side, as in the GUI
+        let margin = if coordinates { unit * 0.8 } else { 0.0 };
+        let width = ((x1 - x0 + 1) as f32 * unit + margin * 2.0).ceil();
+        let height = ((y1 - y0 + 1) as f32 * unit + margin * 2.0).ceil();
+        if options.unit == 0 || width > MAX_PIXELS as f32 || height > MAX_PIXELS as f32 {
+            return Err(ExportError::Size);
+        }
+
+        let colors = options.colors;
+        let center = |x: u32, y: u32| {
+            [
+                margin + ((x - x0) as f32 + 0.5) * unit,
+                margin + ((y - y0) as f32 + 0.5) * unit,
+            ]
+        };
+        let mut shapes = vec![Shape::Rect {
+            min: [0.0, 0.0],
+            max: [width, height],
+            color: colors.board,
+        }];
+
+        // Lines run out to the edge of the picture where the board goes on
+        let thickness = (unit / 32.0).max(1.0);
+        let [left, top] = center(x0, y0);
+        let [right, bottom] = center(x1, y1);
+        let top = if y0 == 0 { top } else { margin };
+        let bottom = if y1 == last { bottom } else { height - margin };
+        let left = if x0 == 0 { left } else { margin };
+        let right = if x1 == last { right } else { width - margin };
+        for x in x0..=x1 {
+            let [cx, _] = center(x, y0);
+            shapes.push(Shape::Line {
+                from: [cx, top],
+                to: [cx, bottom],
+                width: thickness,
+                color: colors.lines,
+            });
+        }
+        for y in y0..=y1 {
+            let [_, cy] = center(x0, y);
+            shapes.push(Shape::Line {
+                from: [left, cy],
+                to: [right, cy],
+                width: thickness,
+                color: colors.lines,
+            });
+        }
+        for [x, y] in coord::hoshi(side) {
+            if (x0..=x1).contains(&x) && (y0..=y1).contains(&y) {
+                shapes.push(Shape::Circle {
+                    center: center(x, y),
+                    radius: thickness * 3.0,
+                    color: colors.lines,
+                });
+            }
+        }
+
+        if coordinates {
+            let size = unit * 0.45;
+            let band = margin / 2.0;
+            for x in x0..=x1 {
+                let [cx, _] = center(x, y0);
+                let text = coord::column_label(x).to_string();
+                for cy in [band, height - band] {
+                    shapes.push(text_shape([cx, cy], size, &text, colors.lines));
+                }
+            }
+            for y in y0..=y1 {
+                let [_, cy] = center(x0, y);
+                let text = coord::row_label(y, side).to_string();
+                for cx in [band, width - band] {
+                    shapes.push(text_shape([cx, cy], size, &text, colors.lines));
+                }
+            }
+        }
+
+        let board = game.board();
+        let numbers = if options.move_numbers {
+            game.stone_move_numbers()
+        } else {
+            vec![]
+        };
+        let radius = unit * 0.48;
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let pos = center(x, y);
+                let piece = board.get(x, y);
+                match piece {
+                    Piece::None => {}
+                    Piece::Black => shapes.push(Shape::Circle {
+                        center: pos,
+                        radius,
+                        color: colors.black,
+                    }),
+                    Piece::White => {
+                        shapes.push(Shape::Circle {
+                            center: pos,
+                            radius,
+                            color: colors.white,
+                        });
+                        shapes.push(Shape::Ring {
+                            center: pos,
+                            radius: radius - thickness / 2.0,
+                            width: thickness,
+                            color: colors.lines,
+                        });
+                    }
+                }
+
+                let markup = game.mark_at([x, y]).filter(|_| options.marks);
+                let number = numbers.iter().find(|(p, _)| *p == [x, y]);
+                let mark = match (markup, number) {
+                    (Some(markup), _) => Mark::Markup(markup.clone()),
+                    (None, Some(&(_, number))) => Mark::Markup(Markup::Label(number.to_string())),
+                    _ if game.last_played_pos() == Some([x, y]) => Mark::Dot,
+                    _ => continue,
+                };
+                mark_shapes(&mut shapes, &mark, piece, pos, radius, &colors);
+            }
+        }
+
+        Ok(Self {
+            width: width as u32,
+            height: height as u32,
+            shapes,
+        })
+    }
+}
+
+enum Mark {
+    Markup(Markup),
+    /// The last move.
+    Dot,
+}
+
+/// Add the shapes for a mark on a point with `piece` on it.
+fn mark_shapes(
+    shapes: &mut Vec<Shape>,
+    mark: &Mark,
+    piece: Piece,
+    pos: Point,
+    radius: f32,
+    colors: &Colors,
+) {
+    let color = match piece {
+        Piece::None => colors.lines,
+        Piece::Black => colors.white,
+        Piece::White => colors.black,
+    };
+    let width = (radius / 8.0).max(1.0);
+    // Shapes are drawn a bit smaller than the stone
+    let r = radius * 0.55;
+    let [x, y] = pos;
+
+    let markup = match mark {
+        Mark::Dot => {
+            shapes.push(Shape::Circle {
+                center: pos,
+                radius: radius / 3.0,
+                color,
+            });
+            return;
+        }
+        Mark::Markup(markup) => markup,
+    };
+    match markup {
+        Markup::Triangle => shapes.push(Shape::Outline {
+            points: [-90.0_f32, 30.0, 150.0]
+                .into_iter()
+                .map(|deg| {
+                    let (sin, cos) = deg.to_radians().sin_cos();
+                    [x + cos * r, y + sin * r]
+                })
+                .collect(),
+            width,
+            color,
+        }),
+        Markup::Square => {
+            let d = r * 0.75;
+            shapes.push(Shape::Outline {
+                points: vec![
+                    [x - d, y - d],
+                    [x + d, y - d],
+                    [x + d, y + d],
+                    [x - d, y + d],
+                ],
+                width,
+                color,
+            });
+        }
+        Markup::Circle => shapes.push(Shape::Ring {
+            center: pos,
+            radius: r * 0.9,
+            width,
+            color,
+        }),
+        Markup::Cross => {
+            let d = r * 0.75;
+            for (from, to) in [
+                ([x - d, y - d], [x + d, y + d]),
+                ([x - d, y + d], [x + d, y - d]),
+            ] {
+                shapes.push(Shape::Line {
+                    from,
+                    to,
+                    width,
+                    color,
+                });
+            }
+        }
+        Markup::Label(text) => {
+            // Hide the board lines behind text on empty points
+            if piece == Piece::None {
+                shapes.push(Shape::Circle {
+                    center: pos,
+                    radius: radius * 0.8,
+                    color: colors.board,
+                });
+            }
+            let size = if text.chars().count() > 2 {
+                radius * 0.8
+            } else {
+                radius * 1.1
+            };
+            shapes.push(text_shape(pos, size, text, color));
+        }
+    }
+}
+
+fn text_shape(center: Point, size: f32, text: &str, color: Color) -> Shape {
+    Shape::Text {
+        center,
+        size,
+        text: text.to_string(),
+        color,
+    }
+}
+
+/// RGB pixels that shapes are drawn onto.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Each pixel is covered by this many samples across and down, to smooth
+/// out the edges of shapes.
+const SAMPLES: u32 = 4;
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 3) as usize],
+        }
+    }
+
+    fn draw(&mut self, shape: &Shape) {
+        match shape {
+            Shape::Rect { min, max, color } => {
+                self.fill(*min, *max, *color, |[x, y]| {
+                    x >= min[0] && x < max[0] && y >= min[1] && y < max[1]
+                });
+            }
+            Shape::Line {
+                from,
+                to,
+                width,
+                color,
+            } => {
+                let h = width / 2.0;
+                let min = [from[0].min(to[0]) - h, from[1].min(to[1]) - h];
+                let max = [from[0].max(to[0]) + h, from[1].max(to[1]) + h];
+                self.fill(min, max, *color, |p| segment_distance(p, *from, *to) <= h);
+            }
+            Shape::Circle {
+                center,
+                radius,
+                color,
+            } => {
+                let [cx, cy] = *center;
+                let min = [cx - radius, cy - radius];
+                let max = [cx + radius, cy + radius];
+                self.fill(min, max, *color, |p| distance(p, *center) <= *radius);
+            }
+            Shape::Ring {
+                center,
+                radius,
+                width,
+                color,
+            } => {
+                let [cx, cy] = *center;
+                let outer = radius + width / 2.0;
+                let min = [cx - outer, cy - outer];
+                let max = [cx + outer, cy + outer];
+                self.fill(min, max, *color, |p| {
+                    (distance(p, *center) - radius).abs() <= width / 2.0
+                });
+            }
+            Shape::Outline {
+                points,
+                width,
+                color,
+            } => {
+                let h = width / 2.0;
+                let min = points.iter().fold([f32::MAX; 2], |[x, y], p| {
+                    [x.min(p[0] - h), y.min(p[1] - h)]
+                });
+                let max = points.iter().fold([f32::MIN; 2], |[x, y], p| {
+                    [x.max(p[0] + h), y.max(p[1] + h)]
+                });
+                let edges: Vec<_> = points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .map(|(a, b)| (*a, *b))
+                    .collect();
+                self.fill(min, max, *color, |p| {
+                    edges.iter().any(|&(a, b)| segment_distance(p, a, b) <= h)
+                });
+            }
+            Shape::Text {
+                center,
+                size,
+                text,
+                color,
+            } => self.text(*center, *size, text, *color),
+        }
+    }
+
+    /// Text in the pixel font. Capitals are about 70% of the font size, as
+    /// in most fonts.
+    fn text(&mut self, center: Point, size: f32, text: &str, color: Color) {
+        let glyphs: Vec<_> = text.chars().filter_map(glyph).collect();
+        if glyphs.is_empty() {
+            return;
+        }
+        let dot = size * 0.7 / GLYPH_HEIGHT as f32;
+        let advance = (GLYPH_WIDTH + 1) as f32 * dot;
+        let width = glyphs.len() as f32 * advance - dot;
+        let left = center[0] - width / 2.0;
+        let top = center[1] - GLYPH_HEIGHT as f32 * dot / 2.0;
+        for (i, rows) in glyphs.iter().enumerate() {
+            let gx = left + i as f32 * advance;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    let min = [gx + column as f32 * dot, top + row as f32 * dot];
+                    let max = [min[0] + dot, min[1] + dot];
+                    self.draw(&Shape::Rect { min, max, color });
+                }
+            }
+        }
+    }
+
+    /// Blend `color` into the pixels between `min` and `max`, by how many
+    /// samples of each pixel are `inside`.
+    fn fill(&mut self, min: Point, max: Point, color: Color, inside: impl Fn(Point) -> bool) {
+        let x0 = min[0].floor().max(0.0) as u32;
+        let y0 = min[1].floor().max(0.0) as u32;
+        let x1 = (max[0].ceil().max(0.0) as u32).min(self.width);
+        let y1 = (max[1].ceil().max(0.0) as u32).min(self.height);
+        let step = 1.0 / SAMPLES as f32;
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let mut covered = 0;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let x = px as f32 + (sx as f32 + 0.5) * step;
+                        let y = py as f32 + (sy as f32 + 0.5) * step;
+                        covered += u32::from(inside([x, y]));
+                    }
+                }
+                if covered == 0 {
+                    continue;
+                }
+                let alpha = covered as f32 / (SAMPLES * SAMPLES) as f32;
+                let i = ((py * self.width + px) * 3) as usize;
+                for (channel, &c) in self.pixels[i..i + 3].iter_mut().zip(&color) {
+                    let blended = f32::from(*channel) * (1.0 - alpha) + f32::from(c) * alpha;
+                    *channel = blended.round() as u8;
+                }
+            }
+        }
+    }
+}
+
+fn distance([ax, ay]: Point, [bx, by]: Point) -> f32 {
+    (ax - bx).hypot(ay - by)
+}
+
+/// The distance from `p` to the closest point of the segment from `a` to
+/// `b`.
+fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+    let [dx, dy] = [b[0] - a[0], b[1] - a[1]];
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / length).clamp(0.0, 1.0)
+    };
+    distance(p, [a[0] + t * dx, a[1] + t * dy])
+}
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// The rows of the pixel font's glyph for `c`, top first, with the left
+/// column in the highest of the five bits. Lowercase letters are drawn as
+/// capitals.
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT as usize]> {
+    Some(match c.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(unit: u32, window: Option<[[u32; 2]; 2]>) -> Options {
+        Options {
+            window,
+            coordinates: false,
+            unit,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn windows_are_read_either_way_round() {
+        assert_eq!(parse_window("A1:J10", 19), Some([[0, 18], [8, 9]]));
+        assert_eq!(parse_window("j10-a1", 19), Some([[8, 9], [0, 18]]));
+        assert_eq!(parse_window("A1:Z30", 19), None);
+        assert_eq!(parse_window("A1:T20", 19), None);
+        assert_eq!(parse_window("A1", 19), None);
+    }
+
+    #[test]
+    fn window_corners_can_be_in_any_order() {
+        let game = Game::new(9);
+        let forward = Picture::new(&game, &options(10, Some([[0, 0], [3, 4]]))).unwrap();
+        let backward = Picture::new(&game, &options(10, Some([[3, 4], [0, 0]]))).unwrap();
+        assert_eq!((forward.width, forward.height), (40, 50));
+        assert_eq!(forward.shapes, backward.shapes);
+    }
+
+    #[test]
+    fn bad_windows_and_sizes_are_errors() {
+        let game = Game::new(9);
+        let off_board = options(10, Some([[0, 0], [9, 4]]));
+        assert_eq!(
+            Picture::new(&game, &off_board).err(),
+            Some(ExportError::Window)
+        );
+        assert_eq!(
+            Picture::new(&game, &options(0, None)).err(),
+            Some(ExportError::Size)
+        );
+        let too_large = options(MAX_PIXELS / 9 + 1, None);
+        assert_eq!(
+            Picture::new(&game, &too_large).err(),
+            Some(ExportError::Size)
+        );
+        assert!(Picture::new(&game, &options(MAX_PIXELS / 9, None)).is_ok());
+    }
+
+    #[test]
+    fn numbers_and_text_for_svg() {
+        assert_eq!(num(10.0), "10");
+        assert_eq!(num(100.0), "100");
+        assert_eq!(num(0.5), "0.5");
+        assert_eq!(num(2.25), "2.25");
+        assert_eq!(escape("a<b & c>"), "a&lt;b &amp; c&gt;");
+    }
+
+    #[test]
+    fn svg_has_the_image_size() {
+        let svg = svg(&Game::new(9), &options(10, None)).unwrap();
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="90" height="90""#)
+        );
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn png_has_the_image_size() {
+        let png = png(&Game::new(9), &options(10, None)).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        // The width and height come first in the header chunk
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], [0, 0, 0, 90, 0, 0, 0, 90]);
+    }
+}
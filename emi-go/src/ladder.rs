@@ -0,0 +1,255 @@
+//! Reading ladders: chasing a group across the board by keeping it in atari
+//! until it runs into the edge or other stones.
+//!
+//! The attacker only plays on the group's liberties, and the defender either
+//! runs by extending or captures an attacking stone that is in atari. A
+//! group in atari gets to run first, and a group with two liberties is
+//! chased first. Which player is actually to move is not taken into
+//! account, so a group in atari may be flagged as escaping even though it
+//! could just be captured.
+//!
+//! Other ways of catching a group, like nets, need more reading than this.
+//! See [`crate::solve`].
+
+use crate::{Board, Game, Move};
+
+/// The most positions to read for one ladder. Real ladders need far fewer,
+/// so running out means the group has too many ways to escape.
+const MAX_NODES: u32 = 10_000;
+
+/// A group that can be captured in a ladder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ladder {
+    /// The stones of the group.
+    pub stones: Vec<[u32; 2]>,
+    /// The moves of the ladder, ending with the capture.
+    pub moves: Vec<Move>,
+}
+
+/// Read whether the group at `pos` can be captured in a ladder. Returns the
+/// moves up to the capture if it can, or `None` if it escapes, or has no
+/// stones or more than two liberties.
+pub fn ladder(board: &Board, pos: [u32; 2]) -> Option<Vec<Move>> {
+    let [x, y] = pos;
+    if !board.coord_is_valid(x, y) {
+        return None;
+    }
+    let color = board.get(x, y);
+    let liberties = board.liberty_points(x, y)?.len();
+    let first = match liberties {
+        1 => color,
+        2 => color.opposing(),
+        _ => return None,
+    };
+
+    let mut game = Game::new(board.board_size());
+    game.set_position(board);
+    if game.next_to_play() != first {
+        game.next_turn();
+    }
+    let mut reader = Reader {
+        game,
+        target: pos,
+        nodes: 0,
+    };
+    if first == color {
+        reader.run()
+    } else {
+        reader.chase()
+    }
+}
+
+/// Every group on the board that can be captured in a ladder.
+pub fn ladders(board: &Board) -> Vec<Ladder> {
+    let side = board.board_size();
+    let mut seen = vec![false; (side * side) as usize];
+    let mut found = vec![];
+    for y in 0..side {
+        for x in 0..side {
+            if seen[(y * side + x) as usize] {
+                continue;
+            }
+            let Some(stones) = board.capture_(x, y) else {
+                continue;
+            };
+            for &[sx, sy] in &stones {
+                seen[(sy * side + sx) as usize] = true;
+            }
+            if let Some(moves) = ladder(board, [x, y]) {
+                found.push(Ladder { stones, moves });
+            }
+        }
+    }
+    found
+}
+
+#[derive(Debug)]
+struct Reader {
+    game: Game,
+    /// A stone of the group being chased.
+    target: [u32; 2],
+    nodes: u32,
+}
+
+impl Reader {
+    fn liberties(&self) -> Vec<[u32; 2]> {
+        let [x, y] = self.target;
+        self.game.board().liberty_points(x, y).unwrap_or_default()
+    }
+
+    /// Play `move_`, read on with `f`, then take it back. Returns `None` if
+    /// the move can not be played.
+    fn try_move<T>(
+        &mut self,
+        move_: Move,
+        f: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<Option<T>> {
+        self.game.play_(move_).ok()?;
+        let result = f(self);
+        self.game.undo();
+        Some(result)
+    }
+
+    /// The attacker to move. Returns the moves that capture the group.
+    fn chase(&mut self) -> Option<Vec<Move>> {
+        self.nodes += 1;
+        if self.nodes > MAX_NODES {
+            return None;
+        }
+        let color = self.game.next_to_play();
+        let liberties = self.liberties();
+        if liberties.len() > 2 {
+            return None;
+        }
+        for pos in liberties {
+            let move_ = Move::Place { pos, color };
+            let line = self.try_move(move_, |reader| match reader.liberties().len() {
+                // Captured
+                0 => Some(vec![]),
+                1 => reader.run(),
+                _ => None,
+            });
+            if let Some(Some(mut line)) = line {
+                line.insert(0, move_);
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    /// The group in atari with the defender to move. Returns the moves that
+    /// still capture it however it runs.
+    fn run(&mut self) -> Option<Vec<Move>> {
+        let color = self.game.next_to_play();
+        let mut escapes: Vec<_> = self.liberties();
+        escapes.extend(self.captures());
+
+        let mut best = None;
+        for pos in escapes {
+            let move_ = Move::Place { pos, color };
+            match self.try_move(move_, Self::chase) {
+                None => continue,
+                Some(None) => return None,
+                Some(Some(mut line)) => {
+                    line.insert(0, move_);
+                    best.get_or_insert(line);
+                }
+            }
+        }
+        match best {
+            Some(line) => Some(line),
+            // Nowhere to run, so the attacker captures right away
+            None => {
+                let mut line = self.try_move(Move::Pass, Self::chase)??;
+                line.insert(0, Move::Pass);
+                Some(line)
+            }
+        }
+    }
+
+    /// Points that capture an attacking group in atari next to the target.
+    fn captures(&self) -> Vec<[u32; 2]> {
+        let board = self.game.board();
+        let [x, y] = self.target;
+        let defender = board.get(x, y);
+        let mut points = vec![];
+        for [sx, sy] in board.capture_(x, y).unwrap_or_default() {
+            for (nx, ny) in board.surround(sx, sy) {
+                if board.get(nx, ny) != defender.opposing() {
+                    continue;
+                }
+                if let Some([lib]) = board.liberty_points(nx, ny).as_deref() {
+                    if !points.contains(lib) {
+                        points.push(*lib);
+                    }
+                }
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Piece;
+
+    /// White's stone in the middle has two liberties, and running leads it
+    /// towards the top right.
+    const LADDER: &str = "
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . X . . . . .
+        . . . X O . . . .
+        . . . . X X . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+    ";
+
+    #[test]
+    fn ladder_captures_on_an_empty_board() {
+        let board = Board::from_rows(LADDER);
+        let moves = ladder(&board, [4, 4]).unwrap();
+        assert_eq!(
+            moves.first(),
+            Some(&Move::Place {
+                pos: [5, 4],
+                color: Piece::Black
+            })
+        );
+        // The attacker makes the last move
+        assert_eq!(moves.len() % 2, 1);
+    }
+
+    #[test]
+    fn ladder_breaker_lets_the_group_escape() {
+        let mut board = Board::from_rows(LADDER);
+        *board.get_mut(6, 2) = Piece::White;
+        assert_eq!(ladder(&board, [4, 4]), None);
+    }
+
+    #[test]
+    fn breaker_on_the_other_side_does_not_help() {
+        let mut board = Board::from_rows(LADDER);
+        *board.get_mut(1, 7) = Piece::White;
+        assert!(ladder(&board, [4, 4]).is_some());
+    }
+
+    #[test]
+    fn three_liberties_is_not_a_ladder() {
+        let mut board = Board::from_rows(LADDER);
+        *board.get_mut(4, 5) = Piece::None;
+        assert_eq!(ladder(&board, [4, 4]), None);
+        assert_eq!(ladder(&board, [0, 0]), None);
+    }
+
+    #[test]
+    fn ladders_finds_the_chased_group() {
+        let board = Board::from_rows(LADDER);
+        let found = ladders(&board);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].stones, vec![[4, 4]]);
+    }
+}
@@ -1,11 +1,36 @@
+use std::time::Duration;
 #[allow(unused_imports)]
 use tracing::{debug, info, trace};
 
 mod board;
+mod clock;
+pub mod coord;
+pub mod diagram;
 mod event;
-
-use board::Board;
-use event::{Event, Events};
+pub mod export;
+mod ladder;
+mod life;
+mod markup;
+pub mod net;
+mod ownership;
+mod result;
+mod score;
+pub mod sgf;
+mod solver;
+pub mod tsumego;
+
+pub use board::Board;
+pub use clock::{Clock, PlayerClock, TimeControl};
+use event::Events;
+pub use event::{Event, ParseEventError};
+pub use ladder::{ladder, ladders, Ladder};
+pub use life::Eye;
+pub use markup::{Annotations, Markup};
+pub use ownership::Ownership;
+pub use result::{GameResult, ParseResultError, WinReason};
+pub use score::{DeadStoneEstimate, Score, DEAD_STONE_PLAYOUTS};
+pub use sgf::{GameInfo, SgfError};
+pub use solver::{solve, Solution, SolveError};
 
 /// A piece of either player's color. Or no piece.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -27,8 +52,16 @@ impl Piece {
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Move {
-    Place { pos: [u32; 2], color: Piece },
+    Place {
+        pos: [u32; 2],
+        color: Piece,
+    },
     Pass,
+    /// The player with stones of `color` gives up. Can be played on either
+    /// player's turn.
+    Resign {
+        color: Piece,
+    },
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -42,6 +75,15 @@ pub struct Game {
     /// Will be changed to support other variants of ko.
     ko_coord: Option<[u32; 2]>,
     events: Events,
+    /// Annotations for every node, starting with the empty board. Always
+    /// one longer than `events`.
+    annotations: Vec<Annotations>,
+    /// `None` for untimed games.
+    clock: Option<Clock>,
+    /// How the game ended, if it has.
+    result: Option<GameResult>,
+    /// Players, date and so on.
+    info: GameInfo,
 }
 
 impl Game {
@@ -51,6 +93,23 @@ impl Game {
             turn: Piece::Black,
             ko_coord: None,
             events: Events::new(),
+            annotations: vec![Annotations::default()],
+            clock: None,
+            result: None,
+            info: GameInfo::default(),
+        }
+    }
+
+    /// Create a timed game. The clock only runs when ticked with
+    /// [`Game::tick_clock`].
+    pub fn with_time_control(side: u32, control: TimeControl) -> Self {
+        let clock = match control {
+            TimeControl::None => None,
+            _ => Some(Clock::new(control)),
+        };
+        Self {
+            clock,
+            ..Self::new(side)
         }
     }
 
@@ -81,9 +140,11 @@ impl Game {
                 for [rx, ry] in captured {
                     board.remove(*rx, *ry);
                 }
-                if captured.len() == 1 {
-                    self.ko_coord = Some(*captured.last().unwrap());
-                }
+                // Only taking a single stone can start a ko
+                self.ko_coord = match captured[..] {
+                    [pos] => Some(pos),
+                    _ => None,
+                };
                 self.next_turn();
             }
             Edit {
@@ -98,7 +159,14 @@ impl Game {
                     self.apply_event_unchecked(edit);
                 }
             }
-            Pass { .. } => self.next_turn(),
+            Turn { to, .. } => self.turn = *to,
+            Pass { .. } => {
+                // Passing counts as a move elsewhere, so the ko can be taken
+                // back after it
+                self.ko_coord = None;
+                self.next_turn();
+            }
+            Resign { color } => self.result = Some(resignation(*color)),
         }
     }
 
@@ -141,10 +209,12 @@ impl Game {
                     self.reverse_event_unchecked(edit);
                 }
             }
+            Turn { from, .. } => self.turn = *from,
             Pass { prev_ko, .. } => {
                 self.ko_coord = *prev_ko;
                 self.prev_turn()
             }
+            Resign { .. } => self.result = None,
         }
     }
 
@@ -154,22 +224,22 @@ impl Game {
                 color: self.next_to_play(),
                 prev_ko: self.ko_coord,
             },
+            Move::Resign { color } => Event::Resign { color },
             Move::Place { pos: [x, y], color } => {
-                // Check for capture
-                let captures: Vec<_> = self
-                    .board
-                    .surround(x, y)
-                    .into_iter()
-                    .flat_map(|(nx, ny)| {
-                        if self.turn.opposing() != self.board.get(nx, ny) {
-                            vec![]
-                        } else if matches!(self.board.liberties(nx, ny), Some(0)) {
-                            self.board.capture_(nx, ny).unwrap()
-                        } else {
-                            vec![]
-                        }
-                    })
-                    .collect();
+                // Check for capture. The stone can touch the same chain
+                // from more than one side, so chains already taken are
+                // skipped.
+                let mut captures: Vec<[u32; 2]> = vec![];
+                for (nx, ny) in self.board.surround(x, y) {
+                    if self.turn.opposing() != self.board.get(nx, ny)
+                        || captures.contains(&[nx, ny])
+                    {
+                        continue;
+                    }
+                    if matches!(self.board.liberties(nx, ny), Some(0)) {
+                        captures.extend(self.board.capture_(nx, ny).unwrap());
+                    }
+                }
 
                 if !captures.is_empty() {
                     Event::Capture {
@@ -191,7 +261,13 @@ impl Game {
 
     /// Play a move
     pub fn play_(&mut self, move_: Move) -> Result<(), GoError> {
+        if self.result.is_some() {
+            return Err(GoError::GameOver { move_ });
+        }
         if let Move::Place { pos: [x, y], color } = move_ {
+            if x >= self.board_size() || y >= self.board_size() {
+                return Err(GoError::OutOfBounds { move_ });
+            }
             if self.board.get(x, y) != Piece::None {
                 return Err(GoError::NotEmpty { move_ });
             }
@@ -224,23 +300,197 @@ impl Game {
             }
         }
 
+        let mover = self.turn;
         self.apply_event_unchecked(&event);
 
         // Add to event list
         self.events.push(event);
 
+        let mut annotations = Annotations::default();
+        let resigned = matches!(move_, Move::Resign { .. });
+        if let Some(clock) = self.clock.as_mut().filter(|_| !resigned) {
+            clock.moved(mover);
+            let player = clock.player(mover);
+            annotations.time_left = Some(player.time_left());
+            annotations.overtime_left = player.overtime_left(clock.time_control());
+        }
+        self.annotations.push(annotations);
+
         Ok(())
     }
 
+    /// Apply an event and add it to the history without checking it is
+    /// legal.
+    pub(crate) fn push_event_unchecked(&mut self, event: Event) {
+        self.apply_event_unchecked(&event);
+        self.events.push(event);
+        self.annotations.push(Annotations::default());
+    }
+
+    /// Change the stones at some points, as one event so it can be undone
+    /// in one go. Points that already have the right stone are left out.
+    /// Returns whether anything changed.
+    pub fn edit(&mut self, changes: impl IntoIterator<Item = ([u32; 2], Piece)>) -> bool {
+        self.edit_to_play(changes, self.next_to_play())
+    }
+
+    /// Like [`Game::edit`], but also hand the turn to `to_play` as part of
+    /// the same event, so undoing gives the turn back too.
+    pub fn edit_to_play(
+        &mut self,
+        changes: impl IntoIterator<Item = ([u32; 2], Piece)>,
+        to_play: Piece,
+    ) -> bool {
+        // The last change to a point wins
+        let mut targets: Vec<([u32; 2], Piece)> = vec![];
+        for (pos @ [x, y], to) in changes {
+            if !self.board.coord_is_valid(x, y) {
+                continue;
+            }
+            match targets.iter_mut().find(|(p, _)| *p == pos) {
+                Some(target) => target.1 = to,
+                None => targets.push((pos, to)),
+            }
+        }
+
+        let mut edits: Vec<_> = targets
+            .into_iter()
+            .filter_map(|(pos @ [x, y], to)| {
+                let from = self.board.get(x, y);
+                (from != to).then_some(Event::Edit { pos, from, to })
+            })
+            .collect();
+        if to_play != Piece::None && to_play != self.turn {
+            edits.push(Event::Turn {
+                from: self.turn,
+                to: to_play,
+            });
+        }
+        match edits.len() {
+            0 => return false,
+            1 => self.push_event_unchecked(edits.remove(0)),
+            _ => self.push_event_unchecked(Event::Edits(edits)),
+        }
+        true
+    }
+
+    /// Change the stones to match `board`, as one event. Returns whether
+    /// anything changed, which it can not if the boards are different
+    /// sizes.
+    pub fn set_position(&mut self, board: &Board) -> bool {
+        self.set_position_to_play(board, self.next_to_play())
+    }
+
+    /// Like [`Game::set_position`], but also hand the turn to `to_play` as
+    /// part of the same event.
+    pub fn set_position_to_play(&mut self, board: &Board, to_play: Piece) -> bool {
+        let side = self.board_size();
+        if board.board_size() != side {
+            return false;
+        }
+        let points = (0..side).flat_map(|y| (0..side).map(move |x| [x, y]));
+        self.edit_to_play(points.map(|[x, y]| ([x, y], board.get(x, y))), to_play)
+    }
+
     pub fn undo(&mut self) {
-        let Some(last) = self.events.pop() else { return; };
+        let Some(last) = self.events.pop() else {
+            return;
+        };
+        self.annotations.pop();
         self.reverse_event_unchecked(&last);
+        // Only the last position can have a result, whether it came from
+        // resigning, counting or running out of time
+        self.result = None;
+        self.restore_clock();
+    }
+
+    /// Set each player's clock to the time left after their last move, as
+    /// stored in its annotations.
+    fn restore_clock(&mut self) {
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        for color in [Piece::Black, Piece::White] {
+            let last = self
+                .events
+                .iter()
+                .zip(&self.annotations[1..])
+                .rev()
+                .find(|(event, annotations)| {
+                    let mover = match event {
+                        Event::Play { color, .. }
+                        | Event::Capture { color, .. }
+                        | Event::Pass { color, .. } => Some(*color),
+                        _ => None,
+                    };
+                    mover == Some(color) && annotations.time_left.is_some()
+                })
+                .map(|(_, annotations)| annotations);
+            clock.restore(
+                color,
+                last.and_then(|a| a.time_left),
+                last.and_then(|a| a.overtime_left),
+            );
+        }
     }
 
     pub fn last_was_pass(&self) -> bool {
         self.events.last_was_pass()
     }
 
+    /// Returns true if both players passed in a row.
+    pub fn is_finished(&self) -> bool {
+        self.events.last_two_were_passes()
+    }
+
+    /// The clock, if the game is timed.
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Spend `elapsed` of the time of the player to move. Does nothing once
+    /// the game is over.
+    ///
+    /// Running out of time loses the game.
+    pub fn tick_clock(&mut self, elapsed: Duration) {
+        if self.is_over() {
+            return;
+        }
+        let turn = self.turn;
+        if let Some(clock) = &mut self.clock {
+            clock.tick(turn, elapsed);
+            if let Some(loser) = clock.flagged() {
+                self.result = Some(GameResult::Win {
+                    winner: loser.opposing(),
+                    by: WinReason::Time,
+                });
+            }
+        }
+    }
+
+    /// How the game ended, if it has. Games ended by both players passing
+    /// have no result until one is set with [`Game::set_result`].
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    /// End the game, eg. after counting or when a player forfeits. No more
+    /// moves can be played once the game has a result, until the last one
+    /// is taken back with [`Game::undo`].
+    pub fn set_result(&mut self, result: Option<GameResult>) {
+        self.result = result;
+    }
+
+    /// Returns true if the game has a result, or both players passed.
+    pub fn is_over(&self) -> bool {
+        self.result.is_some() || self.is_finished()
+    }
+
+    /// The player who ran out of time, if any.
+    pub fn timed_out(&self) -> Option<Piece> {
+        self.clock.as_ref()?.flagged()
+    }
+
     /// Play a move. (Old)
     pub fn play(&mut self, move_: Move) -> Result<(), GoError> {
         match move_ {
@@ -311,6 +561,10 @@ impl Game {
                 self.ko_coord = None;
                 Ok(())
             }
+            Move::Resign { color } => {
+                self.result = Some(resignation(color));
+                Ok(())
+            }
         }
     }
 
@@ -336,6 +590,51 @@ impl Game {
         self.board.board_size()
     }
 
+    /// A hash of the whole position: the stones, the player to move and the
+    /// ko point. Used to check two copies of a game are in sync.
+    pub fn position_hash(&self) -> u64 {
+        let mut hash = self.board.zobrist_hash();
+        if self.turn == Piece::White {
+            hash ^= board::splitmix64(u64::MAX);
+        }
+        if let Some([x, y]) = self.ko_coord {
+            hash ^= board::splitmix64(u64::MAX - 1 - u64::from(y * self.board_size() + x));
+        }
+        hash
+    }
+
+    /// Information about the game as a whole, like the players' names.
+    pub fn info(&self) -> &GameInfo {
+        &self.info
+    }
+
+    pub fn info_mut(&mut self) -> &mut GameInfo {
+        &mut self.info
+    }
+
+    /// Every event so far, oldest first.
+    pub fn events(&self) -> std::slice::Iter<'_, Event> {
+        self.events.iter()
+    }
+
+    /// The number of moves played so far, including passes but not edits.
+    pub fn moves_played(&self) -> u32 {
+        self.events.moves_played()
+    }
+
+    /// The number of stones the player with stones of `color` has
+    /// captured.
+    pub fn captures(&self, color: Piece) -> u32 {
+        self.events.captures(color)
+    }
+
+    /// The move number of every stone on the board that was played as a
+    /// move, for drawing numbered diagrams. Stones placed by edits are not
+    /// included.
+    pub fn stone_move_numbers(&self) -> Vec<([u32; 2], u32)> {
+        self.events.stone_move_numbers()
+    }
+
     /// The last played position if the last move was not a
     /// pass.
     pub fn last_played_pos(&self) -> Option<[u32; 2]> {
@@ -343,6 +642,15 @@ impl Game {
     }
 }
 
+/// The result of `color` resigning.
+fn resignation(color: Piece) -> GameResult {
+    GameResult::Win {
+        winner: color.opposing(),
+        by: WinReason::Resignation,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GoError {
     /// A move cannot be played because there is already a stone
     /// in that position.
@@ -353,4 +661,23 @@ pub enum GoError {
     SelfCapture { move_: Move },
     /// Illegal ko capture based on settings
     IllegalKo { move_: Move },
+    /// The game has already ended.
+    GameOver { move_: Move },
+    /// The position is not on the board.
+    OutOfBounds { move_: Move },
 }
+
+impl std::fmt::Display for GoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NotEmpty { .. } => write!(f, "There is already a stone there"),
+            Self::WrongTurn { .. } => write!(f, "It is the other player's turn"),
+            Self::SelfCapture { .. } => write!(f, "Self capture is not allowed"),
+            Self::IllegalKo { .. } => write!(f, "The ko can not be retaken yet"),
+            Self::GameOver { .. } => write!(f, "The game is over"),
+            Self::OutOfBounds { .. } => write!(f, "Not a point on the board"),
+        }
+    }
+}
+
+impl std::error::Error for GoError {}
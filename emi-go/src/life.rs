@@ -0,0 +1,287 @@
+//! Eyes, and groups that can never be captured.
+
+use crate::{Board, Piece};
+
+/// What kind of eye an empty point is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    /// The opponent can never play here.
+    Real,
+    /// Surrounded, but the opponent holds enough of the diagonals that the
+    /// stones around it can be cut apart and the point filled.
+    False,
+}
+
+impl Board {
+    /// Whether the point is an eye of `color`: empty, with every neighbor a
+    /// stone of `color`.
+    ///
+    /// It is false if the opponent holds two of the diagonal points, or one
+    /// on the edge of the board. This is only a shape, and does not know if
+    /// those opponent stones could be captured.
+    pub fn eye(&self, x: u32, y: u32, color: Piece) -> Option<Eye> {
+        if color == Piece::None || !self.is_simple_eye(x, y, color) {
+            return None;
+        }
+        let diagonals = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+            .into_iter()
+            .filter_map(|(dx, dy)| {
+                let nx = x.checked_add_signed(dx)?;
+                let ny = y.checked_add_signed(dy)?;
+                self.coord_is_valid(nx, ny).then_some((nx, ny))
+            })
+            .collect::<Vec<_>>();
+        let taken = diagonals
+            .iter()
+            .filter(|&&(nx, ny)| self.get(nx, ny) == color.opposing())
+            .count();
+        // Edge and corner points have fewer diagonals to spare
+        let allowed = if diagonals.len() == 4 { 1 } else { 0 };
+        if taken > allowed {
+            Some(Eye::False)
+        } else {
+            Some(Eye::Real)
+        }
+    }
+
+    /// Returns true if the point is a real eye of `color`.
+    pub fn is_real_eye(&self, x: u32, y: u32, color: Piece) -> bool {
+        self.eye(x, y, color) == Some(Eye::Real)
+    }
+
+    /// The real eyes next to the group the stone at the specified coordinate
+    /// is a part of.
+    ///
+    /// Returns `Option::None` if the specified coordinate is empty.
+    pub fn eyes(&self, x: u32, y: u32) -> Option<Vec<[u32; 2]>> {
+        let color = self.get(x, y);
+        let liberties = self.liberty_points(x, y)?;
+        Some(
+            liberties
+                .into_iter()
+                .filter(|&[lx, ly]| self.is_real_eye(lx, ly, color))
+                .collect(),
+        )
+    }
+
+    /// Find what `color` holds unconditionally with Benson's algorithm: the
+    /// groups that can not be captured even if `color` always passes, and
+    /// the regions they enclose as eyes. Any opponent stones in those regions
+    /// are dead.
+    ///
+    /// Returns whether each point is held, indexed by `y * side + x`.
+    pub fn unconditional_life(&self, color: Piece) -> Vec<bool> {
+        let side = self.board_size();
+        let len = (side * side) as usize;
+        let index = |x: u32, y: u32| (y * side + x) as usize;
+
+        // Number the chains of `color`, and the regions of everything else
+        let mut chain_of = vec![None; len];
+        let mut region_of = vec![None; len];
+        let mut chains: Vec<Vec<[u32; 2]>> = vec![];
+        let mut regions: Vec<Vec<[u32; 2]>> = vec![];
+        for y in 0..side {
+            for x in 0..side {
+                if chain_of[index(x, y)].is_some() || region_of[index(x, y)].is_some() {
+                    continue;
+                }
+                let in_chain = self.get(x, y) == color;
+                let mut points = vec![];
+                self.dfs((x, y), |nx, ny| {
+                    let same = (self.get(nx, ny) == color) == in_chain;
+                    if same {
+                        points.push([nx, ny]);
+                    }
+                    same
+                });
+                for &[px, py] in &points {
+                    if in_chain {
+                        chain_of[index(px, py)] = Some(chains.len());
+                    } else {
+                        region_of[index(px, py)] = Some(regions.len());
+                    }
+                }
+                if in_chain {
+                    chains.push(points);
+                } else {
+                    regions.push(points);
+                }
+            }
+        }
+
+        // The chains around each region, and the ones each region is vital
+        // to: every empty point in it is a liberty of the chain
+        let mut borders = vec![vec![]; regions.len()];
+        let mut vital_to = vec![vec![]; regions.len()];
+        for (r, region) in regions.iter().enumerate() {
+            let mut empties = 0;
+            let mut touching: Vec<(usize, usize)> = vec![];
+            for &[x, y] in region {
+                let empty = self.get(x, y) == Piece::None;
+                empties += usize::from(empty);
+                let mut next_to = vec![];
+                for (nx, ny) in self.surround(x, y) {
+                    if let Some(c) = chain_of[index(nx, ny)] {
+                        if !next_to.contains(&c) {
+                            next_to.push(c);
+                        }
+                    }
+                }
+                for c in next_to {
+                    match touching.iter_mut().find(|(chain, _)| *chain == c) {
+                        Some((_, count)) => *count += usize::from(empty),
+                        None => touching.push((c, usize::from(empty))),
+                    }
+                }
+            }
+            borders[r] = touching.iter().map(|&(c, _)| c).collect();
+            vital_to[r] = touching
+                .iter()
+                .filter(|&&(_, liberties)| empties > 0 && liberties == empties)
+                .map(|&(c, _)| c)
+                .collect();
+        }
+
+        // Drop chains with fewer than two vital regions, and regions next to
+        // dropped chains, until nothing changes
+        let mut chain_alive = vec![true; chains.len()];
+        let mut region_alive = vec![true; regions.len()];
+        loop {
+            let mut changed = false;
+            for (c, alive) in chain_alive.iter_mut().enumerate() {
+                if !*alive {
+                    continue;
+                }
+                let vital = (0..regions.len())
+                    .filter(|&r| region_alive[r] && vital_to[r].contains(&c))
+                    .count();
+                if vital < 2 {
+                    *alive = false;
+                    changed = true;
+                }
+            }
+            for (r, alive) in region_alive.iter_mut().enumerate() {
+                if *alive && borders[r].iter().any(|&c| !chain_alive[c]) {
+                    *alive = false;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut held = vec![false; len];
+        for (c, chain) in chains.iter().enumerate() {
+            if chain_alive[c] {
+                for &[x, y] in chain {
+                    held[index(x, y)] = true;
+                }
+            }
+        }
+        for (r, region) in regions.iter().enumerate() {
+            if region_alive[r] && vital_to[r].iter().any(|&c| chain_alive[c]) {
+                for &[x, y] in region {
+                    held[index(x, y)] = true;
+                }
+            }
+        }
+        held
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White has two real eyes in the corner, at (0, 5) and (2, 5).
+    const TWO_EYES: &str = "
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+        X X X X X . .
+        O O O O X . .
+        . O . O X . .
+        O O O O X . .
+    ";
+
+    #[test]
+    fn eye_in_the_middle_allows_one_diagonal() {
+        let board = Board::from_rows(
+            "
+            . . . . .
+            X O . . .
+            O . O . .
+            . O . . .
+            . . . . .
+            ",
+        );
+        assert_eq!(board.eye(1, 2, Piece::White), Some(Eye::Real));
+        assert_eq!(board.eye(1, 2, Piece::Black), None);
+        assert_eq!(board.eye(0, 0, Piece::White), None);
+    }
+
+    #[test]
+    fn two_diagonals_make_a_false_eye() {
+        let board = Board::from_rows(
+            "
+            . . . . .
+            X O . . .
+            O . O . .
+            . O X . .
+            . . . . .
+            ",
+        );
+        assert_eq!(board.eye(1, 2, Piece::White), Some(Eye::False));
+        assert!(!board.is_real_eye(1, 2, Piece::White));
+    }
+
+    #[test]
+    fn edge_eye_allows_no_diagonals() {
+        let board = Board::from_rows(
+            "
+            . . . . .
+            . . . . .
+            . . . . .
+            X O . . .
+            O . O . .
+            ",
+        );
+        assert_eq!(board.eye(1, 4, Piece::White), Some(Eye::False));
+    }
+
+    #[test]
+    fn eyes_of_a_group() {
+        let board = Board::from_rows(TWO_EYES);
+        let mut eyes = board.eyes(1, 5).unwrap();
+        eyes.sort();
+        assert_eq!(eyes, vec![[0, 5], [2, 5]]);
+        assert_eq!(board.eyes(3, 3), Some(vec![]));
+        assert_eq!(board.eyes(6, 0), None);
+    }
+
+    #[test]
+    fn two_eyes_are_unconditionally_alive() {
+        let board = Board::from_rows(TWO_EYES);
+        let side = board.board_size();
+        let alive = board.unconditional_life(Piece::White);
+        for [x, y] in [[0, 4], [3, 6], [0, 5], [2, 5]] {
+            assert!(alive[(y * side + x) as usize], "({x}, {y})");
+        }
+        // Black has no eyes at all
+        assert!(!board
+            .unconditional_life(Piece::Black)
+            .iter()
+            .any(|&held| held));
+    }
+
+    #[test]
+    fn one_eye_is_not_alive() {
+        let mut board = Board::from_rows(TWO_EYES);
+        *board.get_mut(2, 5) = Piece::White;
+        assert!(!board
+            .unconditional_life(Piece::White)
+            .iter()
+            .any(|&held| held));
+    }
+}
@@ -1,3 +1,496 @@
+//! A terminal client for when the GUI is not around, eg. over SSH.
+//!
+//! Both players share the terminal and type their moves in turn. The board
+//! is drawn with Unicode, and ANSI colors unless `NO_COLOR` is set or the
+//! output is not a terminal.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use emi_go::export::{self, Format, Options};
+use emi_go::{coord, diagram, Game, Move, Piece, DEAD_STONE_PLAYOUTS};
+
+const USAGE: &str = "\
+Usage:
+    emi-go play [SIZE] [--komi KOMI]    Start a game, 19x19 by default
+    emi-go play FILE.sgf                Carry on a game from SGF
+    emi-go export FILE IMAGE [OPTIONS]  Draw the position in an SGF file or a
+                                        diagram as an .svg or .png image
+    emi-go help                         Show this message
+
+Export options:
+    --move N            The position after move N instead of the last one
+    --window A1:J10     Only the points between two corners
+    --numbers           Number the stones that were played
+    --no-coordinates    Leave out the coordinates
+    --no-marks          Leave out marks and labels
+    --unit PIXELS       The distance between lines, 32 by default";
+
+const COMMANDS: &str = "\
+Commands:
+    D4, q16, ...    Play a stone there, or mark a group dead or alive when
+                    counting
+    pass            Pass. Counting starts once both players pass
+    undo            Take back the last move
+    resign          Resign for the player to move
+    done            Accept the count and end the game
+    save FILE       Save the game as SGF
+    help            Show this message
+    quit            Leave without saving";
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None | Some("play") => new_game(args.get(1..).unwrap_or_default()).and_then(play),
+        Some("export") => export_image(&args[1..]),
+        Some("help" | "-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(command) => Err(format!("Unknown command `{command}`\n\n{USAGE}")),
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// Start the game asked for by the arguments after `play`.
+fn new_game(args: &[String]) -> Result<Game, String> {
+    let mut size = 19;
+    let mut komi = 7.5;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--komi" {
+            let value = args.next().ok_or("--komi needs a value")?;
+            komi = value.parse().map_err(|_| format!("Bad komi `{value}`"))?;
+        } else if let Ok(n) = arg.parse::<u32>() {
+            if !(2..=coord::MAX_LABELED_SIZE).contains(&n) {
+                return Err(format!("Boards can be 2 to {}", coord::MAX_LABELED_SIZE));
+            }
+            size = n;
+        } else {
+            file = Some(arg);
+        }
+    }
+
+    let Some(file) = file else {
+        let mut game = Game::new(size);
+        game.info_mut().komi = komi;
+        return Ok(game);
+    };
+    let text = fs::read_to_string(file).map_err(|e| format!("Could not read {file}: {e}"))?;
+    let game = Game::from_sgf(&text).map_err(|e| format!("Could not open {file}: {e}"))?;
+    if game.board_size() > coord::MAX_LABELED_SIZE {
+        return Err(format!("{file} is too large to play here"));
+    }
+    Ok(game)
+}
+
+/// The arguments after `export`.
+#[derive(Debug)]
+struct ExportArgs<'a> {
+    file: &'a str,
+    image: &'a str,
+    options: Options,
+    /// The window as typed, which needs the board size to read.
+    window: Option<&'a str>,
+    move_number: Option<u32>,
+}
+
+fn export_args(args: &[String]) -> Result<ExportArgs<'_>, String> {
+    let mut options = Options::default();
+    let mut window = None;
+    let mut move_number = None;
+    let mut files = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--move" => {
+                let value = value()?;
+                move_number = Some(value.parse().map_err(|_| format!("Bad move `{value}`"))?);
+            }
+            "--window" => window = Some(value()?.as_str()),
+            "--numbers" => options.move_numbers = true,
+            "--no-coordinates" => options.coordinates = false,
+            "--no-marks" => options.marks = false,
+            "--unit" => {
+                let value = value()?;
+                options.unit = value.parse().map_err(|_| format!("Bad unit `{value}`"))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option `{arg}`")),
+            _ => files.push(arg.as_str()),
+        }
+    }
+    let [file, image] = files[..] else {
+        return Err(format!(
+            "Expected a file to read and an image to write\n\n{USAGE}"
+        ));
+    };
+    Ok(ExportArgs {
+        file,
+        image,
+        options,
+        window,
+        move_number,
+    })
+}
+
+/// Draw a position as asked for by the arguments after `export`.
+fn export_image(args: &[String]) -> Result<(), String> {
+    let ExportArgs {
+        file,
+        image,
+        mut options,
+        window,
+        move_number,
+    } = export_args(args)?;
+
+    let format = Format::from_path(Path::new(image))
+        .ok_or_else(|| format!("{image} should end in .svg or .png"))?;
+    let text = fs::read_to_string(file).map_err(|e| format!("Could not read {file}: {e}"))?;
+    let mut game = if file.to_lowercase().ends_with(".sgf") {
+        Game::from_sgf(&text).map_err(|e| format!("Could not open {file}: {e}"))?
+    } else {
+        diagram::parse_game(&text).map_err(|e| format!("Could not read {file}: {e}"))?
+    };
+    if let Some(n) = move_number {
+        if n > game.moves_played() {
+            return Err(format!("{file} only has {} moves", game.moves_played()));
+        }
+        while game.moves_played() > n {
+            game.undo();
+        }
+    }
+    if let Some(window) = window {
+        options.window = Some(
+            export::parse_window(window, game.board_size())
+                .ok_or_else(|| format!("Bad window `{window}`, expected eg. A1:J10"))?,
+        );
+    }
+
+    let bytes = export::export(&game, &options, format)
+        .map_err(|e| format!("Could not draw {file}: {e}"))?;
+    fs::write(image, bytes).map_err(|e| format!("Could not save {image}: {e}"))
+}
+
+/// Read commands until the players quit, or run out of input.
+fn play(mut game: Game) -> Result<(), String> {
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    // Stones marked dead, once both players pass
+    let mut dead: Option<Vec<[u32; 2]>> = None;
+    println!("{COMMANDS}\n");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if game.is_finished() && game.result().is_none() {
+            dead.get_or_insert_with(|| game.estimate_dead_stones(DEAD_STONE_PLAYOUTS));
+        } else {
+            dead = None;
+        }
+        print!("{}", render(&game, dead.as_deref(), color));
+        println!("{}", status(&game, dead.as_deref()));
+        print!("> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line.map_err(|e| e.to_string())?;
+        match run_command(&mut game, &mut dead, &line) {
+            Reply::Quit => return Ok(()),
+            Reply::Continue(Some(reply)) => println!("{reply}"),
+            Reply::Continue(None) => {}
+        }
+    }
+}
+
+/// What to do after a command.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// Carry on, after showing this if there is anything to say.
+    Continue(Option<String>),
+    Quit,
+}
+
+/// Carry out one line typed by the players. `dead` holds the stones marked
+/// dead while counting.
+fn run_command(game: &mut Game, dead: &mut Option<Vec<[u32; 2]>>, line: &str) -> Reply {
+    let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+    let reply = match command.to_lowercase().as_str() {
+        "" => None,
+        "quit" | "exit" => return Reply::Quit,
+        "help" | "?" => Some(COMMANDS.to_string()),
+        "pass" => game.play_(Move::Pass).err().map(|e| e.to_string()),
+        "undo" => {
+            game.undo();
+            None
+        }
+        "resign" => {
+            let color = game.next_to_play();
+            game.play_(Move::Resign { color })
+                .err()
+                .map(|e| e.to_string())
+        }
+        "done" => match dead {
+            Some(dead) => {
+                game.set_result(Some(game.area_score(dead).result()));
+                None
+            }
+            None => Some("Counting starts once both players pass".to_string()),
+        },
+        "save" if rest.is_empty() => Some("Save where?".to_string()),
+        "save" => match fs::write(rest.trim(), game.to_sgf()) {
+            Ok(()) => Some(format!("Saved to {}", rest.trim())),
+            Err(e) => Some(format!("Could not save: {e}")),
+        },
+        name => match coord::from_pos_name(name, game.board_size()) {
+            None => Some(format!("Unknown command `{command}`. Type `help` for help")),
+            Some(pos) => match dead {
+                Some(dead) => {
+                    game.toggle_dead(dead, pos);
+                    None
+                }
+                None => {
+                    let color = game.next_to_play();
+                    let move_ = Move::Place { pos, color };
+                    game.play_(move_).err().map(|e| e.to_string())
+                }
+            },
+        },
+    };
+    Reply::Continue(reply)
+}
+
+/// Who is to play, the captures so far and how the game ended.
+fn status(game: &Game, dead: Option<&[[u32; 2]]>) -> String {
+    let captures = format!(
+        "Captures: Black {}, White {}",
+        game.captures(Piece::Black),
+        game.captures(Piece::White)
+    );
+    if let Some(result) = game.result() {
+        return format!("{captures}\n{}", result.describe());
+    }
+    if let Some(dead) = dead {
+        let score = game.area_score(dead);
+        return format!(
+            "{captures}\nCounting: Black {}, White {} + {} komi. {}\n\
+             Type points to mark groups dead or alive, then `done`",
+            score.black,
+            score.white,
+            score.komi,
+            score.result().describe()
+        );
+    }
+    let turn = match game.next_to_play() {
+        Piece::White => "White",
+        _ => "Black",
+    };
+    let mut status = format!("{captures}\n{turn} to play");
+    if game.last_was_pass() {
+        status += " (the last move was a pass)";
+    }
+    status
+}
+
+/// Draw the board with coordinates around it. The last move is ringed,
+/// and while counting, dead stones are crossed out and territory dotted.
+fn render(game: &Game, dead: Option<&[[u32; 2]]>, color: bool) -> String {
+    const BOARD: &str = "\x1b[48;5;179m\x1b[38;5;94m";
+    const BLACK: &str = "\x1b[30m";
+    const WHITE: &str = "\x1b[97m";
+    const RESET: &str = "\x1b[0m";
+    let paint = |code: &'static str| if color { code } else { "" };
+
+    let board = game.board();
+    let side = game.board_size();
+    let owners = dead.map(|dead| {
+        let mut counted = board.clone();
+        for &[x, y] in dead {
+            counted.remove(x, y);
+        }
+        counted.area_owners()
+    });
+    let hoshi = coord::hoshi(side);
+    let columns: String = (0..side)
+        .map(|x| format!("{} ", coord::column_label(x)))
+        .collect();
+
+    let mut out = format!("    {columns}\n");
+    for y in 0..side {
+        let row = coord::row_label(y, side);
+        out += &format!("{row:>2} {} ", paint(BOARD));
+        for x in 0..side {
+            let piece = board.get(x, y);
+            let is_dead = dead.is_some_and(|dead| dead.contains(&[x, y]));
+            let owner = owners
+                .as_ref()
+                .map_or(Piece::None, |o| o[(y * side + x) as usize]);
+            let (glyph, stone) = match piece {
+                Piece::None => match owner {
+                    Piece::Black => ("▪", BLACK),
+                    Piece::White => ("▪", WHITE),
+                    Piece::None => (grid(x, y, side, &hoshi), ""),
+                },
+                _ if is_dead => ("×", if piece == Piece::Black { BLACK } else { WHITE }),
+                _ if game.last_played_pos() == Some([x, y]) => match piece {
+                    Piece::Black => ("◉", BLACK),
+                    _ => ("◎", WHITE),
+                },
+                Piece::Black => ("●", BLACK),
+                Piece::White => ("○", WHITE),
+            };
+            out += paint(stone);
+            out += glyph;
+            out += paint(BOARD);
+            out += if x + 1 < side { "─" } else { " " };
+        }
+        out += &format!("{} {row}\n", paint(RESET));
+    }
+    out += &format!("    {columns}\n");
+    out
+}
+
+/// The line drawing character for an empty point.
+fn grid(x: u32, y: u32, side: u32, hoshi: &[[u32; 2]]) -> &'static str {
+    let last = side - 1;
+    match (x, y) {
+        (0, 0) => "┌",
+        (x, 0) if x == last => "┐",
+        (0, y) if y == last => "└",
+        (x, y) if x == last && y == last => "┘",
+        (_, 0) => "┬",
+        (_, y) if y == last => "┴",
+        (0, _) => "├",
+        (x, _) if x == last => "┤",
+        _ if hoshi.contains(&[x, y]) => "╋",
+        _ => "┼",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn games_start_from_the_size_and_komi() {
+        let game = new_game(&[]).unwrap();
+        assert_eq!((game.board_size(), game.info().komi), (19, 7.5));
+        let game = new_game(&args("9 --komi 6.5")).unwrap();
+        assert_eq!((game.board_size(), game.info().komi), (9, 6.5));
+
+        assert_eq!(
+            new_game(&args("--komi")).err().unwrap(),
+            "--komi needs a value"
+        );
+        assert_eq!(
+            new_game(&args("--komi lots")).err().unwrap(),
+            "Bad komi `lots`"
+        );
+        assert!(new_game(&args("1")).is_err());
+        assert!(new_game(&args("26")).is_err());
+        let missing = new_game(&args("no-such-game.sgf")).err().unwrap();
+        assert!(missing.starts_with("Could not read no-such-game.sgf"));
+    }
+
+    #[test]
+    fn export_options_are_read() {
+        let line = args("game.sgf out.png");
+        let export = export_args(&line).unwrap();
+        assert_eq!((export.file, export.image), ("game.sgf", "out.png"));
+        assert_eq!(export.options, Options::default());
+        assert_eq!((export.window, export.move_number), (None, None));
+
+        let line = args(
+            "--move 12 game.sgf --window A1:E5 --numbers --no-coordinates --no-marks \
+             --unit 20 out.svg",
+        );
+        let export = export_args(&line).unwrap();
+        assert_eq!((export.file, export.image), ("game.sgf", "out.svg"));
+        assert_eq!(
+            (export.window, export.move_number),
+            (Some("A1:E5"), Some(12))
+        );
+        assert!(export.options.move_numbers);
+        assert!(!export.options.coordinates && !export.options.marks);
+        assert_eq!(export.options.unit, 20);
+    }
+
+    #[test]
+    fn bad_export_options_are_errors() {
+        for (line, error) in [
+            ("game.sgf out.png --unit", "--unit needs a value"),
+            ("game.sgf out.png --unit big", "Bad unit `big`"),
+            ("game.sgf out.png --move last", "Bad move `last`"),
+            ("game.sgf out.png --colour", "Unknown option `--colour`"),
+        ] {
+            assert_eq!(export_args(&args(line)).err().unwrap(), error, "{line}");
+        }
+        let error = export_args(&args("game.sgf")).err().unwrap();
+        assert!(error.starts_with("Expected a file to read and an image to write"));
+    }
+
+    #[test]
+    fn commands_play_the_game() {
+        let mut game = Game::new(9);
+        let mut dead = None;
+        let mut run = |game: &mut Game, line| run_command(game, &mut dead, line);
+
+        assert_eq!(run(&mut game, "D4"), Reply::Continue(None));
+        assert_eq!(game.board().get(3, 5), Piece::Black);
+        assert_eq!(run(&mut game, " e5 "), Reply::Continue(None));
+        assert_eq!(game.board().get(4, 4), Piece::White);
+        assert!(matches!(run(&mut game, "d4"), Reply::Continue(Some(_))));
+        assert_eq!(run(&mut game, "undo"), Reply::Continue(None));
+        assert_eq!(game.board().get(4, 4), Piece::None);
+
+        assert_eq!(run(&mut game, ""), Reply::Continue(None));
+        assert_eq!(
+            run(&mut game, "play d4"),
+            Reply::Continue(Some(
+                "Unknown command `play`. Type `help` for help".to_string()
+            ))
+        );
+        assert_eq!(
+            run(&mut game, "save"),
+            Reply::Continue(Some("Save where?".to_string()))
+        );
+        assert_eq!(
+            run(&mut game, "done"),
+            Reply::Continue(Some("Counting starts once both players pass".to_string()))
+        );
+        assert_eq!(run(&mut game, "resign"), Reply::Continue(None));
+        assert_eq!(game.result().and_then(|r| r.winner()), Some(Piece::Black));
+        assert_eq!(run(&mut game, "QUIT"), Reply::Quit);
+    }
+
+    #[test]
+    fn commands_count_the_game() {
+        let mut game = Game::new(9);
+        for line in ["c3", "g7", "pass", "pass"] {
+            run_command(&mut game, &mut None, line);
+        }
+        assert!(game.is_finished());
+        let mut dead = Some(vec![]);
+        assert_eq!(
+            run_command(&mut game, &mut dead, "g7"),
+            Reply::Continue(None)
+        );
+        assert_eq!(dead, Some(vec![[6, 2]]));
+        assert_eq!(
+            run_command(&mut game, &mut dead, "done"),
+            Reply::Continue(None)
+        );
+        assert_eq!(game.result().and_then(|r| r.winner()), Some(Piece::Black));
+    }
 }
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use crate::Game;
+
+/// A mark placed on the board while reviewing a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Markup {
+    /// SGF `CR`
+    Circle,
+    /// SGF `MA`
+    Cross,
+    /// SGF `TR`
+    Triangle,
+    /// SGF `SQ`
+    Square,
+    /// A short piece of text, usually a number or a letter. SGF `LB`
+    Label(String),
+}
+
+impl Markup {
+    /// The SGF property this mark is stored under.
+    pub fn sgf_property(&self) -> &'static str {
+        match self {
+            Self::Circle => "CR",
+            Self::Cross => "MA",
+            Self::Triangle => "TR",
+            Self::Square => "SQ",
+            Self::Label(_) => "LB",
+        }
+    }
+}
+
+/// Extra information attached to one position (node) of a game.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Annotations {
+    /// Marks on the board, at most one per position.
+    pub marks: Vec<([u32; 2], Markup)>,
+    /// Time left for the player who just moved. SGF `BL`/`WL`
+    pub time_left: Option<Duration>,
+    /// Overtime periods or stones left for the player who just moved.
+    /// SGF `OB`/`OW`
+    pub overtime_left: Option<u32>,
+    /// A comment on the position, empty if there is none. SGF `C`
+    pub comment: String,
+}
+
+impl Game {
+    /// The number of moves and edits made so far. Also the index of the
+    /// current node.
+    pub fn move_number(&self) -> usize {
+        self.annotations.len() - 1
+    }
+
+    /// Annotations on the current position.
+    pub fn annotations(&self) -> &Annotations {
+        self.annotations
+            .last()
+            .expect("There is always a root node")
+    }
+
+    fn annotations_mut(&mut self) -> &mut Annotations {
+        self.annotations
+            .last_mut()
+            .expect("There is always a root node")
+    }
+
+    /// All the marks on the current position.
+    pub fn marks(&self) -> &[([u32; 2], Markup)] {
+        &self.annotations().marks
+    }
+
+    /// The mark at `pos` on the current position, if any.
+    pub fn mark_at(&self, pos: [u32; 2]) -> Option<&Markup> {
+        self.marks()
+            .iter()
+            .find(|(mark_pos, _)| *mark_pos == pos)
+            .map(|(_, mark)| mark)
+    }
+
+    /// Place a mark at `pos` on the current position, replacing any mark
+    /// already there. `None` removes the mark.
+    pub fn set_mark(&mut self, pos: [u32; 2], mark: Option<Markup>) {
+        let marks = &mut self.annotations_mut().marks;
+        marks.retain(|(mark_pos, _)| *mark_pos != pos);
+        if let Some(mark) = mark {
+            marks.push((pos, mark));
+        }
+    }
+
+    /// Remove every mark from the current position.
+    pub fn clear_marks(&mut self) {
+        self.annotations_mut().marks.clear();
+    }
+
+    /// The comment on the current position, empty if there is none.
+    pub fn comment(&self) -> &str {
+        &self.annotations().comment
+    }
+
+    /// Replace the comment on the current position.
+    pub fn set_comment(&mut self, comment: impl Into<String>) {
+        self.annotations_mut().comment = comment.into();
+    }
+
+    /// Replace the comment on the position after `node` moves and edits.
+    /// Returns false if there is no such node.
+    pub fn set_comment_at(&mut self, node: usize, comment: impl Into<String>) -> bool {
+        match self.annotations.get_mut(node) {
+            Some(annotations) => {
+                annotations.comment = comment.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every node with a comment, as its number and the comment.
+    pub fn comments(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.annotations
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| !a.comment.is_empty())
+            .map(|(node, a)| (node, a.comment.as_str()))
+    }
+}
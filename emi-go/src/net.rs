@@ -0,0 +1,626 @@
+//! Two player games over TCP, for playing across a local network.
+//!
+//! One side hosts and picks the colors, and the other joins by address.
+//! Both sides keep their own [`Game`] and send each move as an [`Event`]
+//! along with the position hash after it, so a mismatch is noticed right
+//! away. Messages are one line each:
+//!
+//! - `emi <version> <size> <joiner color>`: sent by the host on connect
+//! - `history <n>` followed by `n` events: the host's game so far
+//! - `sync <hash>`: the host's position hash after the history
+//! - `move <hash> <event>`: a move played by the sender
+//! - `resync`: asks the host to send the game again
+//! - `chat <text>`: a chat message
+//! - `comment <node> <text>`: the comment on the position after `node`
+//!   moves and edits, replacing any comment there
+//!
+//! Events are written with [`Event`]'s `Display`, and hashes are
+//! [`Game::position_hash`] in hex. Text has backslashes and newlines
+//! escaped as `\\` and `\n`. If the connection drops, the host waits for
+//! the other player to connect again and the joiner keeps retrying. The
+//! host's game, along with its comments, is sent again whenever they
+//! reconnect.
+//!
+//! Time controls are not shared, so network games are untimed.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+use crate::{Event, Game, Move, Piece};
+
+/// Bumped whenever the messages change.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The port used when none is given.
+pub const DEFAULT_PORT: u16 = 7650;
+
+/// How long to wait between attempts to reconnect to the host.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long one attempt to connect may block for.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Something that happened while polling a [`Session`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetEvent {
+    /// Connected to the other player, and both games match.
+    Connected,
+    /// Lost the connection. The session keeps trying to get it back.
+    Disconnected,
+    /// The other player played a move.
+    Moved(Move),
+    /// The other player sent a chat message.
+    Chat(String),
+    /// The other player changed the comment on a position.
+    Commented { node: usize },
+    /// The games did not match, so the joiner's game was replaced with the
+    /// host's.
+    Resynced,
+    /// The other player sent something that could not be understood or
+    /// played.
+    Rejected(String),
+}
+
+/// A connection to the other player, buffering whole lines in and out.
+#[derive(Debug)]
+struct Peer {
+    stream: TcpStream,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// The other side closed the connection.
+    closed: bool,
+}
+
+impl Peer {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            input: vec![],
+            output: vec![],
+            closed: false,
+        })
+    }
+
+    fn send(&mut self, line: &str) {
+        debug!("Sending {:?}", line);
+        self.output.extend_from_slice(line.as_bytes());
+        self.output.push(b'\n');
+    }
+
+    /// Write out what can be written and read whatever has arrived,
+    /// returning complete lines.
+    fn pump(&mut self) -> io::Result<Vec<String>> {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => _ = self.output.drain(..n),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut buf = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut lines = vec![];
+        while let Some(end) = self.input.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            lines.push(line.trim_end_matches('\r').to_string());
+        }
+        Ok(lines)
+    }
+}
+
+#[derive(Debug)]
+enum Role {
+    Host {
+        listener: TcpListener,
+        /// The color the other player plays.
+        joiner_color: Piece,
+    },
+    Join {
+        addr: SocketAddr,
+        last_attempt: Instant,
+    },
+}
+
+/// The joiner's progress through the host's greeting.
+#[derive(Debug, Clone, PartialEq)]
+enum Handshake {
+    /// Waiting for `emi`.
+    Hello,
+    /// Waiting for `history`.
+    History { size: u32 },
+    /// Reading `remaining` more events.
+    Events {
+        size: u32,
+        remaining: usize,
+        events: Vec<Event>,
+    },
+    /// Waiting for `sync`.
+    Sync { size: u32, events: Vec<Event> },
+    /// Playing.
+    Done,
+}
+
+/// One side of a network game.
+///
+/// Call [`Session::poll`] regularly (eg. every frame) to send and receive
+/// moves, and [`Session::send_last_move`] after playing a local move.
+#[derive(Debug)]
+pub struct Session {
+    role: Role,
+    peer: Option<Peer>,
+    /// The color played on this side. `Piece::None` until a joiner has
+    /// heard from the host.
+    local_color: Piece,
+    handshake: Handshake,
+}
+
+impl Session {
+    /// Wait for another player to join on `addr`, playing `local_color`.
+    pub fn host(addr: impl ToSocketAddrs, local_color: Piece) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("Hosting on {}", listener.local_addr()?);
+        Ok(Self {
+            role: Role::Host {
+                listener,
+                joiner_color: local_color.opposing(),
+            },
+            peer: None,
+            local_color,
+            handshake: Handshake::Done,
+        })
+    }
+
+    /// Join a game hosted at `addr`. The host picks the colors and the board
+    /// size, and its game replaces the local one once connected.
+    pub fn join(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No address to join"))?;
+        let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+        info!("Joined {}", addr);
+        Ok(Self {
+            role: Role::Join {
+                addr,
+                last_attempt: Instant::now(),
+            },
+            peer: Some(Peer::new(stream)?),
+            local_color: Piece::None,
+            handshake: Handshake::Hello,
+        })
+    }
+
+    /// The address this side is listening on or connected from.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match (&self.role, &self.peer) {
+            (Role::Host { listener, .. }, _) => listener.local_addr(),
+            (Role::Join { .. }, Some(peer)) => peer.stream.local_addr(),
+            (Role::Join { .. }, None) => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// The color played on this side, or `Piece::None` if a joiner has not
+    /// heard from the host yet.
+    pub fn local_color(&self) -> Piece {
+        self.local_color
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    /// Returns true if the other player is connected and both games are in
+    /// sync.
+    pub fn is_connected(&self) -> bool {
+        self.peer.is_some() && self.handshake == Handshake::Done
+    }
+
+    /// Returns true if it is this side's turn to place a stone.
+    pub fn can_play(&self, game: &Game) -> bool {
+        self.is_connected() && game.next_to_play() == self.local_color
+    }
+
+    /// Drop the connection, as if it was lost. Mostly useful for testing
+    /// reconnects.
+    pub fn disconnect(&mut self) {
+        self.peer = None;
+    }
+
+    /// Send the last move in `game` to the other player. Call this after
+    /// playing a move on this side.
+    pub fn send_last_move(&mut self, game: &Game) {
+        let Some(event) = game.events.last() else {
+            return;
+        };
+        if let Some(peer) = &mut self.peer {
+            peer.send(&format!("move {:016x} {}", game.position_hash(), event));
+        }
+    }
+
+    /// Send a chat message to the other player.
+    pub fn send_chat(&mut self, text: &str) {
+        if let Some(peer) = &mut self.peer {
+            peer.send(&format!("chat {}", escape(text)));
+        }
+    }
+
+    /// Send the comment on the current position of `game` to the other
+    /// player. Call this after changing it on this side.
+    pub fn send_comment(&mut self, game: &Game) {
+        if let Some(peer) = &mut self.peer {
+            peer.send(&format!(
+                "comment {} {}",
+                game.move_number(),
+                escape(game.comment())
+            ));
+        }
+    }
+
+    /// Send and receive messages, applying the other player's moves to
+    /// `game`.
+    pub fn poll(&mut self, game: &mut Game) -> Vec<NetEvent> {
+        let mut net_events = vec![];
+        self.connect(game, &mut net_events);
+
+        let Some(peer) = &mut self.peer else {
+            return net_events;
+        };
+        let lines = match peer.pump() {
+            Ok(lines) => lines,
+            Err(e) => {
+                warn!("Connection lost: {}", e);
+                self.lost_connection(&mut net_events);
+                return net_events;
+            }
+        };
+
+        for line in lines {
+            debug!("Received {:?}", line);
+            if let Err(reason) = self.handle(&line, game, &mut net_events) {
+                warn!("Rejected {:?}: {}", line, reason);
+                net_events.push(NetEvent::Rejected(reason));
+                self.resync(game);
+            }
+        }
+
+        // Send any replies now rather than next poll
+        let closed = match &mut self.peer {
+            Some(peer) => peer.pump().is_err() || peer.closed,
+            None => false,
+        };
+        if closed {
+            self.lost_connection(&mut net_events);
+        }
+
+        net_events
+    }
+
+    /// Accept a new connection as the host, or reconnect to the host.
+    fn connect(&mut self, game: &Game, net_events: &mut Vec<NetEvent>) {
+        match &mut self.role {
+            Role::Host {
+                listener,
+                joiner_color,
+            } => match listener.accept() {
+                Ok((_, addr)) if self.peer.is_some() => {
+                    // Dropping the stream closes it
+                    info!("Refused {}: already playing", addr);
+                }
+                Ok((stream, addr)) => {
+                    info!("{} joined", addr);
+                    let joiner_color = *joiner_color;
+                    match Peer::new(stream) {
+                        Ok(mut peer) => {
+                            peer.send(&format!(
+                                "emi {} {} {}",
+                                PROTOCOL_VERSION,
+                                game.board_size(),
+                                piece_name(joiner_color)
+                            ));
+                            self.peer = Some(peer);
+                            self.send_game(game);
+                            net_events.push(NetEvent::Connected);
+                        }
+                        Err(e) => warn!("Could not set up connection: {}", e),
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => warn!("Could not accept connection: {}", e),
+            },
+            Role::Join { addr, last_attempt } => {
+                if self.peer.is_some() || last_attempt.elapsed() < RETRY_INTERVAL {
+                    return;
+                }
+                *last_attempt = Instant::now();
+                match TcpStream::connect_timeout(addr, CONNECT_TIMEOUT).and_then(Peer::new) {
+                    Ok(peer) => {
+                        info!("Reconnected to {}", addr);
+                        self.peer = Some(peer);
+                        self.handshake = Handshake::Hello;
+                    }
+                    Err(e) => debug!("Could not reconnect: {}", e),
+                }
+            }
+        }
+    }
+
+    fn lost_connection(&mut self, net_events: &mut Vec<NetEvent>) {
+        self.peer = None;
+        if let Role::Join { last_attempt, .. } = &mut self.role {
+            *last_attempt = Instant::now();
+        }
+        net_events.push(NetEvent::Disconnected);
+    }
+
+    /// As the host, send the whole game. As the joiner, ask for it.
+    fn resync(&mut self, game: &Game) {
+        if self.is_host() {
+            self.send_game(game);
+        } else if let Some(peer) = &mut self.peer {
+            peer.send("resync");
+        }
+    }
+
+    fn send_game(&mut self, game: &Game) {
+        let Some(peer) = &mut self.peer else {
+            return;
+        };
+        peer.send(&format!("history {}", game.events.iter().count()));
+        for event in game.events.iter() {
+            peer.send(&event.to_string());
+        }
+        peer.send(&format!("sync {:016x}", game.position_hash()));
+        for (node, comment) in game.comments() {
+            peer.send(&format!("comment {} {}", node, escape(comment)));
+        }
+    }
+
+    fn handle(
+        &mut self,
+        line: &str,
+        game: &mut Game,
+        net_events: &mut Vec<NetEvent>,
+    ) -> Result<(), String> {
+        // The joiner reads the host's game line by line
+        if !self.is_host() && self.handshake != Handshake::Done {
+            return self.handle_handshake(line, game, net_events);
+        }
+
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "move" => {
+                let (hash, event) = rest.split_once(' ').ok_or("Missing event")?;
+                let hash = parse_hash(hash)?;
+                let event: Event = event.parse().map_err(|_| "Bad event")?;
+                self.handle_move(hash, event, game, net_events)
+            }
+            "chat" => {
+                net_events.push(NetEvent::Chat(unescape(rest)));
+                Ok(())
+            }
+            "comment" => {
+                let (node, text) = rest.split_once(' ').unwrap_or((rest, ""));
+                let node = parse_field(Some(node))?;
+                if !game.set_comment_at(node, unescape(text)) {
+                    return Err(format!("No position {node} to comment on"));
+                }
+                net_events.push(NetEvent::Commented { node });
+                Ok(())
+            }
+            "resync" if self.is_host() => {
+                self.send_game(game);
+                Ok(())
+            }
+            // The host resends the game after a rejected move
+            "history" if !self.is_host() => {
+                self.handshake = Handshake::History {
+                    size: game.board_size(),
+                };
+                self.handle_handshake(line, game, net_events)
+            }
+            _ => Err(format!("Unknown message {command:?}")),
+        }
+    }
+
+    fn handle_move(
+        &mut self,
+        hash: u64,
+        event: Event,
+        game: &mut Game,
+        net_events: &mut Vec<NetEvent>,
+    ) -> Result<(), String> {
+        let move_ = event.to_move().ok_or("Edits are not allowed")?;
+        let peer_color = self.local_color.opposing();
+        let color = match move_ {
+            Move::Resign { color } => color,
+            _ => game.next_to_play(),
+        };
+        if color != peer_color {
+            return Err("Not the other player's move".to_string());
+        }
+        game.play_(move_)
+            .map_err(|_| format!("Illegal move {move_:?}"))?;
+        net_events.push(NetEvent::Moved(move_));
+
+        if game.events.last() != Some(&event) || game.position_hash() != hash {
+            return Err("Position does not match".to_string());
+        }
+        Ok(())
+    }
+
+    fn handle_handshake(
+        &mut self,
+        line: &str,
+        game: &mut Game,
+        net_events: &mut Vec<NetEvent>,
+    ) -> Result<(), String> {
+        let mut fields = line.split_whitespace();
+        self.handshake = match std::mem::replace(&mut self.handshake, Handshake::Done) {
+            Handshake::Hello => {
+                if fields.next() != Some("emi") {
+                    return Err("Expected greeting".to_string());
+                }
+                let version: u32 = parse_field(fields.next())?;
+                if version != PROTOCOL_VERSION {
+                    return Err(format!("Host uses protocol version {version}"));
+                }
+                let size = parse_field(fields.next())?;
+                if !(2..=52).contains(&size) {
+                    return Err(format!("Bad board size {size}"));
+                }
+                self.local_color = match fields.next() {
+                    Some("B") => Piece::Black,
+                    Some("W") => Piece::White,
+                    _ => return Err("Bad color".to_string()),
+                };
+                Handshake::History { size }
+            }
+            Handshake::History { size } => {
+                if fields.next() != Some("history") {
+                    return Err("Expected history".to_string());
+                }
+                let remaining = parse_field(fields.next())?;
+                Handshake::Events {
+                    size,
+                    remaining,
+                    events: vec![],
+                }
+                .finish_events()
+            }
+            Handshake::Events {
+                size,
+                remaining,
+                mut events,
+            } => {
+                events.push(line.parse().map_err(|_| "Bad event")?);
+                Handshake::Events {
+                    size,
+                    remaining: remaining - 1,
+                    events,
+                }
+                .finish_events()
+            }
+            Handshake::Sync { size, events } => {
+                if fields.next() != Some("sync") {
+                    return Err("Expected sync".to_string());
+                }
+                let hash = parse_hash(fields.next().unwrap_or(""))?;
+
+                let rebuilt = replay(size, &events)?;
+                if rebuilt.position_hash() != hash {
+                    return Err("History does not match its hash".to_string());
+                }
+                if game.board_size() == size && game.events == rebuilt.events {
+                    net_events.push(NetEvent::Connected);
+                } else {
+                    *game = rebuilt;
+                    net_events.push(NetEvent::Resynced);
+                }
+                Handshake::Done
+            }
+            Handshake::Done => unreachable!("Checked by caller"),
+        };
+        Ok(())
+    }
+}
+
+impl Handshake {
+    /// Move on to `Sync` once every event was read.
+    fn finish_events(self) -> Self {
+        match self {
+            Self::Events {
+                size,
+                remaining: 0,
+                events,
+            } => Self::Sync { size, events },
+            other => other,
+        }
+    }
+}
+
+/// Play `events` on a new board.
+fn replay(size: u32, events: &[Event]) -> Result<Game, String> {
+    let mut game = Game::new(size);
+    for event in events {
+        match event.to_move() {
+            Some(move_) => game
+                .play_(move_)
+                .map_err(|_| format!("Illegal move {move_:?} in history"))?,
+            None if edit_is_valid(&game, event) => game.push_event_unchecked(event.clone()),
+            None => return Err(format!("Bad edit {event:?} in history")),
+        }
+    }
+    Ok(game)
+}
+
+/// Whether every stone `event` edits is on the board, and any change of
+/// turn is from the player whose turn it is.
+fn edit_is_valid(game: &Game, event: &Event) -> bool {
+    match event {
+        Event::Edit { pos: [x, y], .. } => game.board().coord_is_valid(*x, *y),
+        Event::Turn { from, to } => {
+            *from == game.next_to_play() && *to == from.opposing() && *to != Piece::None
+        }
+        Event::Edits(edits) => edits.iter().all(|edit| edit_is_valid(game, edit)),
+        _ => false,
+    }
+}
+
+fn piece_name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::White => "W",
+        _ => "B",
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(field: Option<&str>) -> Result<T, String> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| "Bad number".to_string())
+}
+
+/// Keep text on one line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+fn parse_hash(hash: &str) -> Result<u64, String> {
+    u64::from_str_radix(hash, 16).map_err(|_| "Bad hash".to_string())
+}
@@ -0,0 +1,308 @@
+use crate::board::Board;
+use crate::{Game, Move, Piece};
+
+/// Estimated ownership of every point on the board.
+///
+/// Values range from `1.0` (certainly black's) to `-1.0` (certainly white's),
+/// with `0.0` meaning the point is contested or neutral.
+#[derive(Debug, Clone)]
+pub struct Ownership {
+    /// The board this estimate was made for.
+    board: Board,
+    /// Sum of the ownership values of every sample.
+    totals: Vec<f32>,
+    /// Number of samples in `totals`. Zero for an exact count.
+    playouts: u32,
+    rng: Rng,
+}
+
+impl Ownership {
+    pub(crate) fn new(board: Board) -> Self {
+        let side = board.board_size();
+        Self {
+            board,
+            totals: vec![0.0; (side * side) as usize],
+            playouts: 0,
+            rng: Rng::new(0x9e37_79b9_7f4a_7c15 ^ u64::from(side)),
+        }
+    }
+
+    /// Ownership of the point at `(x, y)`.
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        let total = self.totals[(y * self.board.board_size() + x) as usize];
+        total / self.playouts.max(1) as f32
+    }
+
+    /// The number of random games this estimate is based on, or zero if it
+    /// was counted directly from the board.
+    pub fn playouts(&self) -> u32 {
+        self.playouts
+    }
+
+    /// The number of points across one side of the board.
+    pub fn board_size(&self) -> u32 {
+        self.board.board_size()
+    }
+
+    /// Returns true if this estimate was made for `board`, so it does not
+    /// need to be recalculated.
+    pub fn is_for(&self, board: &Board) -> bool {
+        &self.board == board
+    }
+}
+
+impl Board {
+    /// Assign every point to a player by area: stones belong to their color,
+    /// and empty regions belong to a player if only their stones border it.
+    ///
+    /// Does not know about dead stones, so this is only accurate for
+    /// finished games.
+    pub fn area_owners(&self) -> Vec<Piece> {
+        let side = self.board_size();
+        let mut owners = vec![Piece::None; (side * side) as usize];
+        let mut seen = vec![false; (side * side) as usize];
+
+        for y in 0..side {
+            for x in 0..side {
+                let idx = (y * side + x) as usize;
+                if seen[idx] {
+                    continue;
+                }
+                let color = self.get(x, y);
+                if color != Piece::None {
+                    owners[idx] = color;
+                    seen[idx] = true;
+                    continue;
+                }
+
+                // Flood fill the empty region, noting who borders it.
+                let mut region = vec![];
+                let mut black = false;
+                let mut white = false;
+                let f = |nx, ny| match self.get(nx, ny) {
+                    Piece::None => {
+                        region.push((nx, ny));
+                        true
+                    }
+                    Piece::Black => {
+                        black = true;
+                        false
+                    }
+                    Piece::White => {
+                        white = true;
+                        false
+                    }
+                };
+                self.dfs((x, y), f);
+
+                let owner = match (black, white) {
+                    (true, false) => Piece::Black,
+                    (false, true) => Piece::White,
+                    _ => Piece::None,
+                };
+                for (rx, ry) in region {
+                    let ridx = (ry * side + rx) as usize;
+                    seen[ridx] = true;
+                    owners[ridx] = owner;
+                }
+            }
+        }
+
+        owners
+    }
+
+    /// Returns true if the point is empty and every neighbor is a stone
+    /// of `color`.
+    pub fn is_simple_eye(&self, x: u32, y: u32, color: Piece) -> bool {
+        self.get(x, y) == Piece::None
+            && self
+                .surround(x, y)
+                .into_iter()
+                .all(|(nx, ny)| self.get(nx, ny) == color)
+    }
+}
+
+impl Game {
+    /// Ownership as counted at the end of the game, without any dead stones
+    /// removed.
+    pub fn area_ownership(&self) -> Ownership {
+        let mut ownership = Ownership::new(self.board.clone());
+        for (total, owner) in ownership.totals.iter_mut().zip(self.board.area_owners()) {
+            *total = owner_value(owner);
+        }
+        ownership
+    }
+
+    /// Estimate ownership by averaging the final positions of `playouts`
+    /// random games played out from the current position.
+    ///
+    /// Finished games, including ones that ended by resignation or time,
+    /// are counted as they are, without any playouts.
+    pub fn estimate_ownership(&self, playouts: u32) -> Ownership {
+        if self.is_finished() || self.result.is_some() || playouts == 0 {
+            return self.area_ownership();
+        }
+        let mut ownership = Ownership::new(self.board.clone());
+        self.add_playouts(&mut ownership, playouts);
+        ownership
+    }
+
+    /// Add `playouts` more random games to an estimate made by
+    /// [`Game::estimate_ownership`], so it can be built up over several
+    /// frames.
+    ///
+    /// Does nothing if the estimate was made for another position, was
+    /// counted directly, or the game has a result.
+    pub fn refine_ownership(&self, ownership: &mut Ownership, playouts: u32) {
+        if !ownership.is_for(&self.board) || ownership.playouts == 0 || self.result.is_some() {
+            return;
+        }
+        self.add_playouts(ownership, playouts);
+    }
+
+    pub(crate) fn add_playouts(&self, ownership: &mut Ownership, playouts: u32) {
+        for _ in 0..playouts {
+            let mut game = self.clone();
+            game.random_playout(&mut ownership.rng);
+            for (total, owner) in ownership.totals.iter_mut().zip(game.board.area_owners()) {
+                *total += owner_value(owner);
+            }
+            ownership.playouts += 1;
+        }
+    }
+
+    /// Play random moves until both players pass. Players never fill in
+    /// their own real eyes, so groups with two eyes stay alive.
+    pub(crate) fn random_playout(&mut self, rng: &mut Rng) {
+        let side = self.board_size();
+        // Stop runaway games. Simple ko allows double kos to cycle forever.
+        let max_moves = side * side * 2;
+
+        let mut empties = Vec::with_capacity((side * side) as usize);
+        for _ in 0..max_moves {
+            let color = self.next_to_play();
+            empties.clear();
+            for y in 0..side {
+                for x in 0..side {
+                    if self.board.get(x, y) == Piece::None {
+                        empties.push([x, y]);
+                    }
+                }
+            }
+
+            let mut played = false;
+            while !empties.is_empty() {
+                let pos @ [x, y] = empties.swap_remove(rng.below(empties.len()));
+                if self.board.is_real_eye(x, y, color) {
+                    continue;
+                }
+                if self.play_(Move::Place { pos, color }).is_ok() {
+                    played = true;
+                    break;
+                }
+            }
+
+            if !played {
+                let _ = self.play_(Move::Pass);
+                if self.is_finished() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn owner_value(owner: Piece) -> f32 {
+    match owner {
+        Piece::None => 0.0,
+        Piece::Black => 1.0,
+        Piece::White => -1.0,
+    }
+}
+
+/// A small xorshift random number generator. Good enough for playouts, and
+/// avoids depending on an rng crate (which needs extra setup on wasm).
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on a state of zero
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// A random number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settled_corners_belong_to_their_owner() {
+        let mut game = Game::new(9);
+        game.board = Board::from_rows(
+            "
+            . X . X . . . . .
+            X X X X . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            ",
+        );
+        let ownership = game.estimate_ownership(32);
+        assert_eq!(ownership.playouts(), 32);
+        for [x, y] in [[0, 0], [1, 0], [2, 0], [1, 1]] {
+            assert_eq!(ownership.get(x, y), 1.0, "{x}, {y}");
+        }
+    }
+
+    #[test]
+    fn the_empty_board_is_about_even() {
+        let ownership = Game::new(9).estimate_ownership(64);
+        let total: f32 = (0..9)
+            .flat_map(|y| (0..9).map(move |x| [x, y]))
+            .map(|[x, y]| ownership.get(x, y))
+            .sum();
+        assert!((total / 81.0).abs() < 0.25, "{}", total / 81.0);
+    }
+
+    #[test]
+    fn resigned_games_are_not_played_out() {
+        let mut game = Game::new(9);
+        game.play_(Move::Place {
+            pos: [4, 4],
+            color: Piece::Black,
+        })
+        .unwrap();
+        let mut ownership = game.estimate_ownership(4);
+        assert_eq!(ownership.playouts(), 4);
+
+        game.play_(Move::Resign {
+            color: Piece::White,
+        })
+        .unwrap();
+        game.refine_ownership(&mut ownership, 4);
+        assert_eq!(ownership.playouts(), 4);
+        let ownership = game.estimate_ownership(4);
+        assert_eq!(ownership.playouts(), 0);
+        assert_eq!(ownership.get(4, 4), 1.0);
+        assert_eq!(ownership.get(0, 0), 1.0);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Piece;
+
+/// How a game was won.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WinReason {
+    /// Won by this many points.
+    Score(f32),
+    Resignation,
+    /// The other player ran out of time.
+    Time,
+    /// The other player forfeited, eg. by leaving or breaking a rule.
+    Forfeit,
+    /// The reason was not recorded.
+    Unknown,
+}
+
+/// How a game ended, as written in the SGF `RE` property.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    /// eg. `B+R` or `W+3.5`
+    Win { winner: Piece, by: WinReason },
+    /// A tie, eg. from an integer komi or triple ko.
+    Draw,
+    /// No result, eg. the game was suspended.
+    Void,
+}
+
+impl GameResult {
+    pub fn winner(&self) -> Option<Piece> {
+        match self {
+            Self::Win { winner, .. } => Some(*winner),
+            _ => None,
+        }
+    }
+
+    /// A sentence describing the result, eg. "Black wins by resignation".
+    pub fn describe(&self) -> String {
+        let Self::Win { winner, by } = self else {
+            return match self {
+                Self::Draw => "Draw".to_string(),
+                _ => "No result".to_string(),
+            };
+        };
+        let winner = match winner {
+            Piece::White => "White",
+            _ => "Black",
+        };
+        match by {
+            WinReason::Score(points) => format!("{winner} wins by {points} points"),
+            WinReason::Resignation => format!("{winner} wins by resignation"),
+            WinReason::Time => format!("{winner} wins on time"),
+            WinReason::Forfeit => format!("{winner} wins by forfeit"),
+            WinReason::Unknown => format!("{winner} wins"),
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Win { winner, by } => {
+                let winner = match winner {
+                    Piece::White => "W",
+                    _ => "B",
+                };
+                match by {
+                    WinReason::Score(points) => write!(f, "{winner}+{points}"),
+                    WinReason::Resignation => write!(f, "{winner}+R"),
+                    WinReason::Time => write!(f, "{winner}+T"),
+                    WinReason::Forfeit => write!(f, "{winner}+F"),
+                    WinReason::Unknown => write!(f, "{winner}+"),
+                }
+            }
+            Self::Draw => write!(f, "Draw"),
+            Self::Void => write!(f, "Void"),
+        }
+    }
+}
+
+/// A result that is not in any of the SGF forms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseResultError;
+
+impl fmt::Display for ParseResultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Not a game result")
+    }
+}
+
+impl std::error::Error for ParseResultError {}
+
+impl FromStr for GameResult {
+    type Err = ParseResultError;
+
+    /// Parse the SGF form, eg. `B+R`, `W+3.5`, `0` or `Draw`, `Void`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "0" | "Draw" | "D" => return Ok(Self::Draw),
+            "Void" | "?" => return Ok(Self::Void),
+            _ => {}
+        }
+        let (winner, by) = s.split_once('+').ok_or(ParseResultError)?;
+        let winner = match winner {
+            "B" => Piece::Black,
+            "W" => Piece::White,
+            _ => return Err(ParseResultError),
+        };
+        let by = match by {
+            "R" | "Resign" => WinReason::Resignation,
+            "T" | "Time" => WinReason::Time,
+            "F" | "Forfeit" => WinReason::Forfeit,
+            "" => WinReason::Unknown,
+            points => WinReason::Score(points.parse().map_err(|_| ParseResultError)?),
+        };
+        Ok(Self::Win { winner, by })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(winner: Piece, by: WinReason) -> GameResult {
+        GameResult::Win { winner, by }
+    }
+
+    #[test]
+    fn results_round_trip() {
+        for (text, result) in [
+            ("B+R", win(Piece::Black, WinReason::Resignation)),
+            ("W+3.5", win(Piece::White, WinReason::Score(3.5))),
+            ("B+T", win(Piece::Black, WinReason::Time)),
+            ("W+F", win(Piece::White, WinReason::Forfeit)),
+            ("B+", win(Piece::Black, WinReason::Unknown)),
+            ("Draw", GameResult::Draw),
+            ("Void", GameResult::Void),
+        ] {
+            assert_eq!(text.parse(), Ok(result), "{text}");
+            assert_eq!(result.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn other_forms_are_read() {
+        for (text, result) in [
+            ("0", GameResult::Draw),
+            ("D", GameResult::Draw),
+            ("?", GameResult::Void),
+            (" B+Resign ", win(Piece::Black, WinReason::Resignation)),
+            ("W+Time", win(Piece::White, WinReason::Time)),
+            ("B+Forfeit", win(Piece::Black, WinReason::Forfeit)),
+            ("W+12", win(Piece::White, WinReason::Score(12.0))),
+        ] {
+            assert_eq!(text.parse(), Ok(result), "{text}");
+        }
+    }
+
+    #[test]
+    fn other_text_is_not_a_result() {
+        for text in ["", "Jigo", "X+R", "B-R", "W+3.5 (time)", "B+R+"] {
+            assert_eq!(text.parse::<GameResult>(), Err(ParseResultError), "{text}");
+        }
+    }
+
+    #[test]
+    fn results_are_described() {
+        assert_eq!(
+            win(Piece::White, WinReason::Score(3.5)).describe(),
+            "White wins by 3.5 points"
+        );
+        assert_eq!(
+            win(Piece::Black, WinReason::Time).describe(),
+            "Black wins on time"
+        );
+        assert_eq!(GameResult::Void.describe(), "No result");
+    }
+}
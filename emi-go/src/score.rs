@@ -0,0 +1,259 @@
+//! Counting finished games: guessing which stones are dead, and scoring by
+//! area once the players agree.
+
+use crate::ownership::Ownership;
+use crate::{Game, GameResult, Piece, WinReason};
+
+/// Number of random games dead stones are guessed from.
+pub const DEAD_STONE_PLAYOUTS: u32 = 64;
+
+/// How far a group's points lean towards the opponent, on average, before
+/// it is guessed to be dead. Groups in seki hover around zero.
+const DEAD_OWNERSHIP: f32 = 0.5;
+
+/// A count by area: stones on the board plus the empty points only that
+/// player's stones surround.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    pub black: u32,
+    pub white: u32,
+    pub komi: f32,
+}
+
+impl Score {
+    /// The result of the game by this count.
+    pub fn result(&self) -> GameResult {
+        let margin = self.black as f32 - self.white as f32 - self.komi;
+        if margin > 0.0 {
+            GameResult::Win {
+                winner: Piece::Black,
+                by: WinReason::Score(margin),
+            }
+        } else if margin < 0.0 {
+            GameResult::Win {
+                winner: Piece::White,
+                by: WinReason::Score(-margin),
+            }
+        } else {
+            GameResult::Draw
+        }
+    }
+}
+
+/// A guess at the dead stones, built up from random games a few at a time
+/// so it can be spread over several frames. See
+/// [`Game::estimate_dead_stones`].
+#[derive(Debug, Clone)]
+pub struct DeadStoneEstimate {
+    ownership: Ownership,
+}
+
+impl DeadStoneEstimate {
+    /// The number of random games played so far.
+    pub fn playouts(&self) -> u32 {
+        self.ownership.playouts()
+    }
+}
+
+impl Game {
+    /// Guess which stones are dead at the end of the game, as a starting
+    /// point for the players to correct.
+    ///
+    /// Groups that are unconditionally alive by Benson's algorithm live, and
+    /// any stones in the eyes of those groups are dead. Everything else is
+    /// decided by where it ends up in `playouts` random games.
+    pub fn estimate_dead_stones(&self, playouts: u32) -> Vec<[u32; 2]> {
+        let mut estimate = self.start_dead_stone_estimate();
+        self.refine_dead_stones(&mut estimate, playouts);
+        self.dead_stones(&estimate)
+    }
+
+    /// A guess at the dead stones with no random games played yet, to build
+    /// up with [`Game::refine_dead_stones`].
+    pub fn start_dead_stone_estimate(&self) -> DeadStoneEstimate {
+        DeadStoneEstimate {
+            ownership: Ownership::new(self.board.clone()),
+        }
+    }
+
+    /// Play `playouts` more random games for `estimate`. Does nothing if it
+    /// was started for another position.
+    pub fn refine_dead_stones(&self, estimate: &mut DeadStoneEstimate, playouts: u32) {
+        if !estimate.ownership.is_for(&self.board) {
+            return;
+        }
+        // Playouts stop after two passes, but not after a result
+        let mut game = self.clone();
+        game.result = None;
+        game.add_playouts(&mut estimate.ownership, playouts);
+    }
+
+    /// The stones guessed dead from the random games in `estimate` so far,
+    /// as described for [`Game::estimate_dead_stones`].
+    pub fn dead_stones(&self, estimate: &DeadStoneEstimate) -> Vec<[u32; 2]> {
+        let board = &self.board;
+        let side = board.board_size();
+        let index = |x: u32, y: u32| (y * side + x) as usize;
+        let black = board.unconditional_life(Piece::Black);
+        let white = board.unconditional_life(Piece::White);
+        let ownership = &estimate.ownership;
+
+        let mut seen = vec![false; (side * side) as usize];
+        let mut dead = vec![];
+        for y in 0..side {
+            for x in 0..side {
+                if seen[index(x, y)] {
+                    continue;
+                }
+                let Some(stones) = board.capture_(x, y) else {
+                    continue;
+                };
+                for &[sx, sy] in &stones {
+                    seen[index(sx, sy)] = true;
+                }
+                let (own, theirs, sign) = match board.get(x, y) {
+                    Piece::Black => (&black, &white, 1.0),
+                    _ => (&white, &black, -1.0),
+                };
+
+                let is_dead = if own[index(x, y)] {
+                    false
+                } else if theirs[index(x, y)] {
+                    true
+                } else {
+                    let total: f32 = stones.iter().map(|&[sx, sy]| ownership.get(sx, sy)).sum();
+                    sign * total / (stones.len() as f32) < -DEAD_OWNERSHIP
+                };
+                if is_dead {
+                    dead.extend(stones);
+                }
+            }
+        }
+        dead
+    }
+
+    /// Mark the group at `pos` in `dead`, or alive again if it already is,
+    /// for the players to correct [`Game::estimate_dead_stones`]. Empty
+    /// points are left alone.
+    pub fn toggle_dead(&self, dead: &mut Vec<[u32; 2]>, pos: [u32; 2]) {
+        let [x, y] = pos;
+        let Some(stones) = self.board.capture_(x, y) else {
+            return;
+        };
+        if dead.contains(&pos) {
+            dead.retain(|p| !stones.contains(p));
+        } else {
+            dead.extend(stones);
+        }
+    }
+
+    /// Count the game by area with the `dead` stones taken off the board,
+    /// using the game's komi.
+    pub fn area_score(&self, dead: &[[u32; 2]]) -> Score {
+        let mut board = self.board.clone();
+        for &[x, y] in dead {
+            if board.coord_is_valid(x, y) {
+                board.remove(x, y);
+            }
+        }
+        let owners = board.area_owners();
+        let count = |color| owners.iter().filter(|&&owner| owner == color).count() as u32;
+        Score {
+            black: count(Piece::Black),
+            white: count(Piece::White),
+            komi: self.info.komi,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Board;
+
+    /// Black lives in the corner with a white stone left inside, and White
+    /// has the rest of the board.
+    const FINISHED: &str = "
+        . X . X O . .
+        X X X X O . .
+        . O . X O . .
+        X X X X O . .
+        O O O O O . .
+        . . . . . . .
+        . . . . . . .
+    ";
+
+    fn finished_game() -> Game {
+        let board = Board::from_rows(FINISHED);
+        let mut game = Game::new(board.board_size());
+        game.set_position(&board);
+        game
+    }
+
+    #[test]
+    fn invader_is_guessed_dead() {
+        let game = finished_game();
+        assert_eq!(game.estimate_dead_stones(DEAD_STONE_PLAYOUTS), vec![[1, 2]]);
+    }
+
+    #[test]
+    fn dead_stones_can_be_guessed_a_few_games_at_a_time() {
+        let game = finished_game();
+        let mut estimate = game.start_dead_stone_estimate();
+        for _ in 0..DEAD_STONE_PLAYOUTS / 4 {
+            game.refine_dead_stones(&mut estimate, 4);
+        }
+        assert_eq!(estimate.playouts(), DEAD_STONE_PLAYOUTS);
+        assert_eq!(game.dead_stones(&estimate), vec![[1, 2]]);
+
+        // Estimates only hold for the position they were started for
+        let other = Game::new(7);
+        other.refine_dead_stones(&mut estimate, 4);
+        assert_eq!(estimate.playouts(), DEAD_STONE_PLAYOUTS);
+    }
+
+    #[test]
+    fn area_score_takes_off_dead_stones() {
+        let game = finished_game();
+        let score = game.area_score(&[[1, 2]]);
+        assert_eq!((score.black, score.white), (16, 33));
+        // Left on the board, the stone spoils Black's territory
+        let score = game.area_score(&[]);
+        assert_eq!((score.black, score.white), (13, 34));
+    }
+
+    #[test]
+    fn groups_are_marked_dead_and_alive_again() {
+        let game = finished_game();
+        let mut dead = vec![[1, 2]];
+        game.toggle_dead(&mut dead, [1, 2]);
+        assert!(dead.is_empty());
+
+        game.toggle_dead(&mut dead, [0, 1]);
+        assert_eq!(dead.len(), 11);
+        assert!(dead.contains(&[3, 3]) && dead.contains(&[1, 0]));
+        game.toggle_dead(&mut dead, [3, 0]);
+        assert!(dead.is_empty());
+
+        game.toggle_dead(&mut dead, [6, 6]);
+        assert!(dead.is_empty());
+    }
+
+    #[test]
+    fn result_takes_komi_from_black() {
+        let score = Score {
+            black: 28,
+            white: 21,
+            komi: 6.5,
+        };
+        assert_eq!(
+            score.result(),
+            GameResult::Win {
+                winner: Piece::Black,
+                by: WinReason::Score(0.5)
+            }
+        );
+        let score = Score { komi: 7.0, ..score };
+        assert_eq!(score.result(), GameResult::Draw);
+    }
+}
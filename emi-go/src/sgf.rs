@@ -0,0 +1,752 @@
+//! Reading and writing games in the
+//! [Smart Game Format](https://www.red-bean.com/sgf/).
+//!
+//! [`parse`] reads the whole game tree, including variations, while
+//! [`Game::from_sgf`] only plays out the main line.
+
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+use crate::event::Event;
+use crate::{coord, Annotations, Game, Markup, Move, Piece, TimeControl};
+
+/// The most variations that can be nested inside each other.
+pub const MAX_DEPTH: usize = 1000;
+
+/// Information about a game as a whole, from the root node.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GameInfo {
+    /// SGF `PB`
+    pub black_name: String,
+    /// SGF `PW`
+    pub white_name: String,
+    /// When the game was played, usually `YYYY-MM-DD`. SGF `DT`
+    pub date: String,
+    /// The name of the game or tournament. SGF `GN`/`EV`
+    pub name: String,
+    /// Points given to White for moving second. SGF `KM`
+    pub komi: f32,
+}
+
+impl Game {
+    /// Write the game as SGF, with the moves played so far as the main
+    /// line.
+    pub fn to_sgf(&self) -> String {
+        let mut sgf = String::from("(;GM[1]FF[4]CA[UTF-8]");
+        push_prop(
+            &mut sgf,
+            "AP",
+            &[format!("emi:{}", env!("CARGO_PKG_VERSION"))],
+        );
+        push_prop(&mut sgf, "SZ", &[self.board_size().to_string()]);
+
+        let info = &self.info;
+        for (ident, value) in [
+            ("PB", &info.black_name),
+            ("PW", &info.white_name),
+            ("DT", &info.date),
+            ("GN", &info.name),
+        ] {
+            if !value.is_empty() {
+                push_prop(&mut sgf, ident, &[escape(value)]);
+            }
+        }
+
+        if info.komi != 0.0 {
+            push_prop(&mut sgf, "KM", &[info.komi.to_string()]);
+        }
+        if let Some(result) = self.result() {
+            push_prop(&mut sgf, "RE", &[escape(&result.to_string())]);
+        }
+
+        if let Some(clock) = self.clock() {
+            let control = clock.time_control();
+            push_prop(&mut sgf, "TM", &[seconds(control.main_time())]);
+            if let Some(overtime) = control.overtime_description() {
+                push_prop(&mut sgf, "OT", &[escape(&overtime)]);
+            }
+        }
+
+        push_annotations(&mut sgf, &self.annotations[0], Piece::None);
+        for (event, annotations) in self.events.iter().zip(&self.annotations[1..]) {
+            if let Event::Resign { .. } = event {
+                // Only recorded in `RE`
+                continue;
+            }
+            sgf.push_str("\n;");
+            let color = push_event(&mut sgf, event);
+            push_annotations(&mut sgf, annotations, color);
+        }
+
+        sgf.push_str(")\n");
+        sgf
+    }
+}
+
+impl Game {
+    /// Write just the current position as setup stones, with whose turn it
+    /// is, for sharing it without the moves that led to it.
+    pub fn position_to_sgf(&self) -> String {
+        let mut sgf = String::from("(;GM[1]FF[4]CA[UTF-8]");
+        push_prop(&mut sgf, "SZ", &[self.board_size().to_string()]);
+        let side = self.board_size();
+        for (ident, piece) in [("AB", Piece::Black), ("AW", Piece::White)] {
+            let points: Vec<_> = (0..side)
+                .flat_map(|y| (0..side).map(move |x| [x, y]))
+                .filter(|&[x, y]| self.board.get(x, y) == piece)
+                .map(|pos| point(&pos))
+                .collect();
+            push_prop(&mut sgf, ident, &points);
+        }
+        push_prop(
+            &mut sgf,
+            "PL",
+            &[color_ident(self.next_to_play()).to_string()],
+        );
+        sgf.push_str(")\n");
+        sgf
+    }
+}
+
+/// The SGF name of a point, eg. `pd`.
+fn point(&[x, y]: &[u32; 2]) -> String {
+    format!("{}{}", coord::sgf_label(x), coord::sgf_label(y))
+}
+
+/// Escape a text value so it can go between brackets.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Seconds with at most one decimal place.
+fn seconds(duration: Duration) -> String {
+    let tenths = (duration.as_secs_f64() * 10.0).round() / 10.0;
+    tenths.to_string()
+}
+
+/// Write a property. The values should already be escaped.
+fn push_prop(sgf: &mut String, ident: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+    sgf.push_str(ident);
+    for value in values {
+        let _ = write!(sgf, "[{value}]");
+    }
+}
+
+fn color_ident(color: Piece) -> &'static str {
+    match color {
+        Piece::White => "W",
+        _ => "B",
+    }
+}
+
+/// Write the properties for an event, returning the color of the player
+/// who moved, or `Piece::None` for edits.
+fn push_event(sgf: &mut String, event: &Event) -> Piece {
+    match event {
+        Event::Play { pos, color, .. } | Event::Capture { pos, color, .. } => {
+            push_prop(sgf, color_ident(*color), &[point(pos)]);
+            *color
+        }
+        Event::Pass { color, .. } => {
+            push_prop(sgf, color_ident(*color), &[String::new()]);
+            *color
+        }
+        Event::Edit { .. } | Event::Turn { .. } => push_edits(sgf, std::slice::from_ref(event)),
+        Event::Edits(edits) => push_edits(sgf, edits),
+        Event::Resign { .. } => Piece::None,
+    }
+}
+
+fn push_edits(sgf: &mut String, edits: &[Event]) -> Piece {
+    for (ident, piece) in [
+        ("AB", Piece::Black),
+        ("AW", Piece::White),
+        ("AE", Piece::None),
+    ] {
+        let points: Vec<_> = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Event::Edit { pos, to, .. } if *to == piece => Some(point(pos)),
+                _ => None,
+            })
+            .collect();
+        push_prop(sgf, ident, &points);
+    }
+    for edit in edits {
+        if let Event::Turn { to, .. } = edit {
+            push_prop(sgf, "PL", &[color_ident(*to).to_string()]);
+        }
+    }
+    Piece::None
+}
+
+/// Write the annotations of a node. `mover` is the color of the player who
+/// just moved, for their time left.
+fn push_annotations(sgf: &mut String, annotations: &Annotations, mover: Piece) {
+    for (ident, values) in markup_props(annotations) {
+        let values: Vec<_> = values.iter().map(|v| escape(v)).collect();
+        push_prop(sgf, ident, &values);
+    }
+
+    if mover != Piece::None {
+        let (time, overtime) = match mover {
+            Piece::White => ("WL", "OW"),
+            _ => ("BL", "OB"),
+        };
+        if let Some(left) = annotations.time_left {
+            push_prop(sgf, time, &[seconds(left)]);
+        }
+        if let Some(left) = annotations.overtime_left {
+            push_prop(sgf, overtime, &[left.to_string()]);
+        }
+    }
+}
+
+/// The comment and marks of a node as unescaped properties, leaving out any
+/// that are empty.
+fn markup_props(annotations: &Annotations) -> Vec<(&'static str, Vec<String>)> {
+    let mut props = vec![];
+    if !annotations.comment.is_empty() {
+        props.push(("C", vec![annotations.comment.clone()]));
+    }
+    for ident in MARKUP_IDENTS {
+        let values: Vec<_> = annotations
+            .marks
+            .iter()
+            .filter(|(_, mark)| mark.sgf_property() == ident)
+            .map(|(pos, mark)| match mark {
+                Markup::Label(text) => format!("{}:{}", point(pos), text),
+                _ => point(pos),
+            })
+            .collect();
+        if !values.is_empty() {
+            props.push((ident, values));
+        }
+    }
+    props
+}
+
+/// Properties for marks on the board.
+const MARKUP_IDENTS: [&str; 5] = ["CR", "MA", "TR", "SQ", "LB"];
+
+/// The color and point of a move in a node. Passes have no point.
+pub type SgfMove = (Piece, Option<[u32; 2]>);
+
+/// One node of a game tree, with its properties in the order they were
+/// read.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SgfNode {
+    pub properties: Vec<(String, Vec<String>)>,
+}
+
+impl SgfNode {
+    /// A node with just a move in it. Passes have no point.
+    pub fn from_move(color: Piece, pos: Option<[u32; 2]>) -> Self {
+        let value = pos.as_ref().map(point).unwrap_or_default();
+        Self {
+            properties: vec![(color_ident(color).to_string(), vec![value])],
+        }
+    }
+
+    /// Every value of a property, or none if it is missing.
+    pub fn values(&self, ident: &str) -> &[String] {
+        self.properties
+            .iter()
+            .find(|(i, _)| i == ident)
+            .map_or(&[], |(_, values)| values.as_slice())
+    }
+
+    /// The first value of a property.
+    pub fn get(&self, ident: &str) -> Option<&str> {
+        self.values(ident).first().map(String::as_str)
+    }
+
+    /// The move in this node, if any, as its color and point. Passes have
+    /// no point. Anything else that is not a point on the board is an
+    /// error, rather than being played as a pass.
+    pub fn move_(&self, board_size: u32) -> Result<Option<SgfMove>, SgfError> {
+        for (ident, color) in [("B", Piece::Black), ("W", Piece::White)] {
+            if let Some(value) = self.get(ident) {
+                return Ok(Some((color, parse_move(ident, value, board_size)?)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The board size in the `SZ` property of a root node, or 19 if it has
+    /// none. Only square boards are supported.
+    pub fn board_size(&self) -> Result<u32, SgfError> {
+        let Some(sz) = self.get("SZ") else {
+            return Ok(19);
+        };
+        let (w, h) = sz.split_once(':').unwrap_or((sz, sz));
+        match (w.trim().parse::<u32>(), h.trim().parse::<u32>()) {
+            (Ok(w), Ok(h)) if w == h && (2..=52).contains(&w) => Ok(w),
+            _ => Err(SgfError::BoardSize(sz.to_string())),
+        }
+    }
+
+    /// Replace the comment and marks with `annotations`'.
+    pub fn set_markup(&mut self, annotations: &Annotations) {
+        self.properties
+            .retain(|(ident, _)| ident != "C" && !MARKUP_IDENTS.contains(&ident.as_str()));
+        for (ident, values) in markup_props(annotations) {
+            self.properties.push((ident.to_string(), values));
+        }
+    }
+}
+
+/// A sequence of nodes, followed by the variations branching off the last
+/// one. The first variation is the main line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SgfTree {
+    pub nodes: Vec<SgfNode>,
+    pub variations: Vec<SgfTree>,
+}
+
+impl SgfTree {
+    /// The first node, holding information about the whole game.
+    pub fn root(&self) -> &SgfNode {
+        self.nodes.first().expect("Parsed trees are never empty")
+    }
+
+    /// The nodes of the main line, following the first variation each time.
+    pub fn main_line(&self) -> impl Iterator<Item = &SgfNode> {
+        let mut tree = Some(self);
+        std::iter::from_fn(move || {
+            let t = tree?;
+            tree = t.variations.first();
+            Some(&t.nodes)
+        })
+        .flatten()
+    }
+}
+
+/// Written back out as SGF, with variations in the same order.
+impl fmt::Display for SgfTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('(')?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            f.write_char(';')?;
+            for (ident, values) in &node.properties {
+                f.write_str(ident)?;
+                for value in values {
+                    write!(f, "[{}]", escape(value))?;
+                }
+            }
+        }
+        for variation in &self.variations {
+            f.write_char('\n')?;
+            variation.fmt(f)?;
+        }
+        f.write_char(')')
+    }
+}
+
+/// Why an SGF file could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SgfError {
+    /// The text is not valid SGF.
+    Syntax { line: usize, expected: &'static str },
+    /// Variations are nested more than [`MAX_DEPTH`] deep.
+    TooDeep { line: usize },
+    /// There is no game in the text.
+    NoGame,
+    /// The game is not go (`GM` is not `1`).
+    NotGo,
+    /// The board is not square, or too small or large.
+    BoardSize(String),
+    /// A property has a value that can not be used.
+    BadValue { ident: String, value: String },
+    /// A move in the main line could not be played.
+    IllegalMove { node: usize },
+}
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { line, expected } => write!(f, "line {line}: expected {expected}"),
+            Self::TooDeep { line } => write!(f, "line {line}: variations are nested too deep"),
+            Self::NoGame => write!(f, "no game found"),
+            Self::NotGo => write!(f, "not a go game"),
+            Self::BoardSize(size) => write!(f, "unsupported board size {size}"),
+            Self::BadValue { ident, value } => write!(f, "bad value `{value}` for {ident}"),
+            Self::IllegalMove { node } => write!(f, "illegal move at node {node}"),
+        }
+    }
+}
+
+impl std::error::Error for SgfError {}
+
+/// Parse every game tree in `text`. Anything before the first tree is
+/// skipped.
+pub fn parse(text: &str) -> Result<Vec<SgfTree>, SgfError> {
+    let bytes = text.as_bytes();
+    let line = |i: usize| bytes[..i].iter().filter(|&&b| b == b'\n').count() + 1;
+    let syntax = |i: usize, expected| SgfError::Syntax {
+        line: line(i),
+        expected,
+    };
+
+    let mut trees = vec![];
+    // Trees that have been opened but not closed yet
+    let mut open: Vec<SgfTree> = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => {
+                if open.len() >= MAX_DEPTH {
+                    return Err(SgfError::TooDeep { line: line(i) });
+                }
+                if open.last().is_some_and(|t| t.nodes.is_empty()) {
+                    return Err(syntax(i, "a node before a variation"));
+                }
+                open.push(SgfTree::default());
+                i += 1;
+            }
+            b')' if !open.is_empty() => {
+                let tree = open.pop().expect("Checked above");
+                if tree.nodes.is_empty() {
+                    return Err(syntax(i, "a node"));
+                }
+                match open.last_mut() {
+                    Some(parent) => parent.variations.push(tree),
+                    None => trees.push(tree),
+                }
+                i += 1;
+            }
+            b';' if !open.is_empty() => {
+                let tree = open.last_mut().expect("Checked above");
+                if !tree.variations.is_empty() {
+                    return Err(syntax(i, "a variation or `)`"));
+                }
+                tree.nodes.push(SgfNode::default());
+                i += 1;
+            }
+            b'A'..=b'Z' if !open.is_empty() => {
+                let Some(node) = open.last_mut().and_then(|t| t.nodes.last_mut()) else {
+                    return Err(syntax(i, "`;` before properties"));
+                };
+                // Old files may have lower case letters in identifiers, which
+                // are ignored
+                let mut ident = String::new();
+                while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                    if bytes[i].is_ascii_uppercase() {
+                        ident.push(bytes[i] as char);
+                    }
+                    i += 1;
+                }
+
+                let mut values = vec![];
+                loop {
+                    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    if bytes.get(i) != Some(&b'[') {
+                        break;
+                    }
+                    let (value, end) = read_value(bytes, i + 1).ok_or_else(|| syntax(i, "`]`"))?;
+                    values.push(value);
+                    i = end;
+                }
+                if values.is_empty() {
+                    return Err(syntax(i, "a property value"));
+                }
+                match node.properties.iter_mut().find(|(id, _)| *id == ident) {
+                    Some((_, existing)) => existing.extend(values),
+                    None => node.properties.push((ident, values)),
+                }
+            }
+            b if b.is_ascii_whitespace() || open.is_empty() => i += 1,
+            _ => return Err(syntax(i, "a node, property or variation")),
+        }
+    }
+
+    if !open.is_empty() {
+        return Err(syntax(bytes.len(), "`)`"));
+    }
+    Ok(trees)
+}
+
+/// Read a value up to its closing bracket, starting just after the opening
+/// one. Returns the value and the index after the closing bracket.
+fn read_value(bytes: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut value = vec![];
+    let mut i = start;
+    loop {
+        match *bytes.get(i)? {
+            b']' => break,
+            b'\\' => {
+                i += 1;
+                match *bytes.get(i)? {
+                    // Soft line breaks are removed
+                    b'\n' => {
+                        if bytes.get(i + 1) == Some(&b'\r') {
+                            i += 1;
+                        }
+                    }
+                    b'\r' => {
+                        if bytes.get(i + 1) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    b => value.push(b),
+                }
+            }
+            b => value.push(b),
+        }
+        i += 1;
+    }
+    Some((String::from_utf8_lossy(&value).into_owned(), i + 1))
+}
+
+fn bad_value(ident: &str, value: &str) -> SgfError {
+    SgfError::BadValue {
+        ident: ident.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// A point like `pd`.
+fn parse_point(value: &str) -> Option<[u32; 2]> {
+    let mut chars = value.chars();
+    let x = coord::from_sgf_label(chars.next()?)?;
+    let y = coord::from_sgf_label(chars.next()?)?;
+    chars.next().is_none().then_some([x, y])
+}
+
+/// The point of a move, or `None` for a pass. Passes are empty, or `tt` on
+/// boards up to 19x19, where it is off the board.
+fn parse_move(ident: &str, value: &str, board_size: u32) -> Result<Option<[u32; 2]>, SgfError> {
+    if value.is_empty() || (value == "tt" && board_size <= 19) {
+        return Ok(None);
+    }
+    match parse_point(value) {
+        Some([x, y]) if x < board_size && y < board_size => Ok(Some([x, y])),
+        _ => Err(bad_value(ident, value)),
+    }
+}
+
+/// A point, or a rectangle of points like `aa:cc`.
+fn parse_points(ident: &str, value: &str) -> Result<Vec<[u32; 2]>, SgfError> {
+    let bad = || bad_value(ident, value);
+    let (from, to) = value.split_once(':').unwrap_or((value, value));
+    let [x1, y1] = parse_point(from).ok_or_else(bad)?;
+    let [x2, y2] = parse_point(to).ok_or_else(bad)?;
+    let mut points = vec![];
+    for y in y1.min(y2)..=y1.max(y2) {
+        for x in x1.min(x2)..=x1.max(x2) {
+            points.push([x, y]);
+        }
+    }
+    Ok(points)
+}
+
+impl Game {
+    /// Read the main line of the first game in `text`.
+    pub fn from_sgf(text: &str) -> Result<Self, SgfError> {
+        let trees = parse(text)?;
+        let tree = trees.first().ok_or(SgfError::NoGame)?;
+        Self::from_sgf_tree(tree)
+    }
+
+    /// Play out the main line of a parsed game, ending with its result.
+    pub fn from_sgf_tree(tree: &SgfTree) -> Result<Self, SgfError> {
+        let mut game = Self::from_sgf_nodes(tree.main_line())?;
+        // Results other than resignation are not part of the history. Other
+        // programs write all sorts of things here, like `Jigo` or notes
+        // after the score, and those are left out rather than refusing the
+        // whole game.
+        if let Some(result) = tree.root().get("RE").and_then(|re| re.parse().ok()) {
+            game.set_result(Some(result));
+        }
+        Ok(game)
+    }
+
+    /// Play out a sequence of nodes, starting with the root.
+    ///
+    /// The result in `RE` is left out, since it only holds at the end of the
+    /// main line and nodes are often played out to look at a position part
+    /// way through.
+    ///
+    /// A move by the player who is not to play hands them the turn first,
+    /// as an event of its own, so taking back the move gives the turn back
+    /// as it was.
+    pub fn from_sgf_nodes<'a>(
+        nodes: impl IntoIterator<Item = &'a SgfNode>,
+    ) -> Result<Self, SgfError> {
+        let mut nodes = nodes.into_iter();
+        let root = nodes.next().ok_or(SgfError::NoGame)?;
+        if root.get("GM").is_some_and(|gm| gm.trim() != "1") {
+            return Err(SgfError::NotGo);
+        }
+        let size = root.board_size()?;
+
+        let control = match root.get("TM").map(str::trim) {
+            None | Some("") => TimeControl::None,
+            Some(tm) => {
+                let main = tm
+                    .parse()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(|| bad_value("TM", tm))?;
+                TimeControl::from_sgf(main, root.get("OT"))
+            }
+        };
+
+        let mut game = Game::with_time_control(size, control);
+        let text = |ident| root.get(ident).unwrap_or_default().to_string();
+        // Like `RE`, komi written some other way, like `6,5`, is left out
+        // rather than refusing the whole game
+        let komi = root
+            .get("KM")
+            .and_then(|km| km.trim().parse::<f32>().ok())
+            .filter(|km| km.is_finite())
+            .unwrap_or(0.0);
+        game.info = GameInfo {
+            black_name: text("PB"),
+            white_name: text("PW"),
+            date: text("DT"),
+            name: root
+                .get("GN")
+                .or(root.get("EV"))
+                .unwrap_or_default()
+                .to_string(),
+            komi,
+        };
+
+        for (n, node) in std::iter::once(root).chain(nodes).enumerate() {
+            game.play_sgf_node(node).map_err(|e| match e {
+                SgfError::IllegalMove { .. } => SgfError::IllegalMove { node: n },
+                e => e,
+            })?;
+        }
+        game.restore_clock();
+        Ok(game)
+    }
+
+    /// Apply the setup stones and move in a node, then its annotations.
+    fn play_sgf_node(&mut self, node: &SgfNode) -> Result<(), SgfError> {
+        let mut edits = vec![];
+        for (ident, to) in [
+            ("AB", Piece::Black),
+            ("AW", Piece::White),
+            ("AE", Piece::None),
+        ] {
+            for value in node.values(ident) {
+                for pos in parse_points(ident, value)? {
+                    let [x, y] = pos;
+                    if x >= self.board_size() || y >= self.board_size() {
+                        return Err(bad_value(ident, value));
+                    }
+                    let from = self.board.get(x, y);
+                    if from != to {
+                        edits.push(Event::Edit { pos, from, to });
+                    }
+                }
+            }
+        }
+
+        // Whose turn it is after setup stones, as part of the same edit. A
+        // move by the other player hands them the turn the same way.
+        let player = match node.get("PL") {
+            Some(player) => match player.trim() {
+                "B" | "b" => Some(Piece::Black),
+                "W" | "w" => Some(Piece::White),
+                _ => return Err(bad_value("PL", player)),
+            },
+            None => None,
+        };
+        let move_ = node.move_(self.board_size())?;
+        if let Some(color) = move_.map(|(color, _)| color).or(player) {
+            if color != self.next_to_play() {
+                edits.push(Event::Turn {
+                    from: self.next_to_play(),
+                    to: color,
+                });
+            }
+        }
+        match edits.len() {
+            0 => {}
+            1 => self.push_event_unchecked(edits.remove(0)),
+            _ => self.push_event_unchecked(Event::Edits(edits)),
+        }
+
+        if let Some((color, pos)) = move_ {
+            let move_ = match pos {
+                Some(pos) => Move::Place { pos, color },
+                None => Move::Pass,
+            };
+            self.play_(move_)
+                .map_err(|_| SgfError::IllegalMove { node: 0 })?;
+        }
+
+        self.read_annotations(node)
+    }
+
+    /// Read comments, marks and time left into the current position.
+    fn read_annotations(&mut self, node: &SgfNode) -> Result<(), SgfError> {
+        let size = self.board_size();
+        let annotations = self
+            .annotations
+            .last_mut()
+            .expect("There is always a root node");
+
+        if let Some(comment) = node.get("C") {
+            annotations.comment = comment.to_string();
+        }
+
+        for (ident, markup) in [
+            ("CR", Markup::Circle),
+            ("MA", Markup::Cross),
+            ("TR", Markup::Triangle),
+            ("SQ", Markup::Square),
+        ] {
+            for value in node.values(ident) {
+                for pos in parse_points(ident, value)? {
+                    annotations.marks.retain(|(p, _)| *p != pos);
+                    annotations.marks.push((pos, markup.clone()));
+                }
+            }
+        }
+        for value in node.values("LB") {
+            let (point, text) = value
+                .split_once(':')
+                .ok_or_else(|| bad_value("LB", value))?;
+            let pos = parse_point(point).ok_or_else(|| bad_value("LB", value))?;
+            annotations.marks.retain(|(p, _)| *p != pos);
+            annotations
+                .marks
+                .push((pos, Markup::Label(text.to_string())));
+        }
+        annotations
+            .marks
+            .retain(|([x, y], _)| *x < size && *y < size);
+
+        for (time, overtime) in [("BL", "OB"), ("WL", "OW")] {
+            if let Some(left) = node.get(time) {
+                let secs: f64 = left.trim().parse().map_err(|_| bad_value(time, left))?;
+                annotations.time_left =
+                    Some(Duration::try_from_secs_f64(secs).map_err(|_| bad_value(time, left))?);
+            }
+            if let Some(left) = node.get(overtime) {
+                annotations.overtime_left =
+                    Some(left.trim().parse().map_err(|_| bad_value(overtime, left))?);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+//! Reading out whether a group lives or dies.
+//!
+//! The search is limited to a region of the board, usually the empty points
+//! around a group in a corner or on the side. The attacker moves first or
+//! second as asked, and only ever plays inside the region. The defender may
+//! also pass, as taking a move away from a living group is often the right
+//! answer.
+//!
+//! The attacker wins by capturing the target group. The defender wins by
+//! making two eyes that only touch the group, or if the attacker runs out
+//! of moves. The attacker never passes, since the defender could pass back
+//! and the group would still be standing.
+//!
+//! Positions are looked up in a transposition table by their Zobrist hash.
+//! Simple ko can repeat positions, eg. in a triple ko, so a line that comes
+//! back to a position it has already been through counts as a win for the
+//! defender. Results that rely on this are not kept in the table, since
+//! they depend on how the position was reached.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Board, Game, Move, Piece};
+
+/// The most positions to search before giving up.
+pub const MAX_NODES: u64 = 1_000_000;
+
+/// The outcome of reading a group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// Whether the group lives with correct play.
+    pub lives: bool,
+    /// The best moves for both sides, starting with the player to move. Ends
+    /// when the group is captured or has two eyes.
+    pub principal_variation: Vec<Move>,
+    /// How many positions were searched.
+    pub nodes: u64,
+}
+
+/// Why a group could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveError {
+    /// There is no stone at the target point.
+    NoTarget,
+    /// The player to move is `Piece::None`.
+    NoPlayer,
+    /// The region is too large to read out in [`MAX_NODES`] positions.
+    TooManyNodes,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTarget => write!(f, "There is no group to read"),
+            Self::NoPlayer => write!(f, "Nobody is to play"),
+            Self::TooManyNodes => write!(f, "Too many positions to read"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// Read whether the group at `target` lives, with `to_play` moving first and
+/// both players only playing on the points in `region`.
+pub fn solve(
+    board: &Board,
+    target: [u32; 2],
+    region: &[[u32; 2]],
+    to_play: Piece,
+) -> Result<Solution, SolveError> {
+    let [x, y] = target;
+    if !board.coord_is_valid(x, y) || board.get(x, y) == Piece::None {
+        return Err(SolveError::NoTarget);
+    }
+    if to_play == Piece::None {
+        return Err(SolveError::NoPlayer);
+    }
+
+    let mut game = Game::new(board.board_size());
+    game.set_position(board);
+    if game.next_to_play() != to_play {
+        game.next_turn();
+    }
+    let mut search = Search {
+        game,
+        target,
+        defender: board.get(x, y),
+        region: region
+            .iter()
+            .copied()
+            .filter(|&[x, y]| board.coord_is_valid(x, y))
+            .collect(),
+        table: HashMap::new(),
+        path: vec![],
+        nodes: 0,
+    };
+    let (attacker_wins, _) = search.search()?;
+    Ok(Solution {
+        lives: !attacker_wins,
+        principal_variation: search.principal_variation(),
+        nodes: search.nodes,
+    })
+}
+
+/// What is known about a position that has been searched.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    attacker_wins: bool,
+    /// The move that wins, or the first one tried if none do.
+    best: Option<Move>,
+    /// Whether the result holds however the position is reached.
+    exact: bool,
+}
+
+#[derive(Debug)]
+struct Search {
+    game: Game,
+    target: [u32; 2],
+    defender: Piece,
+    region: Vec<[u32; 2]>,
+    table: HashMap<u64, Entry>,
+    /// Positions on the line being searched, to catch repeats.
+    path: Vec<u64>,
+    nodes: u64,
+}
+
+impl Search {
+    /// Returns whether the attacker wins from the current position, and
+    /// whether that depends on the line taken to reach it.
+    fn search(&mut self) -> Result<(bool, bool), SolveError> {
+        let [x, y] = self.target;
+        if self.game.board().get(x, y) != self.defender {
+            return Ok((true, false));
+        }
+        if self.has_two_eyes() {
+            return Ok((false, false));
+        }
+        let hash = self.game.position_hash();
+        if let Some(entry) = self.table.get(&hash).filter(|e| e.exact) {
+            return Ok((entry.attacker_wins, false));
+        }
+        if self.path.contains(&hash) {
+            return Ok((false, true));
+        }
+        self.nodes += 1;
+        if self.nodes > MAX_NODES {
+            return Err(SolveError::TooManyNodes);
+        }
+
+        let attacking = self.game.next_to_play() != self.defender;
+        let mut first = None;
+        let mut winner = None;
+        let mut depends_on_path = false;
+        self.path.push(hash);
+        for move_ in self.moves() {
+            if self.game.play_(move_).is_err() {
+                continue;
+            }
+            let result = self.search();
+            self.game.undo();
+            let (attacker_wins, from_path) = result?;
+            first.get_or_insert(move_);
+            depends_on_path |= from_path;
+            if attacker_wins == attacking {
+                winner = Some(move_);
+                break;
+            }
+        }
+        self.path.pop();
+
+        // Whoever is to move loses if none of their moves win
+        let attacker_wins = attacking == winner.is_some();
+        self.table.insert(
+            hash,
+            Entry {
+                attacker_wins,
+                best: winner.or(first),
+                exact: !depends_on_path,
+            },
+        );
+        Ok((attacker_wins, depends_on_path))
+    }
+
+    /// The moves to try for the player to move, most promising first: the
+    /// target's liberties, the rest of the region, then passing for the
+    /// defender.
+    fn moves(&self) -> Vec<Move> {
+        let color = self.game.next_to_play();
+        let board = self.game.board();
+        let liberties = self.liberties();
+        let empty = self
+            .region
+            .iter()
+            .copied()
+            .filter(|&[x, y]| board.get(x, y) == Piece::None);
+        let (mut moves, rest): (Vec<_>, Vec<_>) = empty.partition(|pos| liberties.contains(pos));
+        moves.extend(rest);
+
+        let mut moves: Vec<_> = moves
+            .into_iter()
+            .map(|pos| Move::Place { pos, color })
+            .collect();
+        if color == self.defender {
+            moves.push(Move::Pass);
+        }
+        moves
+    }
+
+    /// The stones of the target group.
+    fn group(&self) -> Vec<[u32; 2]> {
+        let [x, y] = self.target;
+        self.game.board().capture_(x, y).unwrap_or_default()
+    }
+
+    fn liberties(&self) -> Vec<[u32; 2]> {
+        let [x, y] = self.target;
+        self.game.board().liberty_points(x, y).unwrap_or_default()
+    }
+
+    /// Whether the target group has two empty points next to it that only
+    /// touch the group. The attacker can not play on either, so the group
+    /// can never be captured.
+    fn has_two_eyes(&self) -> bool {
+        let board = self.game.board();
+        let group = self.group();
+        let eyes = self.liberties().into_iter().filter(|&[x, y]| {
+            board
+                .surround(x, y)
+                .into_iter()
+                .all(|(nx, ny)| group.contains(&[nx, ny]))
+        });
+        eyes.take(2).count() == 2
+    }
+
+    /// Follow the best moves from the start, as far as they were searched.
+    fn principal_variation(&self) -> Vec<Move> {
+        let mut game = self.game.clone();
+        let mut seen = vec![];
+        let mut moves = vec![];
+        loop {
+            let hash = game.position_hash();
+            if seen.contains(&hash) {
+                break;
+            }
+            seen.push(hash);
+            let Some(move_) = self.table.get(&hash).and_then(|e| e.best) else {
+                break;
+            };
+            if game.play_(move_).is_err() {
+                break;
+            }
+            moves.push(move_);
+        }
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White's eye space along the bottom edge, and every empty point in it.
+    const STRAIGHT_THREE: &str = "
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+        X X X X X . .
+        O O O O X . .
+        . . . O X . .
+    ";
+
+    const STRAIGHT_FOUR: &str = "
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+        X X X X X X .
+        O O O O O X .
+        . . . . O X .
+    ";
+
+    fn empty_points(board: &Board, points: &[[u32; 2]]) -> Vec<[u32; 2]> {
+        points
+            .iter()
+            .copied()
+            .filter(|&[x, y]| board.get(x, y) == Piece::None)
+            .collect()
+    }
+
+    fn bottom_row(board: &Board) -> Vec<[u32; 2]> {
+        let y = board.board_size() - 1;
+        let row: Vec<_> = (0..board.board_size()).map(|x| [x, y]).collect();
+        empty_points(board, &row)
+    }
+
+    #[test]
+    fn straight_three_lives_if_the_defender_plays_first() {
+        let board = Board::from_rows(STRAIGHT_THREE);
+        let region = &bottom_row(&board)[..3];
+        let solution = solve(&board, [0, 5], region, Piece::White).unwrap();
+        assert!(solution.lives);
+        assert_eq!(
+            solution.principal_variation,
+            vec![Move::Place {
+                pos: [1, 6],
+                color: Piece::White
+            }]
+        );
+    }
+
+    #[test]
+    fn straight_three_dies_if_the_attacker_plays_first() {
+        let board = Board::from_rows(STRAIGHT_THREE);
+        let region = &bottom_row(&board)[..3];
+        let solution = solve(&board, [0, 5], region, Piece::Black).unwrap();
+        assert!(!solution.lives);
+        assert_eq!(
+            solution.principal_variation.first(),
+            Some(&Move::Place {
+                pos: [1, 6],
+                color: Piece::Black
+            })
+        );
+    }
+
+    #[test]
+    fn straight_four_lives() {
+        let board = Board::from_rows(STRAIGHT_FOUR);
+        let region = &bottom_row(&board)[..4];
+        for to_play in [Piece::Black, Piece::White] {
+            let solution = solve(&board, [0, 5], region, to_play).unwrap();
+            assert!(solution.lives, "{to_play:?} to play");
+        }
+    }
+
+    #[test]
+    fn seki_lives() {
+        // Neither group has two eyes, and whoever fills the shared liberty
+        // is captured
+        let board = Board::from_rows(
+            "
+            . O . X . X
+            O O O X X X
+            X X X O O O
+            . . . . . .
+            . . . . . .
+            . . . . . .
+            ",
+        );
+        let region = [[0, 0], [2, 0], [4, 0]];
+        let solution = solve(&board, [1, 0], &region, Piece::Black).unwrap();
+        assert!(solution.lives);
+        let solution = solve(&board, [1, 0], &region, Piece::White).unwrap();
+        assert!(solution.lives);
+        assert_eq!(solution.principal_variation.first(), Some(&Move::Pass));
+    }
+
+    #[test]
+    fn double_ko_lives() {
+        // White and the black group inside it share a liberty, and when
+        // black takes one ko white takes the other, so black never holds both
+        let board = Board::from_rows(
+            "
+            . X O O O O O . O
+            X O O O . O O O X
+            X X X X X X X X X
+            O O O O O O O O O
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            ",
+        );
+        let region = [[0, 0], [1, 0], [4, 1], [7, 0], [8, 0]];
+        for to_play in [Piece::Black, Piece::White] {
+            let solution = solve(&board, [2, 0], &region, to_play).unwrap();
+            assert!(solution.lives, "{to_play:?} to play");
+            assert!(solution.nodes < MAX_NODES);
+        }
+    }
+
+    #[test]
+    fn triple_ko_repeats() {
+        // Black can take a ko whenever it is their turn, and white can
+        // always take another back, so play goes round in circles
+        let board = Board::from_rows(
+            "
+            O . O O O O O . O
+            X O O O X O O O X
+            X X X X . X X X X
+            X X X X X X X X X
+            O O O O O O O O O
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            ",
+        );
+        let region = [[0, 0], [1, 0], [4, 1], [4, 2], [7, 0], [8, 0]];
+        let solution = solve(&board, [2, 0], &region, Piece::Black).unwrap();
+        assert!(solution.lives);
+    }
+
+    #[test]
+    fn principal_variation_ends_in_capture_or_two_eyes() {
+        for (text, eye_space) in [(STRAIGHT_THREE, 3), (STRAIGHT_FOUR, 4)] {
+            let board = Board::from_rows(text);
+            let region = &bottom_row(&board)[..eye_space];
+            for to_play in [Piece::Black, Piece::White] {
+                let solution = solve(&board, [0, 5], region, to_play).unwrap();
+                let mut game = Game::new(board.board_size());
+                game.set_position(&board);
+                if game.next_to_play() != to_play {
+                    game.next_turn();
+                }
+                for &move_ in &solution.principal_variation {
+                    game.play_(move_).unwrap();
+                }
+                let end = Search {
+                    game,
+                    target: [0, 5],
+                    defender: Piece::White,
+                    region: region.to_vec(),
+                    table: HashMap::new(),
+                    path: vec![],
+                    nodes: 0,
+                };
+                let captured = end.game.board().get(0, 5) == Piece::None;
+                let context = format!("{eye_space} points, {to_play:?} to play");
+                assert_eq!(solution.lives, end.has_two_eyes(), "{context}");
+                assert_eq!(solution.lives, !captured, "{context}");
+            }
+        }
+    }
+}
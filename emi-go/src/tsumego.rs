@@ -0,0 +1,370 @@
+//! Life and death problems, read from SGF where the variations are the
+//! answers.
+//!
+//! The root node sets up the position, and the player to solve it moves
+//! first, as given by `PL` or the first move in the tree. Each variation is
+//! a line of play: the solver's move, then the opponent's reply, and so on.
+//! How a line is judged follows the common conventions of problem
+//! collections:
+//!
+//! - A node is right if it has `TE`, or its comment starts with `Correct`
+//!   or has `RIGHT` in it.
+//! - A node is wrong if it has `BM`, or its comment starts with `Wrong` or
+//!   `Incorrect`.
+//! - Otherwise a line that ends on the solver's move is right, and one that
+//!   ends on the opponent's reply is wrong, as the reply is the refutation.
+//!
+//! Moves that are not in the tree at all are wrong.
+
+use std::fmt;
+
+use crate::sgf::{SgfNode, SgfTree};
+use crate::{board, Game, GoError, Move, Piece, SgfError};
+
+/// How an attempt at a problem is going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Keep going.
+    Unsolved,
+    Solved,
+    Failed,
+}
+
+/// Why a move could not be tried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TsumegoError {
+    /// The attempt is over, either way.
+    Finished,
+    /// The move is against the rules.
+    Illegal(GoError),
+    /// The reply in the problem's tree can not be played, so the move is
+    /// taken back.
+    BadProblem,
+}
+
+impl fmt::Display for TsumegoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Finished => write!(f, "The problem is finished"),
+            Self::Illegal(e) => e.fmt(f),
+            Self::BadProblem => write!(f, "The problem's reply to that move is illegal"),
+        }
+    }
+}
+
+impl std::error::Error for TsumegoError {}
+
+/// Where an attempt is in the problem's tree: a stretch of nodes, found by
+/// taking the variations in `path`, and a node in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Cursor {
+    path: Vec<usize>,
+    node: usize,
+}
+
+/// One attempt at a problem.
+#[derive(Debug, Clone)]
+pub struct Tsumego {
+    tree: SgfTree,
+    game: Game,
+    /// The color of the one solving the problem.
+    player: Piece,
+    cursor: Cursor,
+    status: Status,
+}
+
+/// Read every problem in a collection.
+pub fn collection(text: &str) -> Result<Vec<SgfTree>, SgfError> {
+    let problems = crate::sgf::parse(text)?;
+    if problems.is_empty() {
+        return Err(SgfError::NoGame);
+    }
+    Ok(problems)
+}
+
+impl Tsumego {
+    /// Set up the problem, ready for the first move.
+    pub fn new(tree: SgfTree) -> Result<Self, SgfError> {
+        let game = Game::from_sgf_nodes([tree.root()])?;
+        let first_move = next_nodes(&tree, &Cursor::default())
+            .into_iter()
+            .find_map(|(_, node)| node.move_(game.board_size()).ok().flatten());
+        let player = match (tree.root().get("PL"), first_move) {
+            (Some(_), _) => game.next_to_play(),
+            (None, Some((color, _))) => color,
+            (None, None) => Piece::Black,
+        };
+
+        let mut tsumego = Self {
+            tree,
+            game,
+            player,
+            cursor: Cursor::default(),
+            status: Status::Unsolved,
+        };
+        // Hand the solver the turn as part of the history, like `PL` would
+        tsumego.game.edit_to_play([], player);
+        Ok(tsumego)
+    }
+
+    /// Start again from the beginning.
+    pub fn restart(&mut self) -> Result<(), SgfError> {
+        *self = Self::new(self.tree.clone())?;
+        Ok(())
+    }
+
+    /// The board as it is now.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The color of the one solving the problem.
+    pub fn player(&self) -> Piece {
+        self.player
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// The same for every attempt at the same problem, to remember which
+    /// have been solved.
+    pub fn id(&self) -> u64 {
+        let start = Game::from_sgf_nodes([self.tree.root()]).map_or(0, |g| g.position_hash());
+        start ^ board::splitmix64(u64::from(self.game.board_size()))
+    }
+
+    /// The problem's name, or the start of its first comment.
+    pub fn name(&self) -> Option<&str> {
+        let root = self.tree.root();
+        root.get("GN")
+            .or_else(|| root.get("C").and_then(|c| c.lines().next()))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// The comment on the last node reached.
+    pub fn comment(&self) -> &str {
+        self.current().get("C").unwrap_or_default()
+    }
+
+    fn current(&self) -> &SgfNode {
+        let stretch = stretch(&self.tree, &self.cursor.path);
+        &stretch.nodes[self.cursor.node]
+    }
+
+    /// Play the solver's move, and the opponent's reply from the tree if
+    /// there is one.
+    pub fn play(&mut self, pos: [u32; 2]) -> Result<Status, TsumegoError> {
+        if self.status != Status::Unsolved {
+            return Err(TsumegoError::Finished);
+        }
+        let color = self.player;
+        let start = self.cursor.clone();
+        self.game
+            .play_(Move::Place { pos, color })
+            .map_err(TsumegoError::Illegal)?;
+
+        let size = self.game.board_size();
+        let Some(cursor) = self.find_next(|node| node.move_(size) == Ok(Some((color, Some(pos)))))
+        else {
+            self.status = Status::Failed;
+            return Ok(self.status);
+        };
+        self.cursor = cursor;
+        if let Some(status) = judge(self.current()) {
+            self.status = status;
+            return Ok(status);
+        }
+
+        // The opponent answers with the first reply in the tree
+        let Some(cursor) = self.find_next(|node| matches!(node.move_(size), Ok(Some(_)))) else {
+            self.status = Status::Solved;
+            return Ok(self.status);
+        };
+        let reply = match stretch(&self.tree, &cursor.path).nodes[cursor.node].move_(size) {
+            Ok(Some((_, Some(pos)))) => Move::Place {
+                pos,
+                color: color.opposing(),
+            },
+            _ => Move::Pass,
+        };
+        // A broken tree says nothing about the move, so take it back
+        if self.game.play_(reply).is_err() {
+            self.game.undo();
+            self.cursor = start;
+            return Err(TsumegoError::BadProblem);
+        }
+        self.cursor = cursor;
+
+        self.status = judge(self.current()).unwrap_or_else(|| {
+            if self.find_next(|_| true).is_some() {
+                Status::Unsolved
+            } else {
+                Status::Failed
+            }
+        });
+        Ok(self.status)
+    }
+
+    /// The first node after the current one that matches `f`.
+    fn find_next(&self, f: impl Fn(&SgfNode) -> bool) -> Option<Cursor> {
+        next_nodes(&self.tree, &self.cursor)
+            .into_iter()
+            .find(|(_, node)| f(node))
+            .map(|(cursor, _)| cursor)
+    }
+}
+
+/// Whether a node says the line is right or wrong.
+fn judge(node: &SgfNode) -> Option<Status> {
+    let comment = node.get("C").unwrap_or_default().trim();
+    let lower = comment.to_lowercase();
+    if node.get("TE").is_some() || lower.starts_with("correct") || comment.contains("RIGHT") {
+        Some(Status::Solved)
+    } else if node.get("BM").is_some()
+        || lower.starts_with("wrong")
+        || lower.starts_with("incorrect")
+    {
+        Some(Status::Failed)
+    } else {
+        None
+    }
+}
+
+/// The stretch of `tree` reached by taking the variations in `path`.
+fn stretch<'a>(mut tree: &'a SgfTree, path: &[usize]) -> &'a SgfTree {
+    for &choice in path {
+        tree = &tree.variations[choice];
+    }
+    tree
+}
+
+/// Every node that can come after `cursor`, in order.
+fn next_nodes<'a>(tree: &'a SgfTree, cursor: &Cursor) -> Vec<(Cursor, &'a SgfNode)> {
+    let here = stretch(tree, &cursor.path);
+    if cursor.node + 1 < here.nodes.len() {
+        let next = Cursor {
+            path: cursor.path.clone(),
+            node: cursor.node + 1,
+        };
+        return vec![(next, &here.nodes[cursor.node + 1])];
+    }
+    here.variations
+        .iter()
+        .enumerate()
+        .map(|(choice, variation)| {
+            let mut path = cursor.path.clone();
+            path.push(choice);
+            (Cursor { path, node: 0 }, &variation.nodes[0])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black to play, with a right and a wrong line for each way of
+    /// marking them.
+    const PROBLEM: &str = "(;SZ[9]AB[cc]AW[cd]
+        (;B[ee]TE[1])
+        (;B[ff]C[Correct!])
+        (;B[gg]C[That is RIGHT])
+        (;B[dd];W[de];B[ed])
+        (;B[ab]BM[1])
+        (;B[ac]C[Wrong, white lives])
+        (;B[ad]C[Incorrect])
+        (;B[ae];W[af]C[White gets out]))";
+
+    fn attempt(text: &str) -> Tsumego {
+        let tree = collection(text).unwrap().remove(0);
+        Tsumego::new(tree).unwrap()
+    }
+
+    fn point(name: &str) -> [u32; 2] {
+        let mut chars = name.chars();
+        let x = crate::coord::from_sgf_label(chars.next().unwrap()).unwrap();
+        let y = crate::coord::from_sgf_label(chars.next().unwrap()).unwrap();
+        [x, y]
+    }
+
+    #[test]
+    fn right_lines_are_solved() {
+        for moves in [&["ee"][..], &["ff"], &["gg"], &["dd", "ed"]] {
+            let mut tsumego = attempt(PROBLEM);
+            let statuses: Vec<_> = moves
+                .iter()
+                .map(|name| tsumego.play(point(name)).unwrap())
+                .collect();
+            assert_eq!(statuses.last(), Some(&Status::Solved), "{moves:?}");
+            assert!(statuses[..statuses.len() - 1]
+                .iter()
+                .all(|&s| s == Status::Unsolved));
+        }
+    }
+
+    #[test]
+    fn wrong_lines_fail() {
+        for name in ["ab", "ac", "ad", "ae", "hh"] {
+            let mut tsumego = attempt(PROBLEM);
+            assert_eq!(tsumego.play(point(name)), Ok(Status::Failed), "{name}");
+        }
+    }
+
+    #[test]
+    fn the_opponent_replies_from_the_tree() {
+        let mut tsumego = attempt(PROBLEM);
+        assert_eq!(tsumego.play(point("ae")), Ok(Status::Failed));
+        assert_eq!(tsumego.game().board().get(0, 5), Piece::White);
+        assert_eq!(tsumego.comment(), "White gets out");
+
+        let mut tsumego = attempt(PROBLEM);
+        assert_eq!(tsumego.play(point("dd")), Ok(Status::Unsolved));
+        assert_eq!(tsumego.game().board().get(3, 4), Piece::White);
+        assert_eq!(tsumego.game().next_to_play(), Piece::Black);
+    }
+
+    #[test]
+    fn white_can_be_the_one_to_play() {
+        let tsumego = attempt("(;SZ[9]AB[cc]PL[W](;W[dd]))");
+        assert_eq!(tsumego.player(), Piece::White);
+        assert_eq!(tsumego.game().next_to_play(), Piece::White);
+
+        // Without `PL` the first move says who plays, and taking back the
+        // setup gives the turn back too
+        let mut tsumego = attempt("(;SZ[9]AB[cc](;W[dd]))");
+        assert_eq!(tsumego.player(), Piece::White);
+        assert_eq!(tsumego.game().next_to_play(), Piece::White);
+        let mut game = tsumego.game().clone();
+        game.undo();
+        assert_eq!(game.next_to_play(), Piece::Black);
+        assert_eq!(game.board().get(2, 2), Piece::Black);
+
+        assert_eq!(tsumego.play(point("dd")), Ok(Status::Solved));
+    }
+
+    #[test]
+    fn finished_attempts_take_no_more_moves() {
+        let mut tsumego = attempt(PROBLEM);
+        tsumego.play(point("ee")).unwrap();
+        assert_eq!(tsumego.status(), Status::Solved);
+        assert_eq!(tsumego.play(point("ff")), Err(TsumegoError::Finished));
+
+        let mut tsumego = attempt(PROBLEM);
+        tsumego.play(point("hh")).unwrap();
+        assert_eq!(tsumego.play(point("ff")), Err(TsumegoError::Finished));
+
+        tsumego.restart().unwrap();
+        assert_eq!(tsumego.play(point("ff")), Ok(Status::Solved));
+    }
+
+    #[test]
+    fn illegal_replies_are_errors() {
+        let mut tsumego = attempt("(;SZ[9]AB[cc](;B[dd];W[cc]))");
+        assert_eq!(tsumego.play(point("dd")), Err(TsumegoError::BadProblem));
+        assert_eq!(tsumego.status(), Status::Unsolved);
+        assert_eq!(tsumego.game().board().get(3, 3), Piece::None);
+        assert_eq!(tsumego.game().next_to_play(), Piece::Black);
+    }
+}
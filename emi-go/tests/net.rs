@@ -0,0 +1,307 @@
+//! Network games between two sessions on localhost.
+
+use std::io::Write;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use emi_go::net::{NetEvent, Session, PROTOCOL_VERSION};
+use emi_go::{Event, Game, Move, Piece};
+
+/// Poll both sides until `done` returns true, collecting what happened on
+/// each side.
+fn poll_until(
+    host: (&mut Session, &mut Game),
+    joiner: (&mut Session, &mut Game),
+    mut done: impl FnMut(&[NetEvent], &[NetEvent]) -> bool,
+) -> (Vec<NetEvent>, Vec<NetEvent>) {
+    let (host, host_game) = host;
+    let (joiner, joiner_game) = joiner;
+    let mut host_events = vec![];
+    let mut joiner_events = vec![];
+    let start = Instant::now();
+    while !done(&host_events, &joiner_events) {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "Timed out; host saw {host_events:?}, joiner saw {joiner_events:?}"
+        );
+        host_events.extend(host.poll(host_game));
+        joiner_events.extend(joiner.poll(joiner_game));
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    (host_events, joiner_events)
+}
+
+fn connected(host: &[NetEvent], joiner: &[NetEvent]) -> bool {
+    host.contains(&NetEvent::Connected)
+        && joiner
+            .iter()
+            .any(|e| matches!(e, NetEvent::Connected | NetEvent::Resynced))
+}
+
+fn place(x: u32, y: u32, color: Piece) -> Move {
+    Move::Place { pos: [x, y], color }
+}
+
+/// Play a move on one side and wait for the other side to get it.
+fn play(mover: (&mut Session, &mut Game), other: (&mut Session, &mut Game), move_: Move) {
+    let (session, game) = mover;
+    assert!(game.play_(move_).is_ok(), "{move_:?} should be legal");
+    session.send_last_move(game);
+    poll_until((session, game), other, |_, theirs| {
+        theirs.contains(&NetEvent::Moved(move_))
+    });
+}
+
+#[test]
+fn moves_are_exchanged() {
+    let mut host = Session::host("127.0.0.1:0", Piece::Black).unwrap();
+    let mut host_game = Game::new(9);
+    let mut joiner = Session::join(host.local_addr().unwrap()).unwrap();
+    // The joiner's game is replaced with the host's
+    let mut joiner_game = Game::new(19);
+
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+    assert_eq!(joiner.local_color(), Piece::White);
+    assert_eq!(joiner_game.board_size(), 9);
+    assert!(host.can_play(&host_game));
+    assert!(!joiner.can_play(&joiner_game));
+
+    play(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        place(2, 2, Piece::Black),
+    );
+    play(
+        (&mut joiner, &mut joiner_game),
+        (&mut host, &mut host_game),
+        place(6, 6, Piece::White),
+    );
+    play(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        Move::Pass,
+    );
+
+    assert_eq!(host_game, joiner_game);
+    assert_eq!(host_game.position_hash(), joiner_game.position_hash());
+}
+
+#[test]
+fn out_of_turn_moves_are_rejected() {
+    let mut host = Session::host("127.0.0.1:0", Piece::Black).unwrap();
+    let mut host_game = Game::new(9);
+    let mut joiner = Session::join(host.local_addr().unwrap()).unwrap();
+    let mut joiner_game = Game::new(9);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+
+    // White plays on a game that has been tampered with
+    assert!(joiner_game.play_(Move::Pass).is_ok());
+    assert!(joiner_game.play_(place(4, 4, Piece::White)).is_ok());
+    joiner.send_last_move(&joiner_game);
+
+    let (host_events, joiner_events) = poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        |_, joiner| joiner.contains(&NetEvent::Resynced),
+    );
+    assert!(host_events
+        .iter()
+        .any(|e| matches!(e, NetEvent::Rejected(_))));
+    assert!(joiner_events.contains(&NetEvent::Resynced));
+    assert_eq!(host_game, joiner_game);
+    assert_eq!(host_game.moves_played(), 0);
+}
+
+#[test]
+fn joiner_reconnects_and_catches_up() {
+    let mut host = Session::host("127.0.0.1:0", Piece::White).unwrap();
+    let mut host_game = Game::new(9);
+    let mut joiner = Session::join(host.local_addr().unwrap()).unwrap();
+    let mut joiner_game = Game::new(9);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+    assert_eq!(joiner.local_color(), Piece::Black);
+
+    play(
+        (&mut joiner, &mut joiner_game),
+        (&mut host, &mut host_game),
+        place(4, 4, Piece::Black),
+    );
+
+    // Lose the connection, and play on the host while it is down
+    joiner.disconnect();
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        |host, _| host.contains(&NetEvent::Disconnected),
+    );
+    assert!(host_game.play_(place(3, 3, Piece::White)).is_ok());
+
+    let (_, joiner_events) = poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+    assert!(joiner_events.contains(&NetEvent::Resynced));
+    assert_eq!(host_game, joiner_game);
+    assert!(joiner.can_play(&joiner_game));
+}
+
+#[test]
+fn chat_and_comments_are_shared() {
+    let mut host = Session::host("127.0.0.1:0", Piece::Black).unwrap();
+    let mut host_game = Game::new(9);
+    let mut joiner = Session::join(host.local_addr().unwrap()).unwrap();
+    let mut joiner_game = Game::new(9);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+
+    joiner.send_chat("Hi!\nGood luck");
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        |host, _| host.contains(&NetEvent::Chat("Hi!\nGood luck".to_string())),
+    );
+
+    play(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        place(2, 6, Piece::Black),
+    );
+    host_game.set_comment("Taking the corner \\ a bit low");
+    host.send_comment(&host_game);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        |_, joiner| joiner.contains(&NetEvent::Commented { node: 1 }),
+    );
+    assert_eq!(joiner_game.comment(), "Taking the corner \\ a bit low");
+    assert!(joiner_game
+        .to_sgf()
+        .contains("C[Taking the corner \\\\ a bit low]"));
+
+    // Comments come back with the rest of the game after reconnecting
+    joiner.disconnect();
+    joiner_game = Game::new(9);
+    let (_, joiner_events) = poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        |_, joiner| joiner.contains(&NetEvent::Commented { node: 1 }),
+    );
+    assert!(joiner_events.contains(&NetEvent::Resynced));
+    assert_eq!(host_game, joiner_game);
+}
+
+#[test]
+fn host_refuses_a_second_joiner() {
+    let mut host = Session::host("127.0.0.1:0", Piece::Black).unwrap();
+    let mut host_game = Game::new(9);
+    let addr = host.local_addr().unwrap();
+    let mut joiner = Session::join(addr).unwrap();
+    let mut joiner_game = Game::new(9);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+
+    let mut intruder = Session::join(addr).unwrap();
+    let mut intruder_game = Game::new(9);
+    let (host_events, _) = poll_until(
+        (&mut host, &mut host_game),
+        (&mut intruder, &mut intruder_game),
+        |_, intruder| intruder.contains(&NetEvent::Disconnected),
+    );
+    assert!(!host_events.contains(&NetEvent::Connected));
+
+    // The first joiner is still the one playing
+    play(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        place(2, 2, Piece::Black),
+    );
+    assert_eq!(host_game, joiner_game);
+}
+
+/// Join a host that sends `lines`, and return what the joiner makes of them.
+fn join_fake_host(lines: &[String]) -> (Vec<NetEvent>, Game) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut joiner = Session::join(listener.local_addr().unwrap()).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    for line in lines {
+        writeln!(stream, "{line}").unwrap();
+    }
+
+    let mut game = Game::new(9);
+    let mut events = vec![];
+    let start = Instant::now();
+    while !events.iter().any(|e| matches!(e, NetEvent::Rejected(_))) {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "Timed out; joiner saw {events:?}"
+        );
+        events.extend(joiner.poll(&mut game));
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    (events, game)
+}
+
+#[test]
+fn bad_board_sizes_are_rejected() {
+    for size in [0, 1, 53, 100_000] {
+        let (events, game) = join_fake_host(&[format!("emi {PROTOCOL_VERSION} {size} W")]);
+        assert!(!events.contains(&NetEvent::Resynced));
+        assert_eq!(game.board_size(), 9);
+    }
+}
+
+#[test]
+fn edits_off_the_board_are_rejected() {
+    let edit = Event::Edit {
+        pos: [9, 0],
+        from: Piece::None,
+        to: Piece::Black,
+    };
+    let (events, game) = join_fake_host(&[
+        format!("emi {PROTOCOL_VERSION} 9 W"),
+        "history 1".to_string(),
+        edit.to_string(),
+        "sync 0".to_string(),
+    ]);
+    assert!(!events.contains(&NetEvent::Resynced));
+    assert_eq!(game.moves_played(), 0);
+}
+
+#[test]
+fn set_up_turns_reach_the_joiner() {
+    let mut host = Session::host("127.0.0.1:0", Piece::White).unwrap();
+    let mut host_game = Game::new(9);
+    let mut board = host_game.board().clone();
+    *board.get_mut(2, 2) = Piece::Black;
+    host_game.set_position_to_play(&board, Piece::White);
+    let mut joiner = Session::join(host.local_addr().unwrap()).unwrap();
+    let mut joiner_game = Game::new(9);
+    poll_until(
+        (&mut host, &mut host_game),
+        (&mut joiner, &mut joiner_game),
+        connected,
+    );
+    assert_eq!(host_game, joiner_game);
+    assert_eq!(joiner_game.next_to_play(), Piece::White);
+    joiner_game.undo();
+    assert_eq!(joiner_game.next_to_play(), Piece::Black);
+}
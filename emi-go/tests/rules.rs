@@ -0,0 +1,335 @@
+//! The rules checked against the positions in `tests/fixtures`.
+//!
+//! Each file in `fixtures/rules` is one case: the position `before:`, the
+//! moves to `play:` from it, and either the position `after:` them or the
+//! `error:` the last move fails with. Lines starting with `#` describe the
+//! case. Positions are diagrams as written by the `Display` of `Game`. The
+//! stones each move captures are checked against the ones that left the
+//! board.
+//!
+//! Each SGF file in `fixtures/sgf` is played out and compared with the
+//! diagram in the file of the same name ending in `.txt`.
+//!
+//! Every move is taken back afterwards, which has to bring back the exact
+//! position it started from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use emi_go::{coord, diagram, Event, Game, GameResult, Move, Piece, TimeControl, WinReason};
+
+#[derive(Debug, Default)]
+struct Case {
+    before: String,
+    moves: Vec<String>,
+    after: Option<String>,
+    error: Option<String>,
+}
+
+fn read_case(text: &str) -> Result<Case, String> {
+    let mut case = Case::default();
+    let mut section = None;
+    for line in text.lines().filter(|l| !l.starts_with('#')) {
+        if let Some(moves) = line.strip_prefix("play:") {
+            case.moves = moves.split_whitespace().map(str::to_string).collect();
+        } else if let Some(error) = line.strip_prefix("error:") {
+            case.error = Some(error.trim().to_string());
+        } else if line == "before:" {
+            section = Some(&mut case.before);
+        } else if line == "after:" {
+            section = Some(case.after.insert(String::new()));
+        } else if let Some(text) = &mut section {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    if case.before.is_empty() || case.moves.is_empty() {
+        return Err("needs a position `before:` and moves to `play:`".to_string());
+    }
+    if case.after.is_some() == case.error.is_some() {
+        return Err("needs either a position `after:` or an `error:`".to_string());
+    }
+    Ok(case)
+}
+
+/// The files in `fixtures/<dir>` ending in `.<extension>`, sorted.
+fn fixtures(dir: &str, extension: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(dir);
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Could not read {}: {e}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "No fixtures in {}", dir.display());
+    paths
+}
+
+fn parse_game(text: &str) -> Result<Game, String> {
+    diagram::parse_game(text).map_err(|e| format!("bad diagram: {e}"))
+}
+
+fn parse_move(game: &Game, name: &str) -> Result<Move, String> {
+    if name.eq_ignore_ascii_case("pass") {
+        return Ok(Move::Pass);
+    }
+    let pos = coord::from_pos_name(name, game.board_size())
+        .ok_or_else(|| format!("bad move `{name}`"))?;
+    let color = game.next_to_play();
+    Ok(Move::Place { pos, color })
+}
+
+/// Every chain on the board has a liberty, or it should have been captured.
+fn check_liberties(game: &Game) -> Result<(), String> {
+    let board = game.board();
+    let side = board.board_size();
+    for y in 0..side {
+        for x in 0..side {
+            if board.liberties(x, y) == Some(0) {
+                let name = coord::pos_name([x, y], side);
+                return Err(format!("the chain at {name} has no liberties\n{game}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The last event took exactly the stones `move_` removed from the board,
+/// each once, and they were added to the mover's captures.
+fn check_captures(before: &Game, game: &Game, move_: Move) -> Result<(), String> {
+    let Move::Place { color, .. } = move_ else {
+        return Ok(());
+    };
+    let side = game.board_size();
+    let mut removed: Vec<[u32; 2]> = (0..side)
+        .flat_map(|y| (0..side).map(move |x| [x, y]))
+        .filter(|&[x, y]| {
+            before.board().get(x, y) == color.opposing() && game.board().get(x, y) == Piece::None
+        })
+        .collect();
+    let mut captured = match game.events().last() {
+        Some(Event::Capture { captured, .. }) => captured.clone(),
+        _ => vec![],
+    };
+    removed.sort();
+    captured.sort();
+    if captured != removed {
+        return Err(format!(
+            "the move removed {removed:?}, but recorded {captured:?}"
+        ));
+    }
+    let count = game.captures(color) - before.captures(color);
+    if count as usize != removed.len() {
+        return Err(format!(
+            "the move took {} stones, but counted {count}",
+            removed.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Take back the last `count` moves, and check that brings back `start`.
+fn undo_to(game: &mut Game, count: usize, start: &Game) -> Result<(), String> {
+    for _ in 0..count {
+        game.undo();
+    }
+    if game.to_string() != start.to_string() || game.position_hash() != start.position_hash() {
+        return Err(format!("undoing every move gives\n{game}\nnot\n{start}"));
+    }
+    Ok(())
+}
+
+fn run_case(case: &Case) -> Result<(), String> {
+    let start = parse_game(&case.before)?;
+    let mut game = start.clone();
+    let mut played = 0;
+    for (i, name) in case.moves.iter().enumerate() {
+        let move_ = parse_move(&game, name)?;
+        let last = i + 1 == case.moves.len();
+        let before = game.clone();
+        match (game.play_(move_), &case.error) {
+            (Ok(()), Some(expected)) if last => {
+                return Err(format!(
+                    "{name} should fail with {expected}, but gives\n{game}"
+                ));
+            }
+            (Ok(()), _) => {
+                check_captures(&before, &game, move_)?;
+                played += 1;
+            }
+            (Err(e), Some(expected)) if last => {
+                let found = format!("{e:?}");
+                if !found.starts_with(expected.as_str()) {
+                    return Err(format!("{name} should fail with {expected}, not {found}"));
+                }
+                if game.to_string() != before.to_string()
+                    || game.position_hash() != before.position_hash()
+                {
+                    return Err(format!("{name} failed, but changed the game to\n{game}"));
+                }
+            }
+            (Err(e), _) => return Err(format!("{name} failed: {e}")),
+        }
+        check_liberties(&game)?;
+    }
+
+    if let Some(after) = &case.after {
+        let expected = parse_game(after)?;
+        if game.to_string() != expected.to_string() {
+            return Err(format!("the moves give\n{game}\nnot\n{expected}"));
+        }
+    }
+    undo_to(&mut game, played, &start)
+}
+
+fn run_sgf(path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut game = Game::from_sgf(&text).map_err(|e| format!("bad SGF: {e}"))?;
+    let diagram = fs::read_to_string(path.with_extension("txt")).map_err(|e| e.to_string())?;
+    let expected = parse_game(&diagram)?;
+    if game.to_string() != expected.to_string() {
+        return Err(format!("the game ends with\n{game}\nnot\n{expected}"));
+    }
+    check_liberties(&game)?;
+
+    let side = game.board_size();
+    for _ in 0..game.events().len() {
+        game.undo();
+    }
+    let empty = (0..side).all(|y| (0..side).all(|x| game.board().get(x, y) == Piece::None));
+    if !empty {
+        return Err(format!("undoing every event leaves\n{game}"));
+    }
+    Ok(())
+}
+
+/// Run every fixture, and report all the ones that fail at once.
+fn check_all(paths: Vec<PathBuf>, run: impl Fn(&Path) -> Result<(), String>) {
+    let failures: Vec<_> = paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            run(path).err().map(|e| format!("{name}: {e}"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
+
+#[test]
+fn rule_fixtures() {
+    check_all(fixtures("rules", "txt"), |path| {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        run_case(&read_case(&text)?)
+    });
+}
+
+#[test]
+fn sgf_fixtures() {
+    check_all(fixtures("sgf", "sgf"), run_sgf);
+}
+
+#[test]
+fn undo_restores_every_position() {
+    // Pseudorandom moves until the board fills up, undone one at a time
+    let mut game = Game::new(9);
+    let mut positions = vec![];
+    let mut seed = 12345_u64;
+    for _ in 0..1000 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let point = ((seed >> 33) % 81) as u32;
+        let color = game.next_to_play();
+        let move_ = Move::Place {
+            pos: [point % 9, point / 9],
+            color,
+        };
+        let position = game.clone();
+        if game.play_(move_).is_ok() {
+            positions.push(position);
+            check_liberties(&game).unwrap();
+        }
+    }
+    assert!(positions.len() > 50);
+    assert!(game.captures(Piece::Black) + game.captures(Piece::White) > 0);
+    while let Some(position) = positions.pop() {
+        undo_to(&mut game, 1, &position).unwrap();
+    }
+}
+
+#[test]
+fn undo_takes_back_results() {
+    let mut game = Game::new(9);
+    game.play_(Move::Pass).unwrap();
+    game.play_(Move::Pass).unwrap();
+    game.set_result(Some(GameResult::Draw));
+    game.undo();
+    assert_eq!(game.result(), None);
+    assert!(game.play_(Move::Pass).is_ok());
+
+    let main = Duration::from_secs(10);
+    let mut game = Game::with_time_control(9, TimeControl::Absolute { main });
+    game.play_(Move::Pass).unwrap();
+    game.tick_clock(Duration::from_secs(20));
+    assert_eq!(
+        game.result(),
+        Some(GameResult::Win {
+            winner: Piece::Black,
+            by: WinReason::Time,
+        })
+    );
+    game.undo();
+    assert_eq!(game.result(), None);
+    assert_eq!(game.timed_out(), None);
+    assert!(game.play_(Move::Pass).is_ok());
+}
+
+#[test]
+fn setting_up_a_position_hands_over_the_turn_in_one_edit() {
+    let mut game = Game::new(9);
+    let mut board = game.board().clone();
+    *board.get_mut(2, 2) = Piece::Black;
+    assert!(game.set_position_to_play(&board, Piece::White));
+    assert_eq!(game.events().count(), 1);
+    assert_eq!(game.next_to_play(), Piece::White);
+
+    // Only the turn changes
+    assert!(game.set_position_to_play(&board, Piece::Black));
+    assert_eq!(game.next_to_play(), Piece::Black);
+    game.undo();
+    assert_eq!(game.next_to_play(), Piece::White);
+
+    game.undo();
+    assert_eq!(game.next_to_play(), Piece::Black);
+    assert_eq!(game.board().get(2, 2), Piece::None);
+}
+
+#[test]
+fn chains_touched_from_two_sides_are_captured_once() {
+    let mut game = parse_game(
+        "
+        O O X . .
+        . O X . .
+        X X . . .
+        . . . . .
+        . . . . .
+        ",
+    )
+    .unwrap();
+    if game.next_to_play() != Piece::Black {
+        game.play_(Move::Pass).unwrap();
+    }
+    game.play_(Move::Place {
+        pos: [0, 1],
+        color: Piece::Black,
+    })
+    .unwrap();
+    assert_eq!(game.captures(Piece::Black), 3);
+    let Some(Event::Capture { captured, .. }) = game.events().last() else {
+        panic!("Expected a capture");
+    };
+    assert_eq!(captured.len(), 3);
+}
@@ -0,0 +1,175 @@
+//! Games written to SGF and read back.
+
+use std::time::Duration;
+
+use emi_go::{sgf, Game, Move, Piece, SgfError, TimeControl};
+
+fn place(x: u32, y: u32, color: Piece) -> Move {
+    Move::Place { pos: [x, y], color }
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn time_controls_are_read_back() {
+    for control in [
+        TimeControl::Absolute { main: secs(600) },
+        TimeControl::ByoYomi {
+            main: secs(600),
+            periods: 5,
+            period: secs(30),
+        },
+        TimeControl::Canadian {
+            main: secs(0),
+            stones: 25,
+            period: secs(300),
+        },
+        TimeControl::Fischer {
+            main: secs(300),
+            increment: secs(10),
+        },
+    ] {
+        let game = Game::with_time_control(9, control);
+        let read = Game::from_sgf(&game.to_sgf()).unwrap();
+        assert_eq!(read.clock().map(|c| c.time_control()), Some(control));
+    }
+
+    let untimed = Game::from_sgf(&Game::new(9).to_sgf()).unwrap();
+    assert!(untimed.clock().is_none());
+}
+
+#[test]
+fn clocks_are_read_back_and_undone() {
+    let control = TimeControl::ByoYomi {
+        main: secs(60),
+        periods: 3,
+        period: secs(30),
+    };
+    let mut game = Game::with_time_control(9, control);
+    game.tick_clock(secs(10));
+    game.play_(place(2, 2, Piece::Black)).unwrap();
+    game.tick_clock(secs(70));
+    game.play_(place(6, 6, Piece::White)).unwrap();
+
+    let read = Game::from_sgf(&game.to_sgf()).unwrap();
+    assert_eq!(read.clock(), game.clock());
+    let white = read.clock().unwrap().player(Piece::White);
+    assert!(white.in_overtime());
+    assert_eq!(white.period_left(), secs(30));
+    assert_eq!(white.periods_left(), 3);
+
+    // Time spent on a move that is taken back is given back
+    game.tick_clock(secs(20));
+    game.play_(place(2, 6, Piece::Black)).unwrap();
+    game.undo();
+    assert_eq!(game.clock(), read.clock());
+    assert_eq!(
+        game.clock().unwrap().player(Piece::Black).main_left(),
+        secs(50)
+    );
+
+    game.undo();
+    game.undo();
+    assert_eq!(game.clock(), Game::with_time_control(9, control).clock());
+}
+
+#[test]
+fn tt_is_a_pass_only_on_small_boards() {
+    let game = Game::from_sgf("(;SZ[19];B[tt])").unwrap();
+    assert!(game.last_was_pass());
+
+    let game = Game::from_sgf("(;SZ[25];B[tt];W[])").unwrap();
+    assert_eq!(game.board().get(19, 19), Piece::Black);
+    assert_eq!(
+        Game::from_sgf(&game.to_sgf()).unwrap().board().get(19, 19),
+        Piece::Black
+    );
+}
+
+#[test]
+fn bad_moves_are_errors_not_passes() {
+    for (text, value) in [
+        ("(;SZ[9];B[zz9])", "zz9"),
+        ("(;SZ[9];B[cc];W[jj])", "jj"),
+        ("(;SZ[9];B[c])", "c"),
+        ("(;SZ[19];B[aa];W[ta])", "ta"),
+    ] {
+        let ident = if text.contains("W[") { "W" } else { "B" };
+        assert_eq!(
+            Game::from_sgf(text).err(),
+            Some(SgfError::BadValue {
+                ident: ident.to_string(),
+                value: value.to_string(),
+            }),
+            "{text}"
+        );
+    }
+    assert!(Game::from_sgf("(;SZ[9];B[])").unwrap().last_was_pass());
+}
+
+#[test]
+fn moves_out_of_turn_are_undone_to_the_right_player() {
+    let mut game = Game::from_sgf("(;SZ[9];B[cc];B[gg])").unwrap();
+    assert_eq!(game.board().get(6, 6), Piece::Black);
+    assert_eq!(game.next_to_play(), Piece::White);
+
+    game.undo();
+    assert_eq!(game.board().get(6, 6), Piece::None);
+    assert_eq!(game.next_to_play(), Piece::Black);
+    game.undo();
+    assert_eq!(game.next_to_play(), Piece::White);
+    game.undo();
+    assert_eq!(game.next_to_play(), Piece::Black);
+    assert_eq!(game.board().get(2, 2), Piece::None);
+}
+
+#[test]
+fn unreadable_results_are_left_out() {
+    for re in ["", "Jigo", "W+0.5 (time)", "?!"] {
+        let game = Game::from_sgf(&format!("(;SZ[9]RE[{re}];B[cc])")).unwrap();
+        assert_eq!(game.result(), None, "RE[{re}]");
+        assert_eq!(game.board().get(2, 2), Piece::Black);
+    }
+    let game = Game::from_sgf("(;SZ[9]RE[W+0.5];B[cc])").unwrap();
+    assert_eq!(game.result().and_then(|r| r.winner()), Some(Piece::White));
+}
+
+#[test]
+fn unreadable_komi_is_left_out() {
+    for km in ["", "6,5", "six", "inf"] {
+        let game = Game::from_sgf(&format!("(;SZ[9]KM[{km}];B[cc])")).unwrap();
+        assert_eq!(game.info().komi, 0.0, "KM[{km}]");
+        assert_eq!(game.board().get(2, 2), Piece::Black);
+    }
+    let game = Game::from_sgf("(;SZ[9]KM[ 6.5 ];B[cc])").unwrap();
+    assert_eq!(game.info().komi, 6.5);
+}
+
+#[test]
+fn variations_can_be_played_part_way_through_a_finished_game() {
+    let text = "(;SZ[9]RE[B+R];B[cc];W[gg];B[cg])";
+    let game = Game::from_sgf(text).unwrap();
+    assert_eq!(game.result().and_then(|r| r.winner()), Some(Piece::Black));
+
+    let tree = sgf::parse(text).unwrap().remove(0);
+    let mut game = Game::from_sgf_nodes(tree.main_line().take(3)).unwrap();
+    assert_eq!(game.result(), None);
+    game.play_(place(6, 2, Piece::Black)).unwrap();
+}
+
+#[test]
+fn turns_set_up_with_stones_are_read_back() {
+    let mut game = Game::new(9);
+    let mut board = game.board().clone();
+    *board.get_mut(2, 2) = Piece::Black;
+    game.set_position_to_play(&board, Piece::White);
+    game.play_(place(6, 6, Piece::White)).unwrap();
+
+    let mut read = Game::from_sgf(&game.to_sgf()).unwrap();
+    assert!(read.events().eq(game.events()));
+    read.undo();
+    read.undo();
+    assert_eq!(read.next_to_play(), Piece::Black);
+}
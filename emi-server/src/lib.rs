@@ -0,0 +1,24 @@
+//! A server hosting many go games at once, for clients that can not reach
+//! each other directly, eg. the wasm frontend.
+//!
+//! Clients connect over a WebSocket and speak JSON (see [`protocol`]). Each
+//! room holds one [`emi_go::Game`], which is the only copy that counts:
+//! moves are checked with `Game::play_` before being sent to everyone in
+//! the room. Every connection gets its own thread.
+//!
+//! Clients can leave out the default `server` feature to just get the
+//! [`protocol`].
+
+#[cfg(feature = "server")]
+mod lobby;
+pub mod protocol;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "server")]
+pub use lobby::{Lobby, BOARD_SIZES};
+#[cfg(feature = "server")]
+pub use server::Server;
+
+/// The port used when none is given.
+pub const DEFAULT_PORT: u16 = 7651;
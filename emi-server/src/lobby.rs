@@ -0,0 +1,353 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::mpsc::Sender;
+
+use emi_go::{Game, Move, Piece};
+#[allow(unused_imports)]
+use tracing::{debug, info, warn};
+
+use crate::protocol::{ClientMessage, NodeComment, RoomId, RoomInfo, Seat, ServerMessage};
+
+/// Board sizes rooms can be opened with, the same as the frontend offers.
+pub const BOARD_SIZES: std::ops::RangeInclusive<u32> = 9..=19;
+
+/// The longest chat message or comment, in bytes.
+pub const MAX_TEXT_LEN: usize = 4096;
+
+pub type ClientId = u64;
+
+/// One game, with whoever is playing and watching it.
+#[derive(Debug)]
+struct Room {
+    name: String,
+    game: Game,
+    black: Option<ClientId>,
+    white: Option<ClientId>,
+    spectators: BTreeSet<ClientId>,
+}
+
+impl Room {
+    fn info(&self, id: RoomId) -> RoomInfo {
+        RoomInfo {
+            id,
+            name: self.name.clone(),
+            board_size: self.game.board_size(),
+            black: self.black.is_some(),
+            white: self.white.is_some(),
+            spectators: self.spectators.len(),
+            moves: self.game.moves_played(),
+            result: self.game.result().map(|r| r.to_string()),
+        }
+    }
+
+    /// Everyone in the room.
+    fn members(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.black
+            .iter()
+            .chain(&self.white)
+            .chain(&self.spectators)
+            .copied()
+    }
+
+    fn seat_of(&self, client: ClientId) -> Option<Seat> {
+        if self.black == Some(client) {
+            Some(Seat::Black)
+        } else if self.white == Some(client) {
+            Some(Seat::White)
+        } else if self.spectators.contains(&client) {
+            Some(Seat::Spectator)
+        } else {
+            None
+        }
+    }
+
+    /// Sit `client` down, or explain why the seat is taken.
+    fn sit(&mut self, client: ClientId, seat: Seat) -> Result<(), String> {
+        let player = match seat {
+            Seat::Black => &mut self.black,
+            Seat::White => &mut self.white,
+            Seat::Spectator => {
+                self.spectators.insert(client);
+                return Ok(());
+            }
+        };
+        if player.is_some() {
+            return Err(format!("{seat:?} is already taken"));
+        }
+        *player = Some(client);
+        Ok(())
+    }
+
+    fn stand(&mut self, client: ClientId) {
+        if self.black == Some(client) {
+            self.black = None;
+        }
+        if self.white == Some(client) {
+            self.white = None;
+        }
+        self.spectators.remove(&client);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.members().next().is_none()
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    outbox: Sender<ServerMessage>,
+    room: Option<RoomId>,
+}
+
+/// Every room and connected client. The server keeps this behind a mutex
+/// shared by the connection threads, and each client gets its messages
+/// through its own channel.
+#[derive(Debug, Default)]
+pub struct Lobby {
+    rooms: BTreeMap<RoomId, Room>,
+    clients: HashMap<ClientId, Client>,
+    next_room: RoomId,
+    next_client: ClientId,
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a client that will be sent messages through `outbox`.
+    pub fn connect(&mut self, outbox: Sender<ServerMessage>) -> ClientId {
+        let id = self.next_client;
+        self.next_client += 1;
+        self.clients.insert(id, Client { outbox, room: None });
+        id
+    }
+
+    /// Remove a client, freeing its seat.
+    pub fn disconnect(&mut self, client: ClientId) {
+        self.leave(client);
+        self.clients.remove(&client);
+    }
+
+    /// Carry out a message from `client`, sending an error back if it can
+    /// not be.
+    pub fn handle(&mut self, client: ClientId, message: ClientMessage) {
+        let result = match message {
+            ClientMessage::ListRooms => {
+                let rooms = self.rooms.iter().map(|(&id, room)| room.info(id)).collect();
+                self.send(client, ServerMessage::Rooms { rooms });
+                Ok(())
+            }
+            ClientMessage::CreateRoom {
+                name,
+                board_size,
+                seat,
+            } => self.create_room(client, name, board_size, seat),
+            ClientMessage::Join { room, seat } => self.join(client, room, seat),
+            ClientMessage::Leave => {
+                self.leave(client);
+                self.send(client, ServerMessage::Left);
+                Ok(())
+            }
+            ClientMessage::Play { x, y } => {
+                self.play(client, |color| Move::Place { pos: [x, y], color })
+            }
+            ClientMessage::Pass => self.play(client, |_| Move::Pass),
+            ClientMessage::Resign => self.play(client, |color| Move::Resign { color }),
+            ClientMessage::Chat { text } => self.chat(client, text),
+            ClientMessage::Comment { text } => self.comment(client, text),
+        };
+        if let Err(message) = result {
+            debug!("Client {} error: {}", client, message);
+            self.send(client, ServerMessage::Error { message });
+        }
+    }
+
+    pub fn send(&self, client: ClientId, message: ServerMessage) {
+        if let Some(c) = self.clients.get(&client) {
+            // The connection is already closing if this fails
+            let _ = c.outbox.send(message);
+        }
+    }
+
+    /// Send `message` to everyone in `room`, except `skip`.
+    fn broadcast(&self, room: RoomId, message: &ServerMessage, skip: Option<ClientId>) {
+        let Some(r) = self.rooms.get(&room) else {
+            return;
+        };
+        for member in r.members().filter(|&m| Some(m) != skip) {
+            self.send(member, message.clone());
+        }
+    }
+
+    fn create_room(
+        &mut self,
+        client: ClientId,
+        name: String,
+        board_size: u32,
+        seat: Seat,
+    ) -> Result<(), String> {
+        if !BOARD_SIZES.contains(&board_size) {
+            return Err(format!("Board size must be within {BOARD_SIZES:?}"));
+        }
+        let id = self.next_room;
+        self.next_room += 1;
+        info!(
+            "Opening room {} ({:?}, {}x{})",
+            id, name, board_size, board_size
+        );
+        self.rooms.insert(
+            id,
+            Room {
+                name,
+                game: Game::new(board_size),
+                black: None,
+                white: None,
+                spectators: BTreeSet::new(),
+            },
+        );
+        self.join(client, id, seat)
+    }
+
+    fn join(&mut self, client: ClientId, room: RoomId, seat: Seat) -> Result<(), String> {
+        if !self.rooms.contains_key(&room) {
+            return Err(format!("No room {room}"));
+        }
+        // Check the seat before leaving, so a failed join keeps the old one
+        let r = &self.rooms[&room];
+        let taken = match seat {
+            Seat::Black => r.black.is_some_and(|c| c != client),
+            Seat::White => r.white.is_some_and(|c| c != client),
+            Seat::Spectator => false,
+        };
+        if taken {
+            return Err(format!("{seat:?} is already taken"));
+        }
+
+        // Changing seats in the same room must not close it
+        if self.clients.get(&client).and_then(|c| c.room) == Some(room) {
+            if let Some(r) = self.rooms.get_mut(&room) {
+                r.stand(client);
+            }
+        } else {
+            self.leave(client);
+        }
+        let r = self.rooms.get_mut(&room).ok_or("Room closed")?;
+        r.sit(client, seat)?;
+        if let Some(c) = self.clients.get_mut(&client) {
+            c.room = Some(room);
+        }
+
+        let r = &self.rooms[&room];
+        let info = r.info(room);
+        let events = r.game.events().map(|e| e.to_string()).collect();
+        let comments = r
+            .game
+            .comments()
+            .map(|(node, text)| NodeComment {
+                node,
+                text: text.to_string(),
+            })
+            .collect();
+        self.send(
+            client,
+            ServerMessage::Joined {
+                room: info.clone(),
+                seat,
+                events,
+                comments,
+            },
+        );
+        self.broadcast(
+            room,
+            &ServerMessage::RoomChanged { room: info },
+            Some(client),
+        );
+        Ok(())
+    }
+
+    /// Leave the current room, closing it if nobody is left.
+    fn leave(&mut self, client: ClientId) {
+        let Some(room) = self.clients.get_mut(&client).and_then(|c| c.room.take()) else {
+            return;
+        };
+        let Some(r) = self.rooms.get_mut(&room) else {
+            return;
+        };
+        r.stand(client);
+        if r.is_empty() {
+            info!("Closing room {}", room);
+            self.rooms.remove(&room);
+        } else {
+            let info = r.info(room);
+            self.broadcast(room, &ServerMessage::RoomChanged { room: info }, None);
+        }
+    }
+
+    /// Play a move for `client`'s color, checked against the room's game.
+    fn play(&mut self, client: ClientId, move_: impl FnOnce(Piece) -> Move) -> Result<(), String> {
+        let (room, seat) = self.seat(client)?;
+        if seat == Seat::Spectator {
+            return Err("Spectators can not play".to_string());
+        }
+        let color = seat.color();
+        let r = self.rooms.get_mut(&room).ok_or("Room closed")?;
+        let move_ = move_(color);
+        // Resigning is allowed on either turn
+        if !matches!(move_, Move::Resign { .. }) && r.game.next_to_play() != color {
+            return Err("Not your turn".to_string());
+        }
+        r.game.play_(move_).map_err(|e| e.to_string())?;
+
+        let event = r
+            .game
+            .events()
+            .last()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        let message = ServerMessage::Moved {
+            event,
+            hash: format!("{:016x}", r.game.position_hash()),
+            result: r.game.result().map(|r| r.to_string()),
+        };
+        self.broadcast(room, &message, None);
+        Ok(())
+    }
+
+    /// The room `client` is in, and where they sit.
+    fn seat(&self, client: ClientId) -> Result<(RoomId, Seat), String> {
+        let room = self
+            .clients
+            .get(&client)
+            .and_then(|c| c.room)
+            .ok_or("Not in a room")?;
+        let seat = self
+            .rooms
+            .get(&room)
+            .and_then(|r| r.seat_of(client))
+            .ok_or("Room closed")?;
+        Ok((room, seat))
+    }
+
+    fn chat(&mut self, client: ClientId, text: String) -> Result<(), String> {
+        let (room, from) = self.seat(client)?;
+        if text.len() > MAX_TEXT_LEN {
+            return Err("Message too long".to_string());
+        }
+        self.broadcast(room, &ServerMessage::Chat { from, text }, None);
+        Ok(())
+    }
+
+    /// Anyone in the room may comment, so a teacher can watch and explain.
+    fn comment(&mut self, client: ClientId, text: String) -> Result<(), String> {
+        let (room, _) = self.seat(client)?;
+        if text.len() > MAX_TEXT_LEN {
+            return Err("Comment too long".to_string());
+        }
+        let r = self.rooms.get_mut(&room).ok_or("Room closed")?;
+        let node = r.game.move_number();
+        r.game.set_comment(text.clone());
+        let message = ServerMessage::Commented(NodeComment { node, text });
+        self.broadcast(room, &message, None);
+        Ok(())
+    }
+}
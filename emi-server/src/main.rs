@@ -0,0 +1,21 @@
+use std::env;
+
+use emi_server::{Server, DEFAULT_PORT};
+use tracing::error;
+
+/// Usage: `emi-server [address]`, eg. `emi-server 0.0.0.0:7651`.
+fn main() {
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_max_level(tracing::Level::INFO)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).expect("Only set once");
+
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| format!("0.0.0.0:{DEFAULT_PORT}"));
+    let result = Server::bind(&*addr).and_then(Server::run);
+    if let Err(e) = result {
+        error!("Server stopped: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,123 @@
+//! The messages sent over the WebSocket, one JSON object per text message.
+//!
+//! Every message has a `type` field naming the variant in snake case, eg.
+//! `{"type": "join", "room": 1, "seat": "black"}`. Moves are sent back as
+//! `emi_go::Event`s in their one-line text form, the same as in
+//! `emi_go::net`, so clients can replay them with `Game::play_`.
+
+use serde::{Deserialize, Serialize};
+
+pub type RoomId = u64;
+
+/// Where a client sits in a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Seat {
+    Black,
+    White,
+    /// Watches, but can not play.
+    Spectator,
+}
+
+impl Seat {
+    pub fn color(&self) -> emi_go::Piece {
+        match self {
+            Self::Black => emi_go::Piece::Black,
+            Self::White => emi_go::Piece::White,
+            Self::Spectator => emi_go::Piece::None,
+        }
+    }
+}
+
+/// A message from a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Ask for every open room.
+    ListRooms,
+    /// Open a new room and sit down in it.
+    CreateRoom {
+        name: String,
+        board_size: u32,
+        seat: Seat,
+    },
+    /// Sit down in an open room, leaving the current one.
+    Join {
+        room: RoomId,
+        seat: Seat,
+    },
+    /// Leave the current room.
+    Leave,
+    /// Place a stone in the current room.
+    Play {
+        x: u32,
+        y: u32,
+    },
+    Pass,
+    Resign,
+    /// Say something to everyone in the current room.
+    Chat {
+        text: String,
+    },
+    /// Replace the comment on the current position, eg. while teaching.
+    Comment {
+        text: String,
+    },
+}
+
+/// A message from the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Every open room, in answer to `list_rooms`.
+    Rooms { rooms: Vec<RoomInfo> },
+    /// This client sat down in a room. `events` is the game so far, and
+    /// `comments` the comment on each position that has one.
+    Joined {
+        room: RoomInfo,
+        seat: Seat,
+        events: Vec<String>,
+        comments: Vec<NodeComment>,
+    },
+    /// Someone else sat down in or left the current room.
+    RoomChanged { room: RoomInfo },
+    /// A move was played in the current room. `hash` is the position hash
+    /// after it in hex, for clients to check they are in sync.
+    Moved {
+        event: String,
+        hash: String,
+        /// The result in SGF form, eg. `B+R`, once the game is over.
+        result: Option<String>,
+    },
+    /// Someone in the current room said something.
+    Chat { from: Seat, text: String },
+    /// The comment on a position changed.
+    Commented(NodeComment),
+    /// This client left its room.
+    Left,
+    /// The last message could not be carried out.
+    Error { message: String },
+}
+
+/// A summary of a room, for picking one to join.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub id: RoomId,
+    pub name: String,
+    pub board_size: u32,
+    /// Someone is playing black.
+    pub black: bool,
+    /// Someone is playing white.
+    pub white: bool,
+    pub spectators: usize,
+    pub moves: u32,
+    /// The result in SGF form, once the game is over.
+    pub result: Option<String>,
+}
+
+/// The comment on the position after `node` moves and edits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeComment {
+    pub node: usize,
+    pub text: String,
+}
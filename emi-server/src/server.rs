@@ -0,0 +1,131 @@
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+use tungstenite::Message;
+
+use crate::lobby::Lobby;
+use crate::protocol::{ClientMessage, ServerMessage};
+
+/// How long a connection waits for a message before checking for ones to
+/// send.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a new connection has to finish the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    lobby: Arc<Mutex<Lobby>>,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            lobby: Arc::new(Mutex::new(Lobby::new())),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections forever.
+    pub fn run(self) -> io::Result<()> {
+        info!("Listening on {}", self.local_addr()?);
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Could not accept connection: {}", e);
+                    continue;
+                }
+            };
+            let lobby = Arc::clone(&self.lobby);
+            std::thread::spawn(move || {
+                let peer = stream.peer_addr().ok();
+                if let Err(e) = serve(stream, &lobby) {
+                    debug!("Connection from {:?} ended: {}", peer, e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Talk to one client until it goes away.
+#[allow(clippy::result_large_err)]
+fn serve(stream: TcpStream, lobby: &Mutex<Lobby>) -> tungstenite::Result<()> {
+    // The handshake can take a while over a real network, so only start
+    // polling once it is done
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    let mut socket = tungstenite::accept(stream).map_err(|e| match e {
+        tungstenite::HandshakeError::Failure(e) => e,
+        tungstenite::HandshakeError::Interrupted(_) => {
+            tungstenite::Error::Io(io::ErrorKind::TimedOut.into())
+        }
+    })?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (outbox, inbox) = mpsc::channel();
+    let client = lock(lobby).connect(outbox);
+    info!("Client {} connected", client);
+
+    let result = loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(message) => {
+                    debug!("Client {} sent {:?}", client, message);
+                    lock(lobby).handle(client, message);
+                }
+                Err(e) => lock(lobby).send(
+                    client,
+                    ServerMessage::Error {
+                        message: format!("Bad message: {e}"),
+                    },
+                ),
+            },
+            Ok(Message::Close(_)) => break Ok(()),
+            // Pings are answered by tungstenite
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => break Err(e),
+        }
+
+        let mut sent = Ok(());
+        for message in inbox.try_iter() {
+            let text = serde_json::to_string(&message).expect("Messages always serialize");
+            sent = socket.send(Message::Text(text));
+            if sent.is_err() {
+                break;
+            }
+        }
+        if let Err(e) = sent {
+            break Err(e);
+        }
+    };
+
+    lock(lobby).disconnect(client);
+    info!("Client {} disconnected", client);
+    match result {
+        Err(tungstenite::Error::ConnectionClosed) => Ok(()),
+        result => result,
+    }
+}
+
+/// Lock the lobby, carrying on if another connection panicked while
+/// holding it.
+fn lock(lobby: &Mutex<Lobby>) -> std::sync::MutexGuard<'_, Lobby> {
+    lobby.lock().unwrap_or_else(|e| e.into_inner())
+}
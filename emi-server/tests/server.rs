@@ -0,0 +1,284 @@
+//! Clients talking to a server on localhost.
+
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use emi_go::{Event, Game, Piece};
+use emi_server::protocol::{ClientMessage, NodeComment, RoomId, Seat, ServerMessage};
+use emi_server::Server;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+fn start_server() -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+    addr
+}
+
+struct Client {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    fn connect(addr: SocketAddr) -> Self {
+        let (socket, _) = tungstenite::connect(format!("ws://{addr}")).unwrap();
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+        }
+        Self { socket }
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        let text = serde_json::to_string(&message).unwrap();
+        self.socket.send(Message::Text(text)).unwrap();
+    }
+
+    fn recv(&mut self) -> ServerMessage {
+        loop {
+            match self.socket.read().unwrap() {
+                Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Create a room and return its id.
+    fn create_room(&mut self, board_size: u32, seat: Seat) -> RoomId {
+        self.send(ClientMessage::CreateRoom {
+            name: "test".to_string(),
+            board_size,
+            seat,
+        });
+        match self.recv() {
+            ServerMessage::Joined { room, .. } => room.id,
+            other => panic!("Expected to join, got {other:?}"),
+        }
+    }
+
+    /// Join a room and return the events played so far.
+    fn join(&mut self, room: RoomId, seat: Seat) -> Vec<String> {
+        self.send(ClientMessage::Join { room, seat });
+        match self.recv() {
+            ServerMessage::Joined { events, .. } => events,
+            other => panic!("Expected to join, got {other:?}"),
+        }
+    }
+
+    fn expect_room_changed(&mut self) {
+        match self.recv() {
+            ServerMessage::RoomChanged { .. } => {}
+            other => panic!("Expected a room change, got {other:?}"),
+        }
+    }
+
+    fn expect_move(&mut self) -> (Event, String) {
+        match self.recv() {
+            ServerMessage::Moved { event, hash, .. } => (event.parse().unwrap(), hash),
+            other => panic!("Expected a move, got {other:?}"),
+        }
+    }
+
+    fn expect_error(&mut self) -> String {
+        match self.recv() {
+            ServerMessage::Error { message } => message,
+            other => panic!("Expected an error, got {other:?}"),
+        }
+    }
+}
+
+/// Play an event from the server on a client's copy of the game.
+fn replay(game: &mut Game, event: &Event) {
+    let move_ = event.to_move().expect("Only moves are sent");
+    assert!(game.play_(move_).is_ok(), "{move_:?} should be legal");
+}
+
+#[test]
+fn players_and_spectators_see_moves() {
+    let addr = start_server();
+    let mut black = Client::connect(addr);
+    let mut white = Client::connect(addr);
+    let mut watcher = Client::connect(addr);
+
+    let room = black.create_room(9, Seat::Black);
+    white.join(room, Seat::White);
+    black.expect_room_changed();
+    watcher.join(room, Seat::Spectator);
+    black.expect_room_changed();
+    white.expect_room_changed();
+
+    let mut game = Game::new(9);
+    for (mover, x, y) in [(0, 2, 2), (1, 6, 6)] {
+        [&mut black, &mut white][mover].send(ClientMessage::Play { x, y });
+        let (event, hash) = watcher.expect_move();
+        assert_eq!(black.expect_move(), (event.clone(), hash.clone()));
+        assert_eq!(white.expect_move(), (event.clone(), hash.clone()));
+        replay(&mut game, &event);
+        assert_eq!(hash, format!("{:016x}", game.position_hash()));
+    }
+    assert_eq!(game.board().get(2, 2), Piece::Black);
+    assert_eq!(game.board().get(6, 6), Piece::White);
+
+    // Someone joining late gets the whole game
+    let mut late = Client::connect(addr);
+    let events = late.join(room, Seat::Spectator);
+    let mut late_game = Game::new(9);
+    for event in &events {
+        replay(&mut late_game, &event.parse().unwrap());
+    }
+    assert_eq!(late_game, game);
+}
+
+#[test]
+fn moves_are_checked_by_the_server() {
+    let addr = start_server();
+    let mut black = Client::connect(addr);
+    let mut white = Client::connect(addr);
+    let mut watcher = Client::connect(addr);
+
+    let room = black.create_room(9, Seat::Black);
+    white.join(room, Seat::White);
+    black.expect_room_changed();
+    watcher.join(room, Seat::Spectator);
+    black.expect_room_changed();
+    white.expect_room_changed();
+
+    white.send(ClientMessage::Play { x: 0, y: 0 });
+    assert_eq!(white.expect_error(), "Not your turn");
+    watcher.send(ClientMessage::Pass);
+    assert_eq!(watcher.expect_error(), "Spectators can not play");
+    black.send(ClientMessage::Play { x: 9, y: 0 });
+    assert_eq!(black.expect_error(), "Not a point on the board");
+
+    black.send(ClientMessage::Play { x: 4, y: 4 });
+    black.expect_move();
+    white.expect_move();
+    white.send(ClientMessage::Play { x: 4, y: 4 });
+    assert_eq!(white.expect_error(), "There is already a stone there");
+
+    // Resigning is allowed out of turn, and ends the game
+    black.send(ClientMessage::Resign);
+    match white.recv() {
+        ServerMessage::Moved { result, .. } => assert_eq!(result.as_deref(), Some("W+R")),
+        other => panic!("Expected a move, got {other:?}"),
+    }
+    black.expect_move();
+    white.send(ClientMessage::Pass);
+    assert_eq!(white.expect_error(), "The game is over");
+}
+
+#[test]
+fn rooms_are_listed_and_seats_are_exclusive() {
+    let addr = start_server();
+    let mut first = Client::connect(addr);
+    let mut second = Client::connect(addr);
+
+    let room = first.create_room(13, Seat::White);
+    let other_room = second.create_room(19, Seat::Black);
+    assert_ne!(room, other_room);
+
+    second.send(ClientMessage::Join {
+        room,
+        seat: Seat::White,
+    });
+    assert_eq!(second.expect_error(), "White is already taken");
+
+    second.send(ClientMessage::ListRooms);
+    let ServerMessage::Rooms { rooms } = second.recv() else {
+        panic!("Expected rooms");
+    };
+    assert_eq!(rooms.len(), 2);
+    let listed = rooms.iter().find(|r| r.id == room).unwrap();
+    assert_eq!(listed.board_size, 13);
+    assert!(listed.white && !listed.black);
+
+    // The second room closes once its only player moves to the first
+    second.join(room, Seat::Black);
+    first.expect_room_changed();
+    second.send(ClientMessage::ListRooms);
+    let ServerMessage::Rooms { rooms } = second.recv() else {
+        panic!("Expected rooms");
+    };
+    assert_eq!(rooms.len(), 1);
+    assert!(rooms[0].white && rooms[0].black);
+
+    second.send(ClientMessage::CreateRoom {
+        name: "huge".to_string(),
+        board_size: 50,
+        seat: Seat::Black,
+    });
+    second.expect_error();
+}
+
+#[test]
+fn bad_json_is_an_error() {
+    let addr = start_server();
+    let mut client = Client::connect(addr);
+    client
+        .socket
+        .send(Message::Text("{\"type\": \"dance\"}".to_string()))
+        .unwrap();
+    assert!(client.expect_error().starts_with("Bad message"));
+}
+
+#[test]
+fn slow_handshakes_are_accepted() {
+    let addr = start_server();
+    let stream = TcpStream::connect(addr).unwrap();
+    // Much longer than the server waits for messages once connected
+    std::thread::sleep(Duration::from_millis(200));
+    let (socket, _) =
+        tungstenite::client(format!("ws://{addr}"), MaybeTlsStream::Plain(stream)).unwrap();
+    let mut client = Client { socket };
+    client.create_room(9, Seat::Black);
+}
+
+#[test]
+fn chat_and_comments_reach_the_room() {
+    let addr = start_server();
+    let mut black = Client::connect(addr);
+    let mut teacher = Client::connect(addr);
+
+    let room = black.create_room(9, Seat::Black);
+    teacher.join(room, Seat::Spectator);
+    black.expect_room_changed();
+
+    teacher.send(ClientMessage::Chat {
+        text: "Try the 3-3 point".to_string(),
+    });
+    for client in [&mut black, &mut teacher] {
+        assert_eq!(
+            client.recv(),
+            ServerMessage::Chat {
+                from: Seat::Spectator,
+                text: "Try the 3-3 point".to_string(),
+            }
+        );
+    }
+
+    black.send(ClientMessage::Play { x: 2, y: 2 });
+    black.expect_move();
+    teacher.expect_move();
+    teacher.send(ClientMessage::Comment {
+        text: "Good".to_string(),
+    });
+    let comment = NodeComment {
+        node: 1,
+        text: "Good".to_string(),
+    };
+    assert_eq!(black.recv(), ServerMessage::Commented(comment.clone()));
+    assert_eq!(teacher.recv(), ServerMessage::Commented(comment.clone()));
+
+    // Comments are part of the game sent to late joiners
+    let mut late = Client::connect(addr);
+    late.send(ClientMessage::Join {
+        room,
+        seat: Seat::White,
+    });
+    match late.recv() {
+        ServerMessage::Joined { comments, .. } => assert_eq!(comments, vec![comment]),
+        other => panic!("Expected to join, got {other:?}"),
+    }
+}